@@ -0,0 +1,148 @@
+//! Parsing of Ed25519 precompile instructions loaded from the instructions sysvar
+//!
+//! The precompile has already verified every signature by the time our
+//! instruction runs, so all that is left is to read back which public keys
+//! signed which messages. Only signatures whose key, signature and message
+//! live inside the precompile instruction itself are accepted, so offsets
+//! cannot point at data from another instruction.
+
+use anchor_lang::prelude::Pubkey;
+use anchor_lang::solana_program::ed25519_program;
+use anchor_lang::solana_program::instruction::Instruction;
+
+const SIGNATURE_OFFSETS_START: usize = 2;
+const SIGNATURE_OFFSETS_LEN: usize = 14;
+const PUBKEY_LEN: usize = 32;
+const SIGNATURE_LEN: usize = 64;
+
+pub struct SignedMessage<'a> {
+    pub signer: Pubkey,
+    pub message: &'a [u8],
+}
+
+/// Returns the (signer, message) pairs carried by an Ed25519 precompile
+/// instruction, or None if the instruction is not a well-formed precompile
+/// call with self-contained data
+pub fn signed_messages(ix: &Instruction) -> Option<Vec<SignedMessage<'_>>> {
+    if ix.program_id != ed25519_program::id() {
+        return None;
+    }
+
+    let data = &ix.data;
+    let count = *data.first()? as usize;
+    let mut messages = Vec::with_capacity(count);
+
+    for i in 0..count {
+        let start = SIGNATURE_OFFSETS_START + i * SIGNATURE_OFFSETS_LEN;
+        let offsets = data.get(start..start + SIGNATURE_OFFSETS_LEN)?;
+        let read = |at: usize| u16::from_le_bytes([offsets[at], offsets[at + 1]]);
+
+        let signature_offset = read(0) as usize;
+        let signature_ix = read(2);
+        let pubkey_offset = read(4) as usize;
+        let pubkey_ix = read(6);
+        let message_offset = read(8) as usize;
+        let message_size = read(10) as usize;
+        let message_ix = read(12);
+
+        if signature_ix != u16::MAX || pubkey_ix != u16::MAX || message_ix != u16::MAX {
+            return None;
+        }
+
+        data.get(signature_offset..signature_offset + SIGNATURE_LEN)?;
+        let pubkey = data.get(pubkey_offset..pubkey_offset + PUBKEY_LEN)?;
+        let message = data.get(message_offset..message_offset + message_size)?;
+
+        messages.push(SignedMessage {
+            signer: Pubkey::try_from(pubkey).ok()?,
+            message,
+        });
+    }
+
+    Some(messages)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Lays out a precompile instruction the way the Ed25519 program
+    /// expects: header, offset records, then (pubkey, signature, message)
+    /// for each entry. Signatures are zero; only the layout is under test.
+    fn precompile_data(entries: &[(Pubkey, &[u8])], instruction_index: u16) -> Vec<u8> {
+        let mut data = vec![entries.len() as u8, 0];
+        let mut payload = Vec::new();
+        let payload_start = SIGNATURE_OFFSETS_START + entries.len() * SIGNATURE_OFFSETS_LEN;
+
+        for (signer, message) in entries {
+            let pubkey_offset = payload_start + payload.len();
+            payload.extend_from_slice(signer.as_ref());
+            let signature_offset = payload_start + payload.len();
+            payload.extend_from_slice(&[0u8; SIGNATURE_LEN]);
+            let message_offset = payload_start + payload.len();
+            payload.extend_from_slice(message);
+
+            for value in [
+                signature_offset as u16,
+                instruction_index,
+                pubkey_offset as u16,
+                instruction_index,
+                message_offset as u16,
+                message.len() as u16,
+                instruction_index,
+            ] {
+                data.extend_from_slice(&value.to_le_bytes());
+            }
+        }
+        data.extend_from_slice(&payload);
+        data
+    }
+
+    fn precompile_ix(data: Vec<u8>) -> Instruction {
+        Instruction {
+            program_id: ed25519_program::id(),
+            accounts: vec![],
+            data,
+        }
+    }
+
+    #[test]
+    fn reads_every_signed_message() {
+        let (first, second) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let ix = precompile_ix(precompile_data(&[(first, b"one"), (second, b"second message")], u16::MAX));
+
+        let messages = signed_messages(&ix).unwrap();
+        assert_eq!(messages.len(), 2);
+        assert_eq!(messages[0].signer, first);
+        assert_eq!(messages[0].message, b"one");
+        assert_eq!(messages[1].signer, second);
+        assert_eq!(messages[1].message, b"second message");
+    }
+
+    #[test]
+    fn rejects_other_programs() {
+        let mut ix = precompile_ix(precompile_data(&[(Pubkey::new_unique(), b"msg")], u16::MAX));
+        ix.program_id = Pubkey::new_unique();
+        assert!(signed_messages(&ix).is_none());
+    }
+
+    #[test]
+    fn rejects_data_from_other_instructions() {
+        let ix = precompile_ix(precompile_data(&[(Pubkey::new_unique(), b"msg")], 0));
+        assert!(signed_messages(&ix).is_none());
+    }
+
+    #[test]
+    fn rejects_out_of_bounds_offsets() {
+        let mut data = precompile_data(&[(Pubkey::new_unique(), b"msg")], u16::MAX);
+        data.truncate(data.len() - 1);
+        assert!(signed_messages(&precompile_ix(data)).is_none());
+
+        // Claims two entries but carries one offset record
+        let mut data = precompile_data(&[(Pubkey::new_unique(), b"msg")], u16::MAX);
+        data[0] = 2;
+        assert!(signed_messages(&precompile_ix(data)).is_none());
+
+        assert!(signed_messages(&precompile_ix(vec![])).is_none());
+    }
+}
@@ -0,0 +1,233 @@
+//! Groth16 verifier over BN254 backed by Solana's alt_bn128 syscalls
+//!
+//! Points use the uncompressed big-endian encoding from EIP-197:
+//! G1 = x || y (64 bytes), G2 = x_c1 || x_c0 || y_c1 || y_c0 (128 bytes).
+//! A proof is A (G1) || B (G2) || C (G1), 256 bytes in total.
+
+use anchor_lang::solana_program::alt_bn128::prelude::{
+    alt_bn128_addition, alt_bn128_multiplication, alt_bn128_pairing,
+};
+
+pub const G1_LEN: usize = 64;
+pub const G2_LEN: usize = 128;
+pub const PROOF_LEN: usize = G1_LEN + G2_LEN + G1_LEN;
+
+/// Public inputs of the withdrawal circuit: commitment, nullifier, amount
+pub const NR_PUBLIC_INPUTS: usize = 3;
+
+// BN254 base field modulus p
+const FIELD_MODULUS: [u8; 32] = [
+    0x30, 0x64, 0x4e, 0x72, 0xe1, 0x31, 0xa0, 0x29, 0xb8, 0x50, 0x45, 0xb6, 0x81, 0x81, 0x58, 0x5d,
+    0x97, 0x81, 0x6a, 0x91, 0x68, 0x71, 0xca, 0x8d, 0x3c, 0x20, 0x8c, 0x16, 0xd8, 0x7c, 0xfd, 0x47,
+];
+
+// BN254 scalar field modulus r
+const SCALAR_MODULUS: [u8; 32] = [
+    0x30, 0x64, 0x4e, 0x72, 0xe1, 0x31, 0xa0, 0x29, 0xb8, 0x50, 0x45, 0xb6, 0x81, 0x81, 0x58, 0x5d,
+    0x28, 0x33, 0xe8, 0x48, 0x79, 0xb9, 0x70, 0x91, 0x43, 0xe1, 0xf5, 0x93, 0xf0, 0x00, 0x00, 0x01,
+];
+
+pub struct VerifyingKeyRef<'a> {
+    pub alpha_g1: &'a [u8; G1_LEN],
+    pub beta_g2: &'a [u8; G2_LEN],
+    pub gamma_g2: &'a [u8; G2_LEN],
+    pub delta_g2: &'a [u8; G2_LEN],
+    pub ic: &'a [[u8; G1_LEN]; NR_PUBLIC_INPUTS + 1],
+}
+
+/// Returns true if `value` is a canonical scalar field element
+pub fn is_scalar(value: &[u8; 32]) -> bool {
+    value < &SCALAR_MODULUS
+}
+
+/// Encodes a u64 as a big-endian scalar field element
+pub fn scalar_from_u64(value: u64) -> [u8; 32] {
+    let mut out = [0u8; 32];
+    out[24..].copy_from_slice(&value.to_be_bytes());
+    out
+}
+
+pub fn verify(
+    vk: &VerifyingKeyRef,
+    proof: &[u8; PROOF_LEN],
+    public_inputs: &[[u8; 32]; NR_PUBLIC_INPUTS],
+) -> bool {
+    if public_inputs.iter().any(|input| !is_scalar(input)) {
+        return false;
+    }
+
+    let proof_a = &proof[0..G1_LEN];
+    let proof_b = &proof[G1_LEN..G1_LEN + G2_LEN];
+    let proof_c = &proof[G1_LEN + G2_LEN..PROOF_LEN];
+
+    let neg_a = match negate_g1(proof_a) {
+        Some(point) => point,
+        None => return false,
+    };
+
+    // vk_x = ic[0] + sum(ic[i + 1] * input[i])
+    let mut vk_x = vk.ic[0].to_vec();
+    for (input, ic) in public_inputs.iter().zip(vk.ic[1..].iter()) {
+        let mut mul_input = [0u8; 96];
+        mul_input[..64].copy_from_slice(ic);
+        mul_input[64..].copy_from_slice(input);
+        let product = match alt_bn128_multiplication(&mul_input) {
+            Ok(point) => point,
+            Err(_) => return false,
+        };
+
+        let mut add_input = [0u8; 128];
+        add_input[..64].copy_from_slice(&vk_x);
+        add_input[64..].copy_from_slice(&product);
+        vk_x = match alt_bn128_addition(&add_input) {
+            Ok(point) => point,
+            Err(_) => return false,
+        };
+    }
+
+    // e(-A, B) * e(alpha, beta) * e(vk_x, gamma) * e(C, delta) == 1
+    let mut pairing_input = Vec::with_capacity(4 * (G1_LEN + G2_LEN));
+    pairing_input.extend_from_slice(&neg_a);
+    pairing_input.extend_from_slice(proof_b);
+    pairing_input.extend_from_slice(vk.alpha_g1);
+    pairing_input.extend_from_slice(vk.beta_g2);
+    pairing_input.extend_from_slice(&vk_x);
+    pairing_input.extend_from_slice(vk.gamma_g2);
+    pairing_input.extend_from_slice(proof_c);
+    pairing_input.extend_from_slice(vk.delta_g2);
+
+    match alt_bn128_pairing(&pairing_input) {
+        Ok(result) => result.len() == 32 && result[..31].iter().all(|&b| b == 0) && result[31] == 1,
+        Err(_) => false,
+    }
+}
+
+// Negates a G1 point by replacing y with p - y. The identity stays as is.
+fn negate_g1(point: &[u8]) -> Option<[u8; G1_LEN]> {
+    let mut out = [0u8; G1_LEN];
+    out[..32].copy_from_slice(&point[..32]);
+
+    let y: [u8; 32] = point[32..64].try_into().ok()?;
+    if y >= FIELD_MODULUS {
+        return None;
+    }
+    if y.iter().all(|&b| b == 0) {
+        return Some(out);
+    }
+
+    let mut borrow = 0u16;
+    for i in (0..32).rev() {
+        let lhs = FIELD_MODULUS[i] as u16;
+        let rhs = y[i] as u16 + borrow;
+        if lhs >= rhs {
+            out[32 + i] = (lhs - rhs) as u8;
+            borrow = 0;
+        } else {
+            out[32 + i] = (lhs + 256 - rhs) as u8;
+            borrow = 1;
+        }
+    }
+
+    Some(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FIXTURE: &str = include_str!("../../../tests/fixtures/groth16_withdrawal.json");
+
+    fn hex<const N: usize>(value: &serde_json::Value) -> [u8; N] {
+        let text = value.as_str().expect("hex string");
+        assert_eq!(text.len(), N * 2);
+        let mut out = [0u8; N];
+        for (i, byte) in out.iter_mut().enumerate() {
+            *byte = u8::from_str_radix(&text[i * 2..i * 2 + 2], 16).expect("hex digit");
+        }
+        out
+    }
+
+    struct Fixture {
+        alpha_g1: [u8; G1_LEN],
+        beta_g2: [u8; G2_LEN],
+        gamma_g2: [u8; G2_LEN],
+        delta_g2: [u8; G2_LEN],
+        ic: [[u8; G1_LEN]; NR_PUBLIC_INPUTS + 1],
+        proof: [u8; PROOF_LEN],
+        public_inputs: [[u8; 32]; NR_PUBLIC_INPUTS],
+    }
+
+    impl Fixture {
+        fn load() -> Self {
+            let json: serde_json::Value = serde_json::from_str(FIXTURE).unwrap();
+            let vk = &json["verifying_key"];
+            let ticket = &json["withdrawal_ticket"];
+            let ic = vk["ic"].as_array().unwrap();
+            assert_eq!(ic.len(), NR_PUBLIC_INPUTS + 1);
+            Fixture {
+                alpha_g1: hex(&vk["alpha_g1"]),
+                beta_g2: hex(&vk["beta_g2"]),
+                gamma_g2: hex(&vk["gamma_g2"]),
+                delta_g2: hex(&vk["delta_g2"]),
+                ic: [hex(&ic[0]), hex(&ic[1]), hex(&ic[2]), hex(&ic[3])],
+                proof: hex(&json["proof_data"]),
+                public_inputs: [
+                    hex(&ticket["partial_note_commitment"]),
+                    hex(&ticket["partial_note_nullifier"]),
+                    scalar_from_u64(ticket["amount"].as_u64().unwrap()),
+                ],
+            }
+        }
+
+        fn verify(&self, proof: &[u8; PROOF_LEN], public_inputs: &[[u8; 32]; NR_PUBLIC_INPUTS]) -> bool {
+            let vk = VerifyingKeyRef {
+                alpha_g1: &self.alpha_g1,
+                beta_g2: &self.beta_g2,
+                gamma_g2: &self.gamma_g2,
+                delta_g2: &self.delta_g2,
+                ic: &self.ic,
+            };
+            verify(&vk, proof, public_inputs)
+        }
+    }
+
+    #[test]
+    fn fixture_proof_verifies() {
+        let fixture = Fixture::load();
+        assert!(fixture.verify(&fixture.proof, &fixture.public_inputs));
+    }
+
+    #[test]
+    fn tampered_amount_fails() {
+        let fixture = Fixture::load();
+        let mut inputs = fixture.public_inputs;
+        inputs[2] = scalar_from_u64(u64::from_be_bytes(inputs[2][24..].try_into().unwrap()) + 1);
+        assert!(!fixture.verify(&fixture.proof, &inputs));
+    }
+
+    #[test]
+    fn tampered_proof_fails() {
+        let fixture = Fixture::load();
+        let mut proof = fixture.proof;
+        proof[PROOF_LEN - 1] ^= 1;
+        assert!(!fixture.verify(&proof, &fixture.public_inputs));
+    }
+
+    #[test]
+    fn rejects_non_canonical_scalar() {
+        let fixture = Fixture::load();
+        let mut inputs = fixture.public_inputs;
+        inputs[0] = SCALAR_MODULUS;
+        assert!(!is_scalar(&inputs[0]));
+        assert!(!fixture.verify(&fixture.proof, &inputs));
+    }
+
+    #[test]
+    fn negate_g1_round_trips() {
+        let fixture = Fixture::load();
+        let point = &fixture.alpha_g1[..];
+        let negated = negate_g1(point).unwrap();
+        assert_ne!(&negated[..], point);
+        assert_eq!(&negate_g1(&negated).unwrap()[..], point);
+    }
+}
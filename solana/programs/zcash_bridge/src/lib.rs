@@ -12,8 +12,22 @@
 //! 5. Set VITE_ZCASH_BRIDGE_PROGRAM_ID in your .env file
 
 use anchor_lang::prelude::*;
-use anchor_spl::token::{self, Token, TokenAccount, Mint, Transfer};
-use anchor_spl::associated_token::AssociatedToken;
+use anchor_lang::Discriminator;
+use anchor_lang::system_program;
+use anchor_lang::solana_program::sysvar::instructions::{
+    load_current_index_checked, load_instruction_at_checked,
+};
+use anchor_lang::solana_program::program_option::COption;
+use anchor_spl::token_2022::spl_token_2022;
+use anchor_spl::token_interface::{
+    self, Burn, CloseAccount, Mint, MintTo, TokenAccount, TokenInterface, TransferChecked,
+};
+
+pub mod ed25519;
+pub mod groth16;
+pub mod zcash_address;
+pub mod zcash_header;
+pub mod zcash_tx;
 
 // IMPORTANT: This program ID is auto-generated by Anchor on first build.
 // After running `anchor build`, get the actual program ID with `anchor keys list`
@@ -28,24 +42,51 @@ pub mod zcash_bridge {
         ctx: Context<Initialize>,
         config: BridgeConfig,
     ) -> Result<()> {
+        require!(config.deposit_timeout > 0, BridgeError::InvalidConfig);
+        
+        // Wrapped ZEC is always the first registered asset
+        let params = AssetParams {
+            min_deposit: config.min_deposit,
+            max_deposit: config.max_deposit,
+            protocol_fee_bps: config.protocol_fee_bps,
+            custody_mode: config.custody_mode,
+            supply_cap: config.supply_cap,
+            custody_address: config.custody_address,
+        };
+        ctx.accounts.asset_config.init(
+            &ctx.accounts.wrapped_zec_mint,
+            &ctx.accounts.bridge_state.key(),
+            config.zcash_network,
+            ctx.accounts.vault.key(),
+            &params,
+            ctx.bumps.asset_config,
+        )?;
+        
         let bridge = &mut ctx.accounts.bridge_state;
         bridge.authority = ctx.accounts.authority.key();
-        bridge.operator = config.operator;
         bridge.wrapped_zec_mint = ctx.accounts.wrapped_zec_mint.key();
-        bridge.vault = ctx.accounts.vault.key();
-        bridge.deposit_nonce = 0;
         bridge.withdrawal_nonce = 0;
-        bridge.total_deposited = 0;
-        bridge.total_withdrawn = 0;
         bridge.is_paused = false;
-        bridge.min_deposit = config.min_deposit;
-        bridge.max_deposit = config.max_deposit;
-        bridge.protocol_fee_bps = config.protocol_fee_bps;
         bridge.bump = ctx.bumps.bridge_state;
+        bridge.deposit_timeout = config.deposit_timeout;
+        bridge.pending_authority = Pubkey::default();
+        bridge.guardian = Pubkey::default();
+        bridge.zcash_network = config.zcash_network;
+        bridge.challenge_period = 0;
+        bridge.watcher_bond = 0;
+        bridge.min_operator_bond = 0;
+        bridge.unbonding_delay = 0;
+        
+        // The first operator still has to bond before it can attest
+        let operator_set = &mut ctx.accounts.operator_set;
+        operator_set.operators = vec![config.operator];
+        operator_set.threshold = 1;
+        operator_set.epoch = 0;
+        operator_set.bump = ctx.bumps.operator_set;
         
         emit!(BridgeInitialized {
             authority: bridge.authority,
-            operator: bridge.operator,
+            operator: config.operator,
             wrapped_zec_mint: bridge.wrapped_zec_mint,
             timestamp: Clock::get()?.unix_timestamp,
         });
@@ -53,6 +94,39 @@ pub mod zcash_bridge {
         Ok(())
     }
 
+    /// Lists another mint for bridging with its own vault, limits and fee
+    pub fn register_asset(
+        ctx: Context<RegisterAsset>,
+        params: AssetParams,
+    ) -> Result<()> {
+        require!(
+            ctx.accounts.authority.key() == ctx.accounts.bridge_state.authority,
+            BridgeError::Unauthorized
+        );
+        
+        ctx.accounts.asset_config.init(
+            &ctx.accounts.mint,
+            &ctx.accounts.bridge_state.key(),
+            ctx.accounts.bridge_state.zcash_network,
+            ctx.accounts.vault.key(),
+            &params,
+            ctx.bumps.asset_config,
+        )?;
+        
+        emit!(AssetRegistered {
+            mint: ctx.accounts.mint.key(),
+            vault: ctx.accounts.vault.key(),
+            min_deposit: params.min_deposit,
+            max_deposit: params.max_deposit,
+            protocol_fee_bps: params.protocol_fee_bps,
+            custody_mode: params.custody_mode,
+            supply_cap: params.supply_cap,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+        
+        Ok(())
+    }
+
     pub fn register_stealth_meta_address(
         ctx: Context<RegisterStealthMetaAddress>,
         spend_pub_key: [u8; 33],
@@ -79,88 +153,520 @@ pub mod zcash_bridge {
     pub fn initiate_deposit(
         ctx: Context<InitiateDeposit>,
         amount: u64,
-        zcash_shielded_address: [u8; 78],
+        zcash_shielded_address: String,
         memo: [u8; 64],
     ) -> Result<()> {
         let bridge = &ctx.accounts.bridge_state;
+        let asset = &ctx.accounts.asset_config;
         
         require!(!bridge.is_paused, BridgeError::BridgePaused);
-        require!(amount >= bridge.min_deposit, BridgeError::DepositTooSmall);
-        require!(amount <= bridge.max_deposit, BridgeError::DepositTooLarge);
+        require!(amount >= asset.min_deposit, BridgeError::DepositTooSmall);
+        require!(amount <= asset.max_deposit, BridgeError::DepositTooLarge);
+        
+        // Validate the destination before any funds are taken
+        require!(
+            zcash_shielded_address.len() <= MAX_ZCASH_ADDRESS_LEN,
+            BridgeError::InvalidZcashAddress
+        );
+        let decoded = zcash_address::decode(&zcash_shielded_address)
+            .ok_or(BridgeError::InvalidZcashAddress)?;
+        require!(decoded.network == bridge.zcash_network, BridgeError::ZcashNetworkMismatch);
+        // A mint-and-burn deposit is ZEC sent to the bridge on Zcash, which
+        // is what the deposit proof must then show arriving
+        if asset.custody_mode == CustodyMode::MintBurn {
+            require!(decoded.receiver == asset.custody_receiver, BridgeError::NotCustodyAddress);
+        }
         
-        let fee = (amount as u128)
-            .checked_mul(bridge.protocol_fee_bps as u128)
+        let received = match asset.custody_mode {
+            CustodyMode::Vault => amount.checked_sub(transfer_fee(&ctx.accounts.mint, amount)?).unwrap(),
+            CustodyMode::MintBurn => amount,
+        };
+        let fee = (received as u128)
+            .checked_mul(asset.fee_bps_at(Clock::get()?.unix_timestamp) as u128)
             .unwrap()
             .checked_div(10000)
             .unwrap() as u64;
-        let net_amount = amount.checked_sub(fee).unwrap();
+        let net_amount = received.checked_sub(fee).unwrap();
         
-        token::transfer(
-            CpiContext::new(
-                ctx.accounts.token_program.to_account_info(),
-                Transfer {
-                    from: ctx.accounts.user_token_account.to_account_info(),
-                    to: ctx.accounts.vault.to_account_info(),
-                    authority: ctx.accounts.user.to_account_info(),
-                },
-            ),
-            amount,
-        )?;
+        // In mint-and-burn mode the wrapped tokens are minted on confirmation
+        if asset.custody_mode == CustodyMode::Vault {
+            token_interface::transfer_checked(
+                CpiContext::new(
+                    ctx.accounts.token_program.to_account_info(),
+                    TransferChecked {
+                        from: ctx.accounts.user_token_account.to_account_info(),
+                        mint: ctx.accounts.mint.to_account_info(),
+                        to: ctx.accounts.vault.to_account_info(),
+                        authority: ctx.accounts.user.to_account_info(),
+                    },
+                ),
+                amount,
+                ctx.accounts.mint.decimals,
+            )?;
+        }
         
+        let counter = &mut ctx.accounts.user_deposit_counter;
         let deposit = &mut ctx.accounts.deposit_ticket;
-        let bridge_mut = &mut ctx.accounts.bridge_state;
         
-        deposit.ticket_id = bridge_mut.deposit_nonce;
+        deposit.ticket_id = counter.next_nonce;
         deposit.depositor = ctx.accounts.user.key();
+        deposit.mint = ctx.accounts.asset_config.mint;
         deposit.amount = net_amount;
         deposit.fee = fee;
         deposit.zcash_shielded_address = zcash_shielded_address;
+        deposit.zcash_pool = decoded.pool;
+        deposit.zcash_receiver = decoded.receiver;
         deposit.memo = memo;
         deposit.status = DepositStatus::Pending;
         deposit.created_at = Clock::get()?.unix_timestamp;
         deposit.processed_at = 0;
         deposit.zcash_tx_id = [0u8; 32];
         deposit.bump = ctx.bumps.deposit_ticket;
+        deposit.expires_at = deposit.created_at.checked_add(bridge.deposit_timeout).unwrap();
+        deposit.attestations = Attestations::default();
+        deposit.finalizes_at = 0;
+        deposit.challenger = Pubkey::default();
+        deposit.delivery_note_commitment = [0u8; 32];
+        deposit.claimed_by = Pubkey::default();
+        deposit.lease_expires_at = 0;
         
-        bridge_mut.deposit_nonce = bridge_mut.deposit_nonce.checked_add(1).unwrap();
-        bridge_mut.total_deposited = bridge_mut.total_deposited.checked_add(net_amount).unwrap();
+        let shard = &mut ctx.accounts.deposit_shard;
+        shard.mint = deposit.mint;
+        shard.index = deposit_shard_index(&deposit.depositor);
+        shard.bump = ctx.bumps.deposit_shard;
+        shard.hold_escrow(asset, deposit);
+        
+        counter.depositor = deposit.depositor;
+        counter.next_nonce = counter.next_nonce.checked_add(1).unwrap();
+        counter.bump = ctx.bumps.user_deposit_counter;
         
         emit!(DepositInitiated {
             ticket_id: deposit.ticket_id,
             depositor: deposit.depositor,
             amount: deposit.amount,
-            zcash_address_hash: hash_zcash_address(&zcash_shielded_address),
+            zcash_address_hash: hash_zcash_receiver(&decoded.receiver),
             timestamp: deposit.created_at,
         });
         
         Ok(())
     }
 
+    /// Takes a lease on a deposit before paying it out on Zcash, so the
+    /// depositor cannot cancel it from under the operator. Another operator
+    /// may take over the ticket once the current lease has lapsed, starting
+    /// the attestations over.
+    pub fn claim_deposit(ctx: Context<ClaimDeposit>) -> Result<()> {
+        let deposit = &mut ctx.accounts.deposit_ticket;
+        let operator = ctx.accounts.operator.key();
+        let now = Clock::get()?.unix_timestamp;
+        
+        require!(!ctx.accounts.bridge_state.is_paused, BridgeError::BridgePaused);
+        require!(ctx.accounts.operator_set.contains(&operator), BridgeError::Unauthorized);
+        require!(
+            ctx.accounts.operator_bond.amount >= ctx.accounts.bridge_state.min_operator_bond,
+            BridgeError::InsufficientOperatorBond
+        );
+        match deposit.status {
+            DepositStatus::Pending => {}
+            DepositStatus::Processing => {
+                require!(now >= deposit.lease_expires_at, BridgeError::LeaseActive);
+                // Votes gathered under the lapsed lease do not carry over
+                deposit.attestations = Attestations::default();
+            }
+            _ => return err!(BridgeError::InvalidDepositStatus),
+        }
+        
+        let previous_operator = deposit.claimed_by;
+        deposit.status = DepositStatus::Processing;
+        deposit.claimed_by = operator;
+        deposit.lease_expires_at = now.checked_add(DEPOSIT_LEASE_PERIOD).unwrap();
+        
+        emit!(DepositClaimed {
+            ticket_id: deposit.ticket_id,
+            depositor: deposit.depositor,
+            operator,
+            previous_operator,
+            lease_expires_at: deposit.lease_expires_at,
+            timestamp: now,
+        });
+        
+        Ok(())
+    }
+
+    /// Records an operator's confirmation of a leased deposit. Mint-and-burn
+    /// deposits must carry `funding`, opening the note that paid the deposit
+    /// into the asset's custody address; vault deposits carry none. The
+    /// Zcash transaction is bound to the deposit once the threshold is
+    /// reached, so it cannot confirm another one.
     pub fn confirm_deposit(
         ctx: Context<ConfirmDeposit>,
         zcash_tx_id: [u8; 32],
         proof: DepositProof,
+        inclusion: ZcashTxInclusion,
+        funding: Option<DeliveryProof>,
     ) -> Result<()> {
+        require!(!ctx.accounts.bridge_state.is_paused, BridgeError::BridgePaused);
         let deposit = &mut ctx.accounts.deposit_ticket;
+        let count = attest_deposit(
+            deposit,
+            &ctx.accounts.asset_config,
+            &ctx.accounts.operator_set,
+            &ctx.accounts.light_client,
+            &ctx.accounts.operator_bond,
+            ctx.accounts.bridge_state.min_operator_bond,
+            &zcash_tx_id,
+            &proof,
+            &inclusion,
+            &funding,
+        )?;
+        
+        if count < ctx.accounts.operator_set.threshold {
+            return Ok(());
+        }
         
-        require!(deposit.status == DepositStatus::Pending, BridgeError::InvalidDepositStatus);
-        require!(ctx.accounts.operator.key() == ctx.accounts.bridge_state.operator, BridgeError::Unauthorized);
+        consume_zcash_tx(
+            &mut ctx.accounts.zcash_tx_record,
+            &zcash_tx_id,
+            &deposit.key(),
+            ctx.bumps.zcash_tx_record,
+        )?;
+        
+        if ctx.accounts.bridge_state.challenge_period > 0 {
+            return start_deposit_challenge_period(&ctx.accounts.bridge_state, deposit, zcash_tx_id);
+        }
+        
+        mark_deposit_confirmed(
+            &mut ctx.accounts.asset_config,
+            &mut ctx.accounts.deposit_shard,
+            deposit,
+            zcash_tx_id,
+        )?;
+        
+        mint_confirmed_deposit(
+            &mut ctx.accounts.asset_config,
+            &mut ctx.accounts.deposit_shard,
+            &mut ctx.accounts.commitment_tree,
+            deposit,
+            &ctx.accounts.bridge_state,
+            &ctx.accounts.mint,
+            &ctx.accounts.vault,
+            &ctx.accounts.depositor_token_account,
+            &ctx.accounts.token_program,
+        )
+    }
+
+    /// Confirms a deposit from operator signatures collected off-chain.
+    /// The instruction right before this one must be an Ed25519 precompile
+    /// call carrying one signature per operator over `deposit_signing_digest`,
+    /// and `remaining_accounts` must hold each signer's bond in the same order.
+    /// `funding` follows `confirm_deposit`.
+    pub fn confirm_deposit_with_signatures<'info>(
+        ctx: Context<'_, '_, 'info, 'info, ConfirmDepositWithSignatures<'info>>,
+        zcash_tx_id: [u8; 32],
+        proof: DepositProof,
+        inclusion: ZcashTxInclusion,
+        funding: Option<DeliveryProof>,
+    ) -> Result<()> {
+        require!(!ctx.accounts.bridge_state.is_paused, BridgeError::BridgePaused);
+        let deposit = &mut ctx.accounts.deposit_ticket;
+        let operator_set = &ctx.accounts.operator_set;
         
+        require!(deposit.status == DepositStatus::Processing, BridgeError::InvalidDepositStatus);
+        require!(
+            Clock::get()?.unix_timestamp < deposit.lease_expires_at,
+            BridgeError::LeaseExpired
+        );
         require!(verify_deposit_proof(&proof, deposit), BridgeError::InvalidProof);
+        verify_deposit_funding(&ctx.accounts.asset_config, deposit, &zcash_tx_id, &funding)?;
+        ctx.accounts.light_client.verify_inclusion(&zcash_tx_id, &inclusion)?;
         
-        deposit.status = DepositStatus::Confirmed;
+        let instructions = ctx.accounts.instructions.to_account_info();
+        let current_index = load_current_index_checked(&instructions)?;
+        require!(current_index > 0, BridgeError::InvalidSignatureInstruction);
+        let ed25519_ix = load_instruction_at_checked(current_index as usize - 1, &instructions)?;
+        let signed = ed25519::signed_messages(&ed25519_ix)
+            .ok_or(BridgeError::InvalidSignatureInstruction)?;
+        
+        require!(ctx.remaining_accounts.len() == signed.len(), BridgeError::InvalidSignatureInstruction);
+        
+        let digest = deposit_signing_digest(
+            &deposit.depositor,
+            deposit.ticket_id,
+            &zcash_tx_id,
+            deposit.amount,
+            &funding,
+        );
+        let mut attestations = Attestations {
+            epoch: operator_set.epoch,
+            ..Attestations::default()
+        };
+        for (entry, info) in signed.iter().zip(ctx.remaining_accounts) {
+            require!(entry.message == digest.as_slice(), BridgeError::InvalidSignatureInstruction);
+            let bond = load_operator_bond(info, ctx.program_id)?;
+            require!(bond.operator == entry.signer, BridgeError::InvalidSignatureInstruction);
+            attestations.record(operator_set, &bond, ctx.accounts.bridge_state.min_operator_bond, digest)?;
+        }
+        
+        require!(attestations.count(&digest) >= operator_set.threshold, BridgeError::InsufficientSignatures);
+        require!(
+            attestations.has_attested(operator_set, &deposit.claimed_by, &digest),
+            BridgeError::LeaseHolderNotAttested
+        );
+        deposit.attestations = attestations;
+        
+        consume_zcash_tx(
+            &mut ctx.accounts.zcash_tx_record,
+            &zcash_tx_id,
+            &deposit.key(),
+            ctx.bumps.zcash_tx_record,
+        )?;
+        
+        if ctx.accounts.bridge_state.challenge_period > 0 {
+            return start_deposit_challenge_period(&ctx.accounts.bridge_state, deposit, zcash_tx_id);
+        }
+        
+        mark_deposit_confirmed(
+            &mut ctx.accounts.asset_config,
+            &mut ctx.accounts.deposit_shard,
+            deposit,
+            zcash_tx_id,
+        )?;
+        
+        mint_confirmed_deposit(
+            &mut ctx.accounts.asset_config,
+            &mut ctx.accounts.deposit_shard,
+            &mut ctx.accounts.commitment_tree,
+            deposit,
+            &ctx.accounts.bridge_state,
+            &ctx.accounts.mint,
+            &ctx.accounts.vault,
+            &ctx.accounts.depositor_token_account,
+            &ctx.accounts.token_program,
+        )
+    }
+
+    /// Records an operator's attestation that a confirmed vault deposit's
+    /// shielded note reached the destination address. The note must be an
+    /// output of the payout transaction, which must still be on the relayed
+    /// chain. The deposit completes, and only then counts towards the
+    /// asset's deposit totals, once the threshold of operators agrees.
+    /// Mint-and-burn deposits complete when they are minted instead.
+    pub fn complete_deposit(
+        ctx: Context<CompleteDeposit>,
+        delivery: DeliveryProof,
+        inclusion: ZcashTxInclusion,
+    ) -> Result<()> {
+        let deposit = &mut ctx.accounts.deposit_ticket;
+        let operator_set = &ctx.accounts.operator_set;
+        
+        require!(!ctx.accounts.bridge_state.is_paused, BridgeError::BridgePaused);
+        require!(deposit.status == DepositStatus::Confirmed, BridgeError::InvalidDepositStatus);
+        require!(
+            delivery.zcash_tx_id == deposit.zcash_tx_id
+                && delivery.receiver == deposit.zcash_receiver
+                && delivery.value >= deposit.amount
+                && delivery.note_commitment != [0u8; 32],
+            BridgeError::DeliveryMismatch
+        );
+        verify_note_in_transaction(&delivery, deposit.zcash_pool)?;
+        ctx.accounts.light_client.verify_inclusion(&deposit.zcash_tx_id, &inclusion)?;
+        
+        let count = deposit.attestations.record(
+            operator_set,
+            &ctx.accounts.operator_bond,
+            ctx.accounts.bridge_state.min_operator_bond,
+            hash_delivery_attestation(&delivery),
+        )?;
+        
+        emit!(DepositAttested {
+            ticket_id: deposit.ticket_id,
+            depositor: deposit.depositor,
+            operator: ctx.accounts.operator.key(),
+            attestations: count,
+            threshold: operator_set.threshold,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+        
+        if count < operator_set.threshold {
+            return Ok(());
+        }
+        
+        record_deposit_completion(
+            &mut ctx.accounts.deposit_shard,
+            &mut ctx.accounts.commitment_tree,
+            deposit,
+            delivery.note_commitment,
+            delivery.transcript_hash,
+        )
+    }
+
+    /// Records an operator's vote to fail a pending or claimed deposit. The
+    /// deposit is failed and refunded once the threshold of operators agrees.
+    pub fn fail_deposit(ctx: Context<FailDeposit>) -> Result<()> {
+        let ticket = ctx.accounts.deposit_ticket.key();
+        let deposit = &mut ctx.accounts.deposit_ticket;
+        let bridge = &ctx.accounts.bridge_state;
+        let operator_set = &ctx.accounts.operator_set;
+        let operator = ctx.accounts.operator.key();
+        
+        require!(
+            matches!(deposit.status, DepositStatus::Pending | DepositStatus::Processing),
+            BridgeError::InvalidDepositStatus
+        );
+        
+        let count = deposit.attestations.record(
+            operator_set,
+            &ctx.accounts.operator_bond,
+            bridge.min_operator_bond,
+            hash_failure_attestation(&ticket),
+        )?;
+        
+        emit!(DepositAttested {
+            ticket_id: deposit.ticket_id,
+            depositor: deposit.depositor,
+            operator,
+            attestations: count,
+            threshold: operator_set.threshold,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+        
+        if count < operator_set.threshold {
+            return Ok(());
+        }
+        
+        let (refund, _) = refund_deposit_escrow(
+            &ctx.accounts.asset_config,
+            &mut ctx.accounts.deposit_shard,
+            deposit,
+            false,
+            &ctx.accounts.bridge_state,
+            &ctx.accounts.mint,
+            &ctx.accounts.vault,
+            &ctx.accounts.depositor_token_account,
+            &ctx.accounts.token_program,
+        )?;
+        
+        deposit.status = DepositStatus::Failed;
         deposit.processed_at = Clock::get()?.unix_timestamp;
-        deposit.zcash_tx_id = zcash_tx_id;
         
-        emit!(DepositConfirmed {
+        emit!(DepositFailed {
             ticket_id: deposit.ticket_id,
-            zcash_tx_id,
+            depositor: deposit.depositor,
+            refunded: refund,
             timestamp: deposit.processed_at,
         });
         
         Ok(())
     }
 
+    pub fn refund_deposit(ctx: Context<RefundDeposit>) -> Result<()> {
+        let deposit = &mut ctx.accounts.deposit_ticket;
+        let now = Clock::get()?.unix_timestamp;
+        
+        deposit.require_unclaimed(now)?;
+        require!(now >= deposit.expires_at, BridgeError::DepositNotExpired);
+        
+        let (refund, _) = refund_deposit_escrow(
+            &ctx.accounts.asset_config,
+            &mut ctx.accounts.deposit_shard,
+            deposit,
+            false,
+            &ctx.accounts.bridge_state,
+            &ctx.accounts.mint,
+            &ctx.accounts.vault,
+            &ctx.accounts.depositor_token_account,
+            &ctx.accounts.token_program,
+        )?;
+        
+        deposit.status = DepositStatus::Failed;
+        deposit.processed_at = now;
+        
+        emit!(DepositRefunded {
+            ticket_id: deposit.ticket_id,
+            depositor: deposit.depositor,
+            refunded: refund,
+            caller: ctx.accounts.caller.key(),
+            timestamp: now,
+        });
+        
+        Ok(())
+    }
+
+    /// Lets the depositor back out of a deposit no operator holds a lease on
+    /// and no operator of the current set has attested. The escrowed amount
+    /// is refunded and the protocol fee is retained.
+    pub fn cancel_deposit(ctx: Context<CancelDeposit>) -> Result<()> {
+        let deposit = &mut ctx.accounts.deposit_ticket;
+        let now = Clock::get()?.unix_timestamp;
+        
+        deposit.require_unclaimed(now)?;
+        require!(
+            !deposit.attestations.is_current(&ctx.accounts.operator_set),
+            BridgeError::TicketAlreadyAttested
+        );
+        require!(
+            now >= deposit.created_at.checked_add(CANCEL_LOCK_PERIOD).unwrap(),
+            BridgeError::CancelLockActive
+        );
+        
+        let (refund, fee_retained) = refund_deposit_escrow(
+            &ctx.accounts.asset_config,
+            &mut ctx.accounts.deposit_shard,
+            deposit,
+            true,
+            &ctx.accounts.bridge_state,
+            &ctx.accounts.mint,
+            &ctx.accounts.vault,
+            &ctx.accounts.depositor_token_account,
+            &ctx.accounts.token_program,
+        )?;
+        
+        deposit.status = DepositStatus::Cancelled;
+        deposit.processed_at = now;
+        
+        let asset_mut = &mut ctx.accounts.asset_config;
+        asset_mut.accrued_fees = asset_mut.accrued_fees.checked_add(fee_retained).unwrap();
+        
+        emit!(DepositCancelled {
+            ticket_id: deposit.ticket_id,
+            depositor: deposit.depositor,
+            refunded: refund,
+            fee_retained,
+            timestamp: now,
+        });
+        
+        Ok(())
+    }
+
+    /// Closes a settled deposit ticket and returns its rent to the depositor.
+    /// The closing event carries a hash of the final ticket for auditors.
+    pub fn close_deposit_ticket(ctx: Context<CloseDepositTicket>) -> Result<()> {
+        let deposit = &ctx.accounts.deposit_ticket;
+        
+        require!(
+            matches!(
+                deposit.status,
+                DepositStatus::Completed | DepositStatus::Failed | DepositStatus::Cancelled
+            ),
+            BridgeError::TicketNotSettled
+        );
+        
+        emit!(DepositTicketClosed {
+            ticket: deposit.key(),
+            ticket_id: deposit.ticket_id,
+            depositor: deposit.depositor,
+            mint: deposit.mint,
+            amount: deposit.amount,
+            fee: deposit.fee,
+            status: deposit.status,
+            zcash_tx_id: deposit.zcash_tx_id,
+            record_hash: hash_closed_record(&***deposit)?,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+        
+        Ok(())
+    }
+
     pub fn initiate_withdrawal(
         ctx: Context<InitiateWithdrawal>,
         amount: u64,
@@ -169,33 +675,76 @@ pub mod zcash_bridge {
         encrypted_value: [u8; 32],
     ) -> Result<()> {
         let bridge = &ctx.accounts.bridge_state;
+        let asset = &ctx.accounts.asset_config;
         
         require!(!bridge.is_paused, BridgeError::BridgePaused);
+        // Both become public inputs of the withdrawal proof, which only
+        // takes canonical scalars
+        require!(
+            groth16::is_scalar(&partial_note_commitment) && groth16::is_scalar(&partial_note_nullifier),
+            BridgeError::InvalidPublicInput
+        );
         
-        let fee = (amount as u128)
-            .checked_mul(bridge.protocol_fee_bps as u128)
+        let received = amount.checked_sub(transfer_fee(&ctx.accounts.mint, amount)?).unwrap();
+        let fee = (received as u128)
+            .checked_mul(asset.fee_bps_at(Clock::get()?.unix_timestamp) as u128)
             .unwrap()
             .checked_div(10000)
             .unwrap() as u64;
-        let net_amount = amount.checked_sub(fee).unwrap();
+        let net_amount = received.checked_sub(fee).unwrap();
+        // A withdrawal larger than a whole window could never be released
+        require!(asset.outflow_limiter.admits(net_amount), BridgeError::OutflowLimitExceeded);
+        
+        token_interface::transfer_checked(
+            CpiContext::new(
+                ctx.accounts.token_program.to_account_info(),
+                TransferChecked {
+                    from: ctx.accounts.user_token_account.to_account_info(),
+                    mint: ctx.accounts.mint.to_account_info(),
+                    to: ctx.accounts.vault.to_account_info(),
+                    authority: ctx.accounts.user.to_account_info(),
+                },
+            ),
+            amount,
+            ctx.accounts.mint.decimals,
+        )?;
+        
+        let nullifier_record = &mut ctx.accounts.nullifier_record;
+        require!(
+            nullifier_record.withdrawal_ticket == Pubkey::default() && !nullifier_record.is_spent,
+            BridgeError::NullifierAlreadySpent
+        );
+        nullifier_record.nullifier = partial_note_nullifier;
+        nullifier_record.withdrawal_ticket = ctx.accounts.withdrawal_ticket.key();
+        nullifier_record.created_at = Clock::get()?.unix_timestamp;
+        nullifier_record.bump = ctx.bumps.nullifier_record;
         
         let withdrawal = &mut ctx.accounts.withdrawal_ticket;
         let bridge_mut = &mut ctx.accounts.bridge_state;
         
         withdrawal.ticket_id = bridge_mut.withdrawal_nonce;
         withdrawal.recipient = ctx.accounts.user.key();
+        withdrawal.mint = ctx.accounts.asset_config.mint;
         withdrawal.amount = net_amount;
         withdrawal.fee = fee;
         withdrawal.partial_note_commitment = partial_note_commitment;
         withdrawal.partial_note_nullifier = partial_note_nullifier;
         withdrawal.encrypted_value = encrypted_value;
         withdrawal.status = WithdrawalStatus::Pending;
+        withdrawal.claimed_by = Pubkey::default();
+        withdrawal.lease_expires_at = 0;
         withdrawal.created_at = Clock::get()?.unix_timestamp;
         withdrawal.processed_at = 0;
         withdrawal.bump = ctx.bumps.withdrawal_ticket;
+        withdrawal.attestations = Attestations::default();
+        withdrawal.finalizes_at = 0;
+        withdrawal.challenger = Pubkey::default();
         
         bridge_mut.withdrawal_nonce = bridge_mut.withdrawal_nonce.checked_add(1).unwrap();
         
+        let asset_mut = &mut ctx.accounts.asset_config;
+        asset_mut.pending_withdrawals = asset_mut.pending_withdrawals.checked_add(received).unwrap();
+        
         emit!(WithdrawalInitiated {
             ticket_id: withdrawal.ticket_id,
             recipient: withdrawal.recipient,
@@ -207,746 +756,6375 @@ pub mod zcash_bridge {
         Ok(())
     }
 
-    pub fn process_withdrawal(
-        ctx: Context<ProcessWithdrawal>,
-        proof: WithdrawalProof,
-    ) -> Result<()> {
+    /// Takes a lease on a withdrawal before paying it out on Zcash. Another
+    /// operator may take over the ticket once the current lease has lapsed,
+    /// starting the attestations over.
+    pub fn claim_withdrawal(ctx: Context<ClaimWithdrawal>) -> Result<()> {
         let withdrawal = &mut ctx.accounts.withdrawal_ticket;
-        let bridge = &ctx.accounts.bridge_state;
-        
-        require!(withdrawal.status == WithdrawalStatus::Pending, BridgeError::InvalidWithdrawalStatus);
-        require!(ctx.accounts.operator.key() == bridge.operator, BridgeError::Unauthorized);
-        
-        require!(verify_withdrawal_proof(&proof, withdrawal), BridgeError::InvalidProof);
-        
-        let bump = bridge.bump;
-        let seeds: &[&[u8]] = &[
-            b"bridge",
-            &[bump],
-        ];
-        let signer_seeds = &[seeds];
-        
-        token::transfer(
-            CpiContext::new_with_signer(
-                ctx.accounts.token_program.to_account_info(),
-                Transfer {
-                    from: ctx.accounts.vault.to_account_info(),
-                    to: ctx.accounts.recipient_token_account.to_account_info(),
-                    authority: ctx.accounts.bridge_state.to_account_info(),
-                },
-                signer_seeds,
-            ),
-            withdrawal.amount,
-        )?;
+        let operator = ctx.accounts.operator.key();
+        let now = Clock::get()?.unix_timestamp;
         
-        withdrawal.status = WithdrawalStatus::Completed;
-        withdrawal.processed_at = Clock::get()?.unix_timestamp;
+        require!(!ctx.accounts.bridge_state.is_paused, BridgeError::BridgePaused);
+        require!(ctx.accounts.operator_set.contains(&operator), BridgeError::Unauthorized);
+        require!(
+            ctx.accounts.operator_bond.amount >= ctx.accounts.bridge_state.min_operator_bond,
+            BridgeError::InsufficientOperatorBond
+        );
+        match withdrawal.status {
+            WithdrawalStatus::Pending => {}
+            WithdrawalStatus::Processing => {
+                require!(now >= withdrawal.lease_expires_at, BridgeError::LeaseActive);
+                // Votes gathered under the lapsed lease do not carry over
+                withdrawal.attestations = Attestations::default();
+            }
+            _ => return err!(BridgeError::InvalidWithdrawalStatus),
+        }
         
-        let bridge_mut = &mut ctx.accounts.bridge_state;
-        bridge_mut.total_withdrawn = bridge_mut.total_withdrawn.checked_add(withdrawal.amount).unwrap();
+        let previous_operator = withdrawal.claimed_by;
+        withdrawal.status = WithdrawalStatus::Processing;
+        withdrawal.claimed_by = operator;
+        withdrawal.lease_expires_at = now.checked_add(WITHDRAWAL_LEASE_PERIOD).unwrap();
         
-        emit!(WithdrawalProcessed {
+        emit!(WithdrawalClaimed {
             ticket_id: withdrawal.ticket_id,
-            recipient: withdrawal.recipient,
-            amount: withdrawal.amount,
-            timestamp: withdrawal.processed_at,
+            operator,
+            previous_operator,
+            lease_expires_at: withdrawal.lease_expires_at,
+            timestamp: now,
         });
         
         Ok(())
     }
 
-    pub fn generate_stealth_address(
-        ctx: Context<GenerateStealthAddress>,
-        ephemeral_pub_key: [u8; 33],
-        view_hint: u8,
-        k: u32,
+    pub fn process_withdrawal(
+        ctx: Context<ProcessWithdrawal>,
+        proof: WithdrawalProof,
     ) -> Result<()> {
-        let stealth_account = &mut ctx.accounts.stealth_address;
-        let meta_address = &ctx.accounts.meta_address;
+        require!(!ctx.accounts.bridge_state.is_paused, BridgeError::BridgePaused);
+        let withdrawal = &mut ctx.accounts.withdrawal_ticket;
+        let proven = attest_withdrawal(
+            withdrawal,
+            &ctx.accounts.operator_set,
+            &ctx.accounts.verifying_key,
+            &ctx.accounts.operator_bond,
+            ctx.accounts.bridge_state.min_operator_bond,
+            &proof,
+        )?;
         
-        stealth_account.owner = meta_address.owner;
-        stealth_account.ephemeral_pub_key = ephemeral_pub_key;
-        stealth_account.view_hint = view_hint;
-        stealth_account.k = k;
-        stealth_account.is_spent = false;
-        stealth_account.created_at = Clock::get()?.unix_timestamp;
-        stealth_account.bump = ctx.bumps.stealth_address;
+        if !proven {
+            return Ok(());
+        }
         
-        emit!(StealthAddressGenerated {
-            owner: stealth_account.owner,
-            ephemeral_pub_key,
-            view_hint,
-            k,
-            timestamp: stealth_account.created_at,
-        });
+        if ctx.accounts.bridge_state.challenge_period > 0 {
+            return start_withdrawal_challenge_period(&ctx.accounts.bridge_state, withdrawal);
+        }
         
-        Ok(())
+        release_withdrawal(
+            &mut ctx.accounts.asset_config,
+            &mut ctx.accounts.commitment_tree,
+            withdrawal,
+            &mut ctx.accounts.nullifier_record,
+        )?;
+        
+        burn_completed_withdrawal(
+            &mut ctx.accounts.asset_config,
+            withdrawal,
+            &ctx.accounts.bridge_state,
+            &ctx.accounts.mint,
+            &ctx.accounts.vault,
+            &ctx.accounts.token_program,
+        )
     }
 
-    pub fn send_to_stealth(
-        ctx: Context<SendToStealth>,
-        amount: u64,
+    /// Confirms several deposits of one asset in a single transaction.
+    /// `remaining_accounts` holds the deposit ticket, the depositor's token
+    /// account, the depositor's deposit shard and the Zcash transaction's
+    /// record for each confirmation, in order. In best-effort mode tickets
+    /// failing validation are skipped; a failure while settling a validated
+    /// ticket always aborts the batch.
+    pub fn batch_confirm_deposits<'info>(
+        ctx: Context<'_, '_, 'info, 'info, BatchConfirmDeposits<'info>>,
+        confirmations: Vec<DepositConfirmation>,
+        mode: BatchMode,
     ) -> Result<()> {
-        let stealth_address = &ctx.accounts.stealth_address;
+        require!(!ctx.accounts.bridge_state.is_paused, BridgeError::BridgePaused);
+        let remaining = ctx.remaining_accounts;
+        require!(
+            !confirmations.is_empty()
+                && confirmations.len() <= MAX_BATCH_TICKETS
+                && remaining.len() == confirmations.len() * 4,
+            BridgeError::InvalidBatch
+        );
         
-        require!(!stealth_address.is_spent, BridgeError::StealthAddressSpent);
+        let operator = ctx.accounts.operator.key();
+        let mint = ctx.accounts.asset_config.mint;
+        let mut applied: u32 = 0;
+        let mut skipped = Vec::new();
         
-        token::transfer(
-            CpiContext::new(
-                ctx.accounts.token_program.to_account_info(),
-                Transfer {
-                    from: ctx.accounts.sender_token_account.to_account_info(),
-                    to: ctx.accounts.stealth_token_account.to_account_info(),
-                    authority: ctx.accounts.sender.to_account_info(),
+        for (confirmation, accounts) in confirmations.iter().zip(remaining.chunks(4)) {
+            let attested = load_batch_deposit(accounts, &mint, ctx.program_id).and_then(
+                |(mut deposit, depositor_token_account, deposit_shard)| {
+                    let count = attest_deposit(
+                        &mut deposit,
+                        &ctx.accounts.asset_config,
+                        &ctx.accounts.operator_set,
+                        &ctx.accounts.light_client,
+                        &ctx.accounts.operator_bond,
+                        ctx.accounts.bridge_state.min_operator_bond,
+                        &confirmation.zcash_tx_id,
+                        &confirmation.proof,
+                        &confirmation.inclusion,
+                        &confirmation.funding,
+                    )?;
+                    Ok((deposit, depositor_token_account, deposit_shard, count))
                 },
-            ),
-            amount,
-        )?;
+            );
+            let Some((mut deposit, depositor_token_account, mut deposit_shard, count)) = mode.admit(attested)? else {
+                skipped.push(accounts[0].key());
+                continue;
+            };
+            
+            if count >= ctx.accounts.operator_set.threshold {
+                let (mut zcash_tx_record, bump) = load_batch_zcash_tx_record(
+                    &accounts[3],
+                    &confirmation.zcash_tx_id,
+                    &ctx.accounts.operator,
+                    &ctx.accounts.system_program,
+                    ctx.program_id,
+                )?;
+                consume_zcash_tx(&mut zcash_tx_record, &confirmation.zcash_tx_id, &deposit.key(), bump)?;
+                zcash_tx_record.exit(ctx.program_id)?;
+                
+                if ctx.accounts.bridge_state.challenge_period > 0 {
+                    start_deposit_challenge_period(&ctx.accounts.bridge_state, &mut deposit, confirmation.zcash_tx_id)?;
+                } else {
+                    mark_deposit_confirmed(
+                        &mut ctx.accounts.asset_config,
+                        &mut deposit_shard,
+                        &mut deposit,
+                        confirmation.zcash_tx_id,
+                    )?;
+                    
+                    mint_confirmed_deposit(
+                        &mut ctx.accounts.asset_config,
+                        &mut deposit_shard,
+                        &mut ctx.accounts.commitment_tree,
+                        &mut deposit,
+                        &ctx.accounts.bridge_state,
+                        &ctx.accounts.mint,
+                        &ctx.accounts.vault,
+                        &depositor_token_account,
+                        &ctx.accounts.token_program,
+                    )?;
+                    // The supply cap check must see earlier mints in this batch
+                    ctx.accounts.mint.reload()?;
+                }
+            }
+            
+            deposit.exit(ctx.program_id)?;
+            deposit_shard.exit(ctx.program_id)?;
+            applied += 1;
+        }
         
-        emit!(StealthPaymentSent {
-            sender: ctx.accounts.sender.key(),
-            stealth_address: stealth_address.key(),
-            ephemeral_pub_key: stealth_address.ephemeral_pub_key,
-            view_hint: stealth_address.view_hint,
-            amount,
+        emit!(DepositBatchConfirmed {
+            mint,
+            operator,
+            submitted: confirmations.len() as u32,
+            applied,
+            skipped,
             timestamp: Clock::get()?.unix_timestamp,
         });
         
         Ok(())
     }
 
-    pub fn claim_from_stealth(
-        ctx: Context<ClaimFromStealth>,
+    /// Processes several withdrawals of one asset in a single transaction.
+    /// `remaining_accounts` holds the withdrawal ticket and its nullifier
+    /// record for each proof, in order. Skipping and abort rules follow
+    /// `batch_confirm_deposits`.
+    pub fn batch_process_withdrawals<'info>(
+        ctx: Context<'_, '_, 'info, 'info, BatchProcessWithdrawals<'info>>,
+        proofs: Vec<WithdrawalProof>,
+        mode: BatchMode,
     ) -> Result<()> {
-        let stealth_address = &mut ctx.accounts.stealth_address;
-        
-        require!(!stealth_address.is_spent, BridgeError::StealthAddressSpent);
-        require!(stealth_address.owner == ctx.accounts.owner.key(), BridgeError::Unauthorized);
-        
-        let balance = ctx.accounts.stealth_token_account.amount;
+        require!(!ctx.accounts.bridge_state.is_paused, BridgeError::BridgePaused);
+        let remaining = ctx.remaining_accounts;
+        require!(
+            !proofs.is_empty()
+                && proofs.len() <= MAX_BATCH_TICKETS
+                && remaining.len() == proofs.len() * 2,
+            BridgeError::InvalidBatch
+        );
         
-        let seeds = &[
-            b"stealth",
-            stealth_address.owner.as_ref(),
-            stealth_address.ephemeral_pub_key.as_ref(),
-            &[stealth_address.bump],
-        ];
-        let signer = &[&seeds[..]];
+        let operator = ctx.accounts.operator.key();
+        let mint = ctx.accounts.asset_config.mint;
+        let mut applied: u32 = 0;
+        let mut skipped = Vec::new();
         
-        token::transfer(
-            CpiContext::new_with_signer(
-                ctx.accounts.token_program.to_account_info(),
-                Transfer {
-                    from: ctx.accounts.stealth_token_account.to_account_info(),
-                    to: ctx.accounts.owner_token_account.to_account_info(),
-                    authority: stealth_address.to_account_info(),
+        for (proof, accounts) in proofs.iter().zip(remaining.chunks(2)) {
+            let attested = load_batch_withdrawal(accounts, &mint, ctx.program_id).and_then(
+                |(mut withdrawal, nullifier_record)| {
+                    let proven = attest_withdrawal(
+                        &mut withdrawal,
+                        &ctx.accounts.operator_set,
+                        &ctx.accounts.verifying_key,
+                        &ctx.accounts.operator_bond,
+                        ctx.accounts.bridge_state.min_operator_bond,
+                        proof,
+                    )?;
+                    Ok((withdrawal, nullifier_record, proven))
                 },
-                signer,
-            ),
-            balance,
-        )?;
-        
-        stealth_address.is_spent = true;
+            );
+            let Some((mut withdrawal, mut nullifier_record, proven)) = mode.admit(attested)? else {
+                skipped.push(accounts[0].key());
+                continue;
+            };
+            
+            if proven {
+                if ctx.accounts.bridge_state.challenge_period > 0 {
+                    start_withdrawal_challenge_period(&ctx.accounts.bridge_state, &mut withdrawal)?;
+                } else {
+                    release_withdrawal(
+                        &mut ctx.accounts.asset_config,
+                        &mut ctx.accounts.commitment_tree,
+                        &mut withdrawal,
+                        &mut nullifier_record,
+                    )?;
+                    
+                    burn_completed_withdrawal(
+                        &mut ctx.accounts.asset_config,
+                        &withdrawal,
+                        &ctx.accounts.bridge_state,
+                        &ctx.accounts.mint,
+                        &ctx.accounts.vault,
+                        &ctx.accounts.token_program,
+                    )?;
+                    ctx.accounts.mint.reload()?;
+                }
+            }
+            
+            withdrawal.exit(ctx.program_id)?;
+            nullifier_record.exit(ctx.program_id)?;
+            applied += 1;
+        }
         
-        emit!(StealthPaymentClaimed {
-            owner: ctx.accounts.owner.key(),
-            stealth_address: stealth_address.key(),
-            amount: balance,
+        emit!(WithdrawalBatchProcessed {
+            mint,
+            operator,
+            submitted: proofs.len() as u32,
+            applied,
+            skipped,
             timestamp: Clock::get()?.unix_timestamp,
         });
         
         Ok(())
     }
 
-    pub fn update_operator(
-        ctx: Context<UpdateOperator>,
-        new_operator: Pubkey,
-    ) -> Result<()> {
-        let bridge = &mut ctx.accounts.bridge_state;
-        require!(ctx.accounts.authority.key() == bridge.authority, BridgeError::Unauthorized);
+    /// Confirms a deposit whose challenge period has passed unchallenged
+    pub fn finalize_deposit(ctx: Context<FinalizeDeposit>) -> Result<()> {
+        let deposit = &mut ctx.accounts.deposit_ticket;
+        
+        require!(!ctx.accounts.bridge_state.is_paused, BridgeError::BridgePaused);
+        require!(deposit.status == DepositStatus::AwaitingFinality, BridgeError::InvalidDepositStatus);
+        require!(
+            Clock::get()?.unix_timestamp >= deposit.finalizes_at,
+            BridgeError::ChallengePeriodActive
+        );
+        
+        let zcash_tx_id = deposit.zcash_tx_id;
+        mark_deposit_confirmed(
+            &mut ctx.accounts.asset_config,
+            &mut ctx.accounts.deposit_shard,
+            deposit,
+            zcash_tx_id,
+        )?;
+        
+        mint_confirmed_deposit(
+            &mut ctx.accounts.asset_config,
+            &mut ctx.accounts.deposit_shard,
+            &mut ctx.accounts.commitment_tree,
+            deposit,
+            &ctx.accounts.bridge_state,
+            &ctx.accounts.mint,
+            &ctx.accounts.vault,
+            &ctx.accounts.depositor_token_account,
+            &ctx.accounts.token_program,
+        )
+    }
+
+    /// Releases a withdrawal whose challenge period has passed unchallenged
+    pub fn finalize_withdrawal(ctx: Context<FinalizeWithdrawal>) -> Result<()> {
+        let withdrawal = &mut ctx.accounts.withdrawal_ticket;
+        
+        require!(!ctx.accounts.bridge_state.is_paused, BridgeError::BridgePaused);
+        require!(
+            withdrawal.status == WithdrawalStatus::AwaitingFinality,
+            BridgeError::InvalidWithdrawalStatus
+        );
+        require!(
+            Clock::get()?.unix_timestamp >= withdrawal.finalizes_at,
+            BridgeError::ChallengePeriodActive
+        );
+        
+        release_withdrawal(
+            &mut ctx.accounts.asset_config,
+            &mut ctx.accounts.commitment_tree,
+            withdrawal,
+            &mut ctx.accounts.nullifier_record,
+        )?;
+        
+        burn_completed_withdrawal(
+            &mut ctx.accounts.asset_config,
+            withdrawal,
+            &ctx.accounts.bridge_state,
+            &ctx.accounts.mint,
+            &ctx.accounts.vault,
+            &ctx.accounts.token_program,
+        )
+    }
+
+    /// Bonds the caller as a watcher, or tops an existing bond up to cover
+    /// its open challenges and one more at the currently required amount
+    pub fn register_watcher(ctx: Context<RegisterWatcher>) -> Result<()> {
+        let required = ctx.accounts.bridge_state.watcher_bond;
+        require!(ctx.accounts.bridge_state.challenge_period > 0, BridgeError::OptimisticModeDisabled);
+        
+        let record = &mut ctx.accounts.watcher_record;
+        let needed = record.required_bond(required, 1).ok_or(BridgeError::Overflow)?;
+        let top_up = needed.saturating_sub(record.bond);
+        if top_up > 0 {
+            system_program::transfer(
+                CpiContext::new(
+                    ctx.accounts.system_program.to_account_info(),
+                    system_program::Transfer {
+                        from: ctx.accounts.watcher.to_account_info(),
+                        to: record.to_account_info(),
+                    },
+                ),
+                top_up,
+            )?;
+        }
         
-        let old_operator = bridge.operator;
-        bridge.operator = new_operator;
+        if record.watcher == Pubkey::default() {
+            record.watcher = ctx.accounts.watcher.key();
+            record.registered_at = Clock::get()?.unix_timestamp;
+            record.bump = ctx.bumps.watcher_record;
+        }
+        record.bond = record.bond.checked_add(top_up).unwrap();
         
-        emit!(OperatorUpdated {
-            old_operator,
-            new_operator,
+        emit!(WatcherBonded {
+            watcher: record.watcher,
+            bond: record.bond,
             timestamp: Clock::get()?.unix_timestamp,
         });
         
         Ok(())
     }
 
-    pub fn pause_bridge(ctx: Context<PauseBridge>) -> Result<()> {
-        let bridge = &mut ctx.accounts.bridge_state;
-        require!(ctx.accounts.authority.key() == bridge.authority, BridgeError::Unauthorized);
+    /// Closes a watcher record and returns the bond, once none of its
+    /// challenges are still open
+    pub fn unregister_watcher(ctx: Context<UnregisterWatcher>) -> Result<()> {
+        require!(
+            ctx.accounts.watcher_record.open_challenges == 0,
+            BridgeError::ChallengesOutstanding
+        );
         
-        bridge.is_paused = true;
+        Ok(())
+    }
+
+    /// Freezes a deposit during its challenge period and escalates it to
+    /// the authority
+    pub fn challenge_deposit(ctx: Context<ChallengeDeposit>, claim: FraudClaim) -> Result<()> {
+        let deposit = &mut ctx.accounts.deposit_ticket;
+        let record = &mut ctx.accounts.watcher_record;
+        let now = Clock::get()?.unix_timestamp;
         
-        emit!(BridgePausedEvent {
-            timestamp: Clock::get()?.unix_timestamp,
+        require!(deposit.status == DepositStatus::AwaitingFinality, BridgeError::InvalidDepositStatus);
+        require!(now < deposit.finalizes_at, BridgeError::ChallengePeriodOver);
+        require!(
+            record.can_challenge(ctx.accounts.bridge_state.watcher_bond),
+            BridgeError::InsufficientWatcherBond
+        );
+        
+        deposit.status = DepositStatus::Disputed;
+        deposit.challenger = record.watcher;
+        record.open_challenges = record.open_challenges.checked_add(1).unwrap();
+        
+        emit!(DepositChallenged {
+            ticket_id: deposit.ticket_id,
+            depositor: deposit.depositor,
+            watcher: record.watcher,
+            kind: claim.kind,
+            evidence_hash: claim.evidence_hash,
+            timestamp: now,
         });
         
         Ok(())
     }
 
-    pub fn unpause_bridge(ctx: Context<UnpauseBridge>) -> Result<()> {
-        let bridge = &mut ctx.accounts.bridge_state;
-        require!(ctx.accounts.authority.key() == bridge.authority, BridgeError::Unauthorized);
+    /// Freezes a withdrawal during its challenge period and escalates it
+    /// to the authority
+    pub fn challenge_withdrawal(ctx: Context<ChallengeWithdrawal>, claim: FraudClaim) -> Result<()> {
+        let withdrawal = &mut ctx.accounts.withdrawal_ticket;
+        let record = &mut ctx.accounts.watcher_record;
+        let now = Clock::get()?.unix_timestamp;
         
-        bridge.is_paused = false;
+        require!(
+            withdrawal.status == WithdrawalStatus::AwaitingFinality,
+            BridgeError::InvalidWithdrawalStatus
+        );
+        require!(now < withdrawal.finalizes_at, BridgeError::ChallengePeriodOver);
+        require!(
+            record.can_challenge(ctx.accounts.bridge_state.watcher_bond),
+            BridgeError::InsufficientWatcherBond
+        );
         
-        emit!(BridgeUnpausedEvent {
-            timestamp: Clock::get()?.unix_timestamp,
+        withdrawal.status = WithdrawalStatus::Disputed;
+        withdrawal.challenger = record.watcher;
+        record.open_challenges = record.open_challenges.checked_add(1).unwrap();
+        
+        emit!(WithdrawalChallenged {
+            ticket_id: withdrawal.ticket_id,
+            watcher: record.watcher,
+            kind: claim.kind,
+            evidence_hash: claim.evidence_hash,
+            timestamp: now,
         });
         
         Ok(())
     }
-}
-
-fn hash_zcash_address(address: &[u8; 78]) -> [u8; 32] {
-    use anchor_lang::solana_program::keccak::hash;
-    hash(address).to_bytes()
-}
 
-fn hash_pubkey(pubkey: &Pubkey) -> [u8; 32] {
-    use anchor_lang::solana_program::keccak::hash;
-    hash(pubkey.as_ref()).to_bytes()
-}
+    /// Settles a disputed deposit. An upheld claim fails and refunds the
+    /// deposit and slashes the operators that attested to it, whose bonds
+    /// go in `remaining_accounts`; a rejected one forfeits one challenge's
+    /// worth of the watcher's bond to the authority and restarts the
+    /// challenge period.
+    pub fn resolve_deposit_challenge<'info>(
+        ctx: Context<'_, '_, 'info, 'info, ResolveDepositChallenge<'info>>,
+        upheld: bool,
+    ) -> Result<()> {
+        let deposit = &mut ctx.accounts.deposit_ticket;
+        let bridge = &ctx.accounts.bridge_state;
+        let now = Clock::get()?.unix_timestamp;
+        
+        require!(ctx.accounts.authority.key() == bridge.authority, BridgeError::Unauthorized);
+        require!(deposit.status == DepositStatus::Disputed, BridgeError::InvalidDepositStatus);
+        
+        let record = &mut ctx.accounts.watcher_record;
+        record.open_challenges = record.open_challenges.checked_sub(1).unwrap();
+        
+        if upheld {
+            let (refund, _) = refund_deposit_escrow(
+                &ctx.accounts.asset_config,
+                &mut ctx.accounts.deposit_shard,
+                deposit,
+                false,
+                &ctx.accounts.bridge_state,
+                &ctx.accounts.mint,
+                &ctx.accounts.vault,
+                &ctx.accounts.depositor_token_account,
+                &ctx.accounts.token_program,
+            )?;
+            
+            deposit.status = DepositStatus::Failed;
+            deposit.processed_at = now;
+            
+            emit!(DepositFailed {
+                ticket_id: deposit.ticket_id,
+                depositor: deposit.depositor,
+                refunded: refund,
+                timestamp: now,
+            });
+            
+            slash_attesters(
+                &deposit.attestations,
+                &mut ctx.accounts.operator_set,
+                ctx.remaining_accounts,
+                ctx.program_id,
+                &ctx.accounts.bridge_state,
+                &ctx.accounts.bond_vault,
+                &ctx.accounts.slash_recipient,
+                &ctx.accounts.bond_mint,
+                &ctx.accounts.bond_token_program,
+            )?;
+        } else {
+            forfeit_watcher_bond(record, &ctx.accounts.authority.to_account_info(), bridge.watcher_bond)?;
+            deposit.status = DepositStatus::AwaitingFinality;
+            // Other watchers get a full period after the dispute ends
+            deposit.finalizes_at = now.checked_add(bridge.challenge_period).unwrap();
+        }
+        deposit.challenger = Pubkey::default();
+        
+        emit!(DepositChallengeResolved {
+            ticket_id: deposit.ticket_id,
+            depositor: deposit.depositor,
+            watcher: record.watcher,
+            upheld,
+            timestamp: now,
+        });
+        
+        Ok(())
+    }
 
-fn verify_deposit_proof(proof: &DepositProof, deposit: &DepositTicket) -> bool {
-    // Proof data must not be empty (all zeros is invalid)
-    if proof.proof_data.iter().all(|&x| x == 0) {
-        return false;
+    /// Settles a disputed withdrawal. An upheld claim fails and refunds the
+    /// withdrawal and slashes the operators that attested to it, whose
+    /// bonds go in `remaining_accounts`; a rejected one forfeits one
+    /// challenge's worth of the watcher's bond to the authority and
+    /// restarts the challenge period.
+    pub fn resolve_withdrawal_challenge<'info>(
+        ctx: Context<'_, '_, 'info, 'info, ResolveWithdrawalChallenge<'info>>,
+        upheld: bool,
+    ) -> Result<()> {
+        let withdrawal = &mut ctx.accounts.withdrawal_ticket;
+        let bridge = &ctx.accounts.bridge_state;
+        let now = Clock::get()?.unix_timestamp;
+        
+        require!(ctx.accounts.authority.key() == bridge.authority, BridgeError::Unauthorized);
+        require!(withdrawal.status == WithdrawalStatus::Disputed, BridgeError::InvalidWithdrawalStatus);
+        
+        let record = &mut ctx.accounts.watcher_record;
+        record.open_challenges = record.open_challenges.checked_sub(1).unwrap();
+        
+        if upheld {
+            let refund = withdrawal.amount.checked_add(withdrawal.fee).unwrap();
+            
+            let bump = bridge.bump;
+            let seeds: &[&[u8]] = &[
+                b"bridge",
+                &[bump],
+            ];
+            let signer_seeds = &[seeds];
+            
+            token_interface::transfer_checked(
+                CpiContext::new_with_signer(
+                    ctx.accounts.token_program.to_account_info(),
+                    TransferChecked {
+                        from: ctx.accounts.vault.to_account_info(),
+                        mint: ctx.accounts.mint.to_account_info(),
+                        to: ctx.accounts.recipient_token_account.to_account_info(),
+                        authority: ctx.accounts.bridge_state.to_account_info(),
+                    },
+                    signer_seeds,
+                ),
+                refund,
+                ctx.accounts.mint.decimals,
+            )?;
+            
+            // Release the nullifier so the note can be presented again
+            ctx.accounts.nullifier_record.withdrawal_ticket = Pubkey::default();
+            
+            withdrawal.status = WithdrawalStatus::Failed;
+            withdrawal.processed_at = now;
+            ctx.accounts.asset_config.release_withdrawal_escrow(withdrawal);
+            
+            emit!(WithdrawalFailed {
+                ticket_id: withdrawal.ticket_id,
+                recipient: withdrawal.recipient,
+                refunded: refund,
+                timestamp: now,
+            });
+            
+            slash_attesters(
+                &withdrawal.attestations,
+                &mut ctx.accounts.operator_set,
+                ctx.remaining_accounts,
+                ctx.program_id,
+                &ctx.accounts.bridge_state,
+                &ctx.accounts.bond_vault,
+                &ctx.accounts.slash_recipient,
+                &ctx.accounts.bond_mint,
+                &ctx.accounts.bond_token_program,
+            )?;
+        } else {
+            forfeit_watcher_bond(record, &ctx.accounts.authority.to_account_info(), bridge.watcher_bond)?;
+            withdrawal.status = WithdrawalStatus::AwaitingFinality;
+            // Other watchers get a full period after the dispute ends
+            withdrawal.finalizes_at = now.checked_add(bridge.challenge_period).unwrap();
+        }
+        withdrawal.challenger = Pubkey::default();
+        
+        emit!(WithdrawalChallengeResolved {
+            ticket_id: withdrawal.ticket_id,
+            watcher: record.watcher,
+            upheld,
+            timestamp: now,
+        });
+        
+        Ok(())
     }
-    
-    // Verify the proof contains valid structure
-    // First 32 bytes: commitment hash
-    // Next 32 bytes: nullifier
-    // Remaining: zk-SNARK proof data
-    let commitment_slice = &proof.proof_data[0..32];
-    let nullifier_slice = &proof.proof_data[32..64];
-    
-    // Verify commitment matches expected address hash
-    let expected_hash = hash_zcash_address(&deposit.zcash_shielded_address);
-    if commitment_slice != expected_hash.as_slice() {
-        return false;
+
+    /// Completes a withdrawal the outflow limiter deferred, once the
+    /// rolling window has room for it again. A ticket left larger than the
+    /// whole cap by a later `set_outflow_limit` can only be failed.
+    pub fn release_deferred_withdrawal(ctx: Context<ReleaseDeferredWithdrawal>) -> Result<()> {
+        require!(!ctx.accounts.bridge_state.is_paused, BridgeError::BridgePaused);
+        let withdrawal = &mut ctx.accounts.withdrawal_ticket;
+        let asset = &mut ctx.accounts.asset_config;
+        
+        require!(withdrawal.status == WithdrawalStatus::Deferred, BridgeError::InvalidWithdrawalStatus);
+        require!(
+            ctx.accounts.operator_set.contains(&ctx.accounts.operator.key()),
+            BridgeError::Unauthorized
+        );
+        
+        let now = Clock::get()?.unix_timestamp;
+        require!(
+            asset.outflow_limiter.try_record(now, withdrawal.amount),
+            BridgeError::OutflowLimitExceeded
+        );
+        
+        complete_withdrawal(
+            asset,
+            &mut ctx.accounts.commitment_tree,
+            withdrawal,
+            &mut ctx.accounts.nullifier_record,
+        )?;
+        
+        burn_completed_withdrawal(
+            asset,
+            withdrawal,
+            &ctx.accounts.bridge_state,
+            &ctx.accounts.mint,
+            &ctx.accounts.vault,
+            &ctx.accounts.token_program,
+        )
     }
-    
-    // Verify public inputs encode the deposit amount correctly
-    let amount_bytes = deposit.amount.to_le_bytes();
-    if proof.public_inputs[0] != amount_bytes {
-        return false;
+
+    /// Records an operator's vote to fail a withdrawal. The withdrawal is
+    /// failed and refunded once the threshold of operators agrees.
+    pub fn fail_withdrawal(ctx: Context<FailWithdrawal>) -> Result<()> {
+        let ticket = ctx.accounts.withdrawal_ticket.key();
+        let withdrawal = &mut ctx.accounts.withdrawal_ticket;
+        let bridge = &ctx.accounts.bridge_state;
+        let operator_set = &ctx.accounts.operator_set;
+        let operator = ctx.accounts.operator.key();
+        
+        require!(
+            matches!(
+                withdrawal.status,
+                WithdrawalStatus::Pending | WithdrawalStatus::Processing | WithdrawalStatus::Deferred
+            ),
+            BridgeError::InvalidWithdrawalStatus
+        );
+        
+        let count = withdrawal.attestations.record(
+            operator_set,
+            &ctx.accounts.operator_bond,
+            bridge.min_operator_bond,
+            hash_failure_attestation(&ticket),
+        )?;
+        
+        emit!(WithdrawalAttested {
+            ticket_id: withdrawal.ticket_id,
+            operator,
+            attestations: count,
+            threshold: operator_set.threshold,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+        
+        if count < operator_set.threshold {
+            return Ok(());
+        }
+        
+        let refund = withdrawal.amount.checked_add(withdrawal.fee).unwrap();
+        
+        let bump = bridge.bump;
+        let seeds: &[&[u8]] = &[
+            b"bridge",
+            &[bump],
+        ];
+        let signer_seeds = &[seeds];
+        
+        token_interface::transfer_checked(
+            CpiContext::new_with_signer(
+                ctx.accounts.token_program.to_account_info(),
+                TransferChecked {
+                    from: ctx.accounts.vault.to_account_info(),
+                    mint: ctx.accounts.mint.to_account_info(),
+                    to: ctx.accounts.recipient_token_account.to_account_info(),
+                    authority: ctx.accounts.bridge_state.to_account_info(),
+                },
+                signer_seeds,
+            ),
+            refund,
+            ctx.accounts.mint.decimals,
+        )?;
+        
+        // Release the nullifier so the note can be presented again
+        ctx.accounts.nullifier_record.withdrawal_ticket = Pubkey::default();
+        
+        withdrawal.status = WithdrawalStatus::Failed;
+        withdrawal.processed_at = Clock::get()?.unix_timestamp;
+        ctx.accounts.asset_config.release_withdrawal_escrow(withdrawal);
+        
+        emit!(WithdrawalFailed {
+            ticket_id: withdrawal.ticket_id,
+            recipient: withdrawal.recipient,
+            refunded: refund,
+            timestamp: withdrawal.processed_at,
+        });
+        
+        Ok(())
     }
-    
-    // Verify depositor pubkey hash is in public inputs
-    let depositor_hash = hash_pubkey(&deposit.depositor);
-    if proof.public_inputs[1] != depositor_hash[0..8].try_into().unwrap_or([0u8; 8]) {
-        return false;
+
+    /// Lets the recipient back out of a withdrawal no operator of the current
+    /// set has picked up yet. The escrowed amount is refunded, the protocol fee is retained and
+    /// the nullifier is released.
+    pub fn cancel_withdrawal(ctx: Context<CancelWithdrawal>) -> Result<()> {
+        let withdrawal = &mut ctx.accounts.withdrawal_ticket;
+        let bridge = &ctx.accounts.bridge_state;
+        let now = Clock::get()?.unix_timestamp;
+        
+        require!(withdrawal.status == WithdrawalStatus::Pending, BridgeError::InvalidWithdrawalStatus);
+        require!(
+            !withdrawal.attestations.is_current(&ctx.accounts.operator_set),
+            BridgeError::TicketAlreadyAttested
+        );
+        require!(
+            now >= withdrawal.created_at.checked_add(CANCEL_LOCK_PERIOD).unwrap(),
+            BridgeError::CancelLockActive
+        );
+        
+        let bump = bridge.bump;
+        let seeds: &[&[u8]] = &[
+            b"bridge",
+            &[bump],
+        ];
+        let signer_seeds = &[seeds];
+        
+        token_interface::transfer_checked(
+            CpiContext::new_with_signer(
+                ctx.accounts.token_program.to_account_info(),
+                TransferChecked {
+                    from: ctx.accounts.vault.to_account_info(),
+                    mint: ctx.accounts.mint.to_account_info(),
+                    to: ctx.accounts.recipient_token_account.to_account_info(),
+                    authority: ctx.accounts.bridge_state.to_account_info(),
+                },
+                signer_seeds,
+            ),
+            withdrawal.amount,
+            ctx.accounts.mint.decimals,
+        )?;
+        
+        ctx.accounts.nullifier_record.withdrawal_ticket = Pubkey::default();
+        
+        withdrawal.status = WithdrawalStatus::Cancelled;
+        withdrawal.processed_at = now;
+        
+        let asset_mut = &mut ctx.accounts.asset_config;
+        asset_mut.accrued_fees = asset_mut.accrued_fees.checked_add(withdrawal.fee).unwrap();
+        asset_mut.release_withdrawal_escrow(withdrawal);
+        
+        emit!(WithdrawalCancelled {
+            ticket_id: withdrawal.ticket_id,
+            recipient: withdrawal.recipient,
+            refunded: withdrawal.amount,
+            fee_retained: withdrawal.fee,
+            timestamp: now,
+        });
+        
+        Ok(())
     }
-    
-    // Verify zk-SNARK proof structure (non-empty proof section)
-    let proof_section = &proof.proof_data[64..];
-    if proof_section.iter().all(|&x| x == 0) {
-        return false;
+
+    /// Closes a settled withdrawal ticket and returns its rent to the recipient.
+    /// The nullifier record is kept so the note can never be replayed.
+    pub fn close_withdrawal_ticket(ctx: Context<CloseWithdrawalTicket>) -> Result<()> {
+        let withdrawal = &ctx.accounts.withdrawal_ticket;
+        
+        require!(
+            matches!(
+                withdrawal.status,
+                WithdrawalStatus::Completed | WithdrawalStatus::Failed | WithdrawalStatus::Cancelled
+            ),
+            BridgeError::TicketNotSettled
+        );
+        
+        emit!(WithdrawalTicketClosed {
+            ticket: withdrawal.key(),
+            ticket_id: withdrawal.ticket_id,
+            recipient: withdrawal.recipient,
+            mint: withdrawal.mint,
+            amount: withdrawal.amount,
+            fee: withdrawal.fee,
+            status: withdrawal.status,
+            partial_note_nullifier: withdrawal.partial_note_nullifier,
+            record_hash: hash_closed_record(&***withdrawal)?,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+        
+        Ok(())
     }
-    
-    true
-}
 
-fn verify_withdrawal_proof(proof: &WithdrawalProof, withdrawal: &WithdrawalTicket) -> bool {
-    // Proof data must not be empty (all zeros is invalid)
-    if proof.proof_data.iter().all(|&x| x == 0) {
-        return false;
+    pub fn initialize_commitment_tree(ctx: Context<InitializeCommitmentTree>) -> Result<()> {
+        require!(
+            ctx.accounts.authority.key() == ctx.accounts.bridge_state.authority,
+            BridgeError::Unauthorized
+        );
+        
+        let tree = &mut ctx.accounts.commitment_tree;
+        tree.init();
+        tree.bump = ctx.bumps.commitment_tree;
+        
+        Ok(())
     }
-    
-    // Verify commitment matches the withdrawal ticket
-    if proof.commitment != withdrawal.partial_note_commitment {
-        return false;
+
+    /// Seeds the light client with a trusted checkpoint. `history` carries
+    /// the timing of the checkpoint's ancestors, parent first, so the
+    /// difficulty of the headers relayed on top of it can be checked.
+    ///
+    /// Equihash solutions are not verified: a relayed header only has to
+    /// hash below the recomputed target, which takes SHA-256 work alone and
+    /// is cheap to forge. Headers are therefore trusted to come from the
+    /// relayers admitted with `add_relayer`, and the chain they build is
+    /// only as sound as that set.
+    pub fn initialize_light_client(
+        ctx: Context<InitializeLightClient>,
+        checkpoint: StoredHeader,
+        history: Vec<BlockTiming>,
+        min_confirmations: u32,
+    ) -> Result<()> {
+        require!(
+            ctx.accounts.authority.key() == ctx.accounts.bridge_state.authority,
+            BridgeError::Unauthorized
+        );
+        require!(min_confirmations > 0, BridgeError::InvalidConfig);
+        require!(history.len() == CHECKPOINT_HISTORY_LEN, BridgeError::InvalidConfig);
+        
+        let light_client = &mut ctx.accounts.light_client;
+        light_client.headers = vec![checkpoint.clone()];
+        light_client.tip_index = 0;
+        light_client.checkpoint_hash = checkpoint.hash;
+        light_client.checkpoint_history = history;
+        light_client.min_confirmations = min_confirmations;
+        light_client.bump = ctx.bumps.light_client;
+        
+        emit!(ZcashHeaderAccepted {
+            hash: checkpoint.hash,
+            height: checkpoint.height,
+            cumulative_work: checkpoint.cumulative_work,
+            is_new_tip: true,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+        
+        Ok(())
     }
-    
-    // Verify nullifier matches and has not been used
-    if proof.nullifier != withdrawal.partial_note_nullifier {
-        return false;
+
+    /// Allows `relayer` to submit Zcash headers
+    pub fn add_relayer(ctx: Context<AddRelayer>, relayer: Pubkey) -> Result<()> {
+        require!(
+            ctx.accounts.authority.key() == ctx.accounts.bridge_state.authority,
+            BridgeError::Unauthorized
+        );
+        
+        let record = &mut ctx.accounts.relayer_record;
+        record.relayer = relayer;
+        record.added_at = Clock::get()?.unix_timestamp;
+        record.bump = ctx.bumps.relayer_record;
+        
+        emit!(RelayerAdded {
+            relayer,
+            timestamp: record.added_at,
+        });
+        
+        Ok(())
     }
-    
-    // Verify nullifier is not all zeros (indicates valid note spend)
-    if proof.nullifier.iter().all(|&x| x == 0) {
-        return false;
+
+    pub fn remove_relayer(ctx: Context<RemoveRelayer>) -> Result<()> {
+        require!(
+            ctx.accounts.authority.key() == ctx.accounts.bridge_state.authority,
+            BridgeError::Unauthorized
+        );
+        
+        emit!(RelayerRemoved {
+            relayer: ctx.accounts.relayer_record.relayer,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+        
+        Ok(())
     }
-    
-    // Verify proof data contains valid zk-SNARK proof structure
-    // First 64 bytes: G1 point (compressed)
-    // Next 128 bytes: G2 point (compressed)  
-    // Remaining: public inputs
-    let g1_point = &proof.proof_data[0..64];
-    let g2_point = &proof.proof_data[64..192];
-    
-    // G1 and G2 points must not be identity (all zeros)
-    if g1_point.iter().all(|&x| x == 0) || g2_point.iter().all(|&x| x == 0) {
-        return false;
+
+    /// Appends a chunk of a raw Zcash header to the relayer's buffer. A full
+    /// header with its Equihash solution does not fit in one transaction.
+    pub fn write_header_buffer(
+        ctx: Context<WriteHeaderBuffer>,
+        offset: u32,
+        chunk: Vec<u8>,
+    ) -> Result<()> {
+        let buffer = &mut ctx.accounts.header_buffer;
+        require!(offset as usize == buffer.data.len(), BridgeError::InvalidHeader);
+        require!(
+            buffer.data.len() + chunk.len() <= zcash_header::HEADER_LEN,
+            BridgeError::InvalidHeader
+        );
+        
+        buffer.relayer = ctx.accounts.relayer.key();
+        buffer.data.extend_from_slice(&chunk);
+        buffer.bump = ctx.bumps.header_buffer;
+        
+        Ok(())
     }
-    
-    // Verify the withdrawal amount is encoded in the proof
-    let amount_bytes = withdrawal.amount.to_le_bytes();
-    let proof_amount = &proof.proof_data[192..200];
-    if proof_amount != amount_bytes.as_slice() {
-        return false;
+
+    /// Validates the buffered header against its parent in the relay window
+    /// and stores it, moving the tip if it carries more cumulative work
+    pub fn submit_zcash_header(ctx: Context<SubmitZcashHeader>) -> Result<()> {
+        let buffer = &mut ctx.accounts.header_buffer;
+        let is_mainnet = ctx.accounts.bridge_state.zcash_network == ZcashNetwork::Mainnet;
+        let now = Clock::get()?.unix_timestamp;
+        
+        let header = zcash_header::parse(&buffer.data).ok_or(BridgeError::InvalidHeader)?;
+        buffer.data.clear();
+        
+        let (stored, is_new_tip) = ctx.accounts.light_client.accept(&header, now, is_mainnet)?;
+        
+        emit!(ZcashHeaderAccepted {
+            hash: stored.hash,
+            height: stored.height,
+            cumulative_work: stored.cumulative_work,
+            is_new_tip,
+            timestamp: now,
+        });
+        
+        Ok(())
     }
-    
-    true
-}
 
-#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
-pub struct BridgeConfig {
-    pub operator: Pubkey,
+    pub fn set_withdrawal_verifying_key(
+        ctx: Context<SetWithdrawalVerifyingKey>,
+        key: VerifyingKeyData,
+    ) -> Result<()> {
+        require!(
+            ctx.accounts.authority.key() == ctx.accounts.bridge_state.authority,
+            BridgeError::Unauthorized
+        );
+        
+        let vk = &mut ctx.accounts.verifying_key;
+        vk.alpha_g1 = key.alpha_g1;
+        vk.beta_g2 = key.beta_g2;
+        vk.gamma_g2 = key.gamma_g2;
+        vk.delta_g2 = key.delta_g2;
+        vk.ic = key.ic;
+        vk.bump = ctx.bumps.verifying_key;
+        
+        emit!(VerifyingKeyUpdated {
+            key_hash: hash_verifying_key(vk),
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+        
+        Ok(())
+    }
+
+    pub fn generate_stealth_address(
+        ctx: Context<GenerateStealthAddress>,
+        ephemeral_pub_key: [u8; 33],
+        view_hint: u8,
+        k: u32,
+    ) -> Result<()> {
+        let stealth_account = &mut ctx.accounts.stealth_address;
+        let meta_address = &ctx.accounts.meta_address;
+        
+        stealth_account.owner = meta_address.owner;
+        stealth_account.ephemeral_pub_key = ephemeral_pub_key;
+        stealth_account.view_hint = view_hint;
+        stealth_account.k = k;
+        stealth_account.is_spent = false;
+        stealth_account.created_at = Clock::get()?.unix_timestamp;
+        stealth_account.bump = ctx.bumps.stealth_address;
+        stealth_account.payer = ctx.accounts.payer.key();
+        
+        emit!(StealthAddressGenerated {
+            owner: stealth_account.owner,
+            ephemeral_pub_key,
+            view_hint,
+            k,
+            timestamp: stealth_account.created_at,
+        });
+        
+        Ok(())
+    }
+
+    pub fn send_to_stealth(
+        ctx: Context<SendToStealth>,
+        amount: u64,
+    ) -> Result<()> {
+        let stealth_address = &ctx.accounts.stealth_address;
+        
+        require!(!stealth_address.is_spent, BridgeError::StealthAddressSpent);
+        
+        token_interface::transfer_checked(
+            CpiContext::new(
+                ctx.accounts.token_program.to_account_info(),
+                TransferChecked {
+                    from: ctx.accounts.sender_token_account.to_account_info(),
+                    mint: ctx.accounts.mint.to_account_info(),
+                    to: ctx.accounts.stealth_token_account.to_account_info(),
+                    authority: ctx.accounts.sender.to_account_info(),
+                },
+            ),
+            amount,
+            ctx.accounts.mint.decimals,
+        )?;
+        
+        emit!(StealthPaymentSent {
+            sender: ctx.accounts.sender.key(),
+            stealth_address: stealth_address.key(),
+            ephemeral_pub_key: stealth_address.ephemeral_pub_key,
+            view_hint: stealth_address.view_hint,
+            amount,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+        
+        Ok(())
+    }
+
+    pub fn claim_from_stealth(
+        ctx: Context<ClaimFromStealth>,
+    ) -> Result<()> {
+        let stealth_address = &mut ctx.accounts.stealth_address;
+        
+        require!(!stealth_address.is_spent, BridgeError::StealthAddressSpent);
+        require!(stealth_address.owner == ctx.accounts.owner.key(), BridgeError::Unauthorized);
+        
+        let balance = ctx.accounts.stealth_token_account.amount;
+        
+        let seeds = &[
+            b"stealth",
+            stealth_address.owner.as_ref(),
+            stealth_address.ephemeral_pub_key.as_ref(),
+            &[stealth_address.bump],
+        ];
+        let signer = &[&seeds[..]];
+        
+        token_interface::transfer_checked(
+            CpiContext::new_with_signer(
+                ctx.accounts.token_program.to_account_info(),
+                TransferChecked {
+                    from: ctx.accounts.stealth_token_account.to_account_info(),
+                    mint: ctx.accounts.mint.to_account_info(),
+                    to: ctx.accounts.owner_token_account.to_account_info(),
+                    authority: stealth_address.to_account_info(),
+                },
+                signer,
+            ),
+            balance,
+            ctx.accounts.mint.decimals,
+        )?;
+        
+        stealth_address.is_spent = true;
+        
+        emit!(StealthPaymentClaimed {
+            owner: ctx.accounts.owner.key(),
+            stealth_address: stealth_address.key(),
+            amount: balance,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+        
+        Ok(())
+    }
+
+    /// Closes a claimed stealth address and its emptied token account and
+    /// returns the rent of both to whoever paid for the stealth address
+    pub fn close_stealth_address(ctx: Context<CloseStealthAddress>) -> Result<()> {
+        let stealth_address = &ctx.accounts.stealth_address;
+        
+        require!(stealth_address.is_spent, BridgeError::StealthAddressNotSpent);
+        
+        let seeds = &[
+            b"stealth",
+            stealth_address.owner.as_ref(),
+            stealth_address.ephemeral_pub_key.as_ref(),
+            &[stealth_address.bump],
+        ];
+        let signer = &[&seeds[..]];
+        
+        token_interface::close_account(CpiContext::new_with_signer(
+            ctx.accounts.token_program.to_account_info(),
+            CloseAccount {
+                account: ctx.accounts.stealth_token_account.to_account_info(),
+                destination: ctx.accounts.payer.to_account_info(),
+                authority: stealth_address.to_account_info(),
+            },
+            signer,
+        ))?;
+        
+        emit!(StealthAddressClosed {
+            owner: stealth_address.owner,
+            stealth_address: stealth_address.key(),
+            ephemeral_pub_key: stealth_address.ephemeral_pub_key,
+            payer: stealth_address.payer,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+        
+        Ok(())
+    }
+
+    pub fn add_operator(
+        ctx: Context<AddOperator>,
+        operator: Pubkey,
+    ) -> Result<()> {
+        require!(
+            ctx.accounts.authority.key() == ctx.accounts.bridge_state.authority,
+            BridgeError::Unauthorized
+        );
+        require!(
+            ctx.accounts.operator_bond.amount >= ctx.accounts.bridge_state.min_operator_bond,
+            BridgeError::InsufficientOperatorBond
+        );
+        
+        let operator_set = &mut ctx.accounts.operator_set;
+        require!(!operator_set.contains(&operator), BridgeError::OperatorAlreadyExists);
+        require!(operator_set.operators.len() < MAX_OPERATORS, BridgeError::OperatorSetFull);
+        
+        // Appending keeps every existing index, so in-flight attestations stay valid
+        operator_set.operators.push(operator);
+        
+        emit!(OperatorAdded {
+            operator,
+            operator_count: operator_set.operators.len() as u8,
+            threshold: operator_set.threshold,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+        
+        Ok(())
+    }
+
+    pub fn remove_operator(
+        ctx: Context<ManageOperators>,
+        operator: Pubkey,
+    ) -> Result<()> {
+        require!(
+            ctx.accounts.authority.key() == ctx.accounts.bridge_state.authority,
+            BridgeError::Unauthorized
+        );
+        
+        let operator_set = &mut ctx.accounts.operator_set;
+        require!(operator_set.contains(&operator), BridgeError::OperatorNotFound);
+        require!(
+            operator_set.operators.len() > operator_set.threshold as usize,
+            BridgeError::InvalidThreshold
+        );
+        
+        operator_set.remove(&operator);
+        
+        emit!(OperatorRemoved {
+            operator,
+            operator_count: operator_set.operators.len() as u8,
+            threshold: operator_set.threshold,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+        
+        Ok(())
+    }
+
+    pub fn set_operator_threshold(
+        ctx: Context<ManageOperators>,
+        threshold: u8,
+    ) -> Result<()> {
+        require!(
+            ctx.accounts.authority.key() == ctx.accounts.bridge_state.authority,
+            BridgeError::Unauthorized
+        );
+        
+        let operator_set = &mut ctx.accounts.operator_set;
+        require!(
+            threshold > 0 && threshold as usize <= operator_set.operators.len(),
+            BridgeError::InvalidThreshold
+        );
+        
+        let old_threshold = operator_set.threshold;
+        operator_set.threshold = threshold;
+        
+        emit!(OperatorThresholdUpdated {
+            old_threshold,
+            new_threshold: threshold,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+        
+        Ok(())
+    }
+
+    pub fn initialize_bond_vault(ctx: Context<InitializeBondVault>) -> Result<()> {
+        require!(
+            ctx.accounts.authority.key() == ctx.accounts.bridge_state.authority,
+            BridgeError::Unauthorized
+        );
+        
+        Ok(())
+    }
+
+    pub fn set_bond_params(
+        ctx: Context<SetBondParams>,
+        min_operator_bond: u64,
+        unbonding_delay: i64,
+    ) -> Result<()> {
+        let bridge = &mut ctx.accounts.bridge_state;
+        require!(ctx.accounts.authority.key() == bridge.authority, BridgeError::Unauthorized);
+        require!(unbonding_delay >= 0, BridgeError::InvalidConfig);
+        
+        bridge.min_operator_bond = min_operator_bond;
+        bridge.unbonding_delay = unbonding_delay;
+        
+        emit!(BondParamsUpdated {
+            min_operator_bond,
+            unbonding_delay,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+        
+        Ok(())
+    }
+
+    /// Stakes wrapped ZEC in the bond vault. Anyone may bond, but only
+    /// operators bonded at or above `min_operator_bond` can be added.
+    pub fn bond_operator(ctx: Context<BondOperator>, amount: u64) -> Result<()> {
+        let received = amount.checked_sub(transfer_fee(&ctx.accounts.mint, amount)?).unwrap();
+        
+        token_interface::transfer_checked(
+            CpiContext::new(
+                ctx.accounts.token_program.to_account_info(),
+                TransferChecked {
+                    from: ctx.accounts.operator_token_account.to_account_info(),
+                    mint: ctx.accounts.mint.to_account_info(),
+                    to: ctx.accounts.bond_vault.to_account_info(),
+                    authority: ctx.accounts.operator.to_account_info(),
+                },
+            ),
+            amount,
+            ctx.accounts.mint.decimals,
+        )?;
+        
+        let bond = &mut ctx.accounts.operator_bond;
+        bond.operator = ctx.accounts.operator.key();
+        bond.amount = bond.amount.checked_add(received).unwrap();
+        bond.bump = ctx.bumps.operator_bond;
+        
+        emit!(OperatorBonded {
+            operator: bond.operator,
+            amount: received,
+            total_bond: bond.amount,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+        
+        Ok(())
+    }
+
+    /// Moves part of the bond into unbonding. It stays slashable until it
+    /// is withdrawn after `unbonding_delay`; starting again resets the clock.
+    pub fn begin_unbonding(ctx: Context<BeginUnbonding>, amount: u64) -> Result<()> {
+        let bridge = &ctx.accounts.bridge_state;
+        let bond = &mut ctx.accounts.operator_bond;
+        let now = Clock::get()?.unix_timestamp;
+        
+        require!(amount > 0 && amount <= bond.amount, BridgeError::InsufficientOperatorBond);
+        let remaining = bond.amount - amount;
+        // Active operators must stay bonded until they are removed
+        require!(
+            !ctx.accounts.operator_set.contains(&bond.operator) || remaining >= bridge.min_operator_bond,
+            BridgeError::InsufficientOperatorBond
+        );
+        
+        bond.amount = remaining;
+        bond.unbonding_amount = bond.unbonding_amount.checked_add(amount).unwrap();
+        bond.unbonding_available_at = now.checked_add(bridge.unbonding_delay).unwrap();
+        
+        emit!(OperatorUnbonding {
+            operator: bond.operator,
+            amount,
+            available_at: bond.unbonding_available_at,
+            timestamp: now,
+        });
+        
+        Ok(())
+    }
+
+    pub fn withdraw_unbonded(ctx: Context<WithdrawUnbonded>) -> Result<()> {
+        let bond = &mut ctx.accounts.operator_bond;
+        let now = Clock::get()?.unix_timestamp;
+        
+        let amount = bond.unbonding_amount;
+        require!(amount > 0, BridgeError::InsufficientOperatorBond);
+        require!(now >= bond.unbonding_available_at, BridgeError::UnbondingNotReady);
+        
+        bond.unbonding_amount = 0;
+        bond.unbonding_available_at = 0;
+        
+        let bump = ctx.accounts.bridge_state.bump;
+        let seeds: &[&[u8]] = &[
+            b"bridge",
+            &[bump],
+        ];
+        let signer_seeds = &[seeds];
+        
+        token_interface::transfer_checked(
+            CpiContext::new_with_signer(
+                ctx.accounts.token_program.to_account_info(),
+                TransferChecked {
+                    from: ctx.accounts.bond_vault.to_account_info(),
+                    mint: ctx.accounts.mint.to_account_info(),
+                    to: ctx.accounts.operator_token_account.to_account_info(),
+                    authority: ctx.accounts.bridge_state.to_account_info(),
+                },
+                signer_seeds,
+            ),
+            amount,
+            ctx.accounts.mint.decimals,
+        )?;
+        
+        emit!(OperatorUnbonded {
+            operator: bond.operator,
+            amount,
+            timestamp: now,
+        });
+        
+        Ok(())
+    }
+
+    /// Moves up to `amount` of an operator's bond, active stake first and
+    /// then any unbonding stake, to an affected user or the treasury. An
+    /// operator left below `min_operator_bond` is ejected from the set.
+    pub fn slash_operator(ctx: Context<SlashOperator>, amount: u64) -> Result<()> {
+        let bridge = &ctx.accounts.bridge_state;
+        require!(ctx.accounts.authority.key() == bridge.authority, BridgeError::Unauthorized);
+        
+        let bond = &mut ctx.accounts.operator_bond;
+        let slashed = slash_bond(
+            bond,
+            amount,
+            bridge,
+            &ctx.accounts.bond_vault,
+            &ctx.accounts.recipient_token_account,
+            &ctx.accounts.mint,
+            &ctx.accounts.token_program,
+        )?;
+        require!(slashed > 0, BridgeError::InsufficientOperatorBond);
+        
+        if bond.amount < bridge.min_operator_bond {
+            eject_operator(&mut ctx.accounts.operator_set, &bond.operator)?;
+        }
+        
+        Ok(())
+    }
+
+    /// Removes an operator whose active bond is below `min_operator_bond`,
+    /// e.g. after the minimum was raised. Such an operator can no longer
+    /// attest, so it should not count towards the set. Anyone may call this.
+    pub fn eject_underbonded_operator(ctx: Context<EjectUnderbondedOperator>) -> Result<()> {
+        let bond = &ctx.accounts.operator_bond;
+        require!(
+            bond.amount < ctx.accounts.bridge_state.min_operator_bond,
+            BridgeError::OperatorSufficientlyBonded
+        );
+        require!(ctx.accounts.operator_set.contains(&bond.operator), BridgeError::OperatorNotFound);
+        
+        eject_operator(&mut ctx.accounts.operator_set, &bond.operator)
+    }
+
+    pub fn collect_fees(ctx: Context<CollectFees>) -> Result<()> {
+        let bridge = &ctx.accounts.bridge_state;
+        require!(ctx.accounts.authority.key() == bridge.authority, BridgeError::Unauthorized);
+        require!(!bridge.is_paused, BridgeError::BridgePaused);
+        
+        let amount = ctx.accounts.asset_config.accrued_fees;
+        require!(amount > 0, BridgeError::NoFeesToCollect);
+        
+        let bump = bridge.bump;
+        let seeds: &[&[u8]] = &[
+            b"bridge",
+            &[bump],
+        ];
+        let signer_seeds = &[seeds];
+        
+        token_interface::transfer_checked(
+            CpiContext::new_with_signer(
+                ctx.accounts.token_program.to_account_info(),
+                TransferChecked {
+                    from: ctx.accounts.vault.to_account_info(),
+                    mint: ctx.accounts.mint.to_account_info(),
+                    to: ctx.accounts.treasury_token_account.to_account_info(),
+                    authority: ctx.accounts.bridge_state.to_account_info(),
+                },
+                signer_seeds,
+            ),
+            amount,
+            ctx.accounts.mint.decimals,
+        )?;
+        
+        let asset = &mut ctx.accounts.asset_config;
+        asset.accrued_fees = 0;
+        asset.total_fees_collected = asset.total_fees_collected.checked_add(amount).unwrap();
+        
+        emit!(FeesCollected {
+            mint: asset.mint,
+            amount,
+            treasury: ctx.accounts.treasury_token_account.key(),
+            total_fees_collected: asset.total_fees_collected,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+        
+        Ok(())
+    }
+
+    /// Updates the deposit timeout and the limits and protocol fee of one
+    /// asset. Fee decreases apply immediately; fee increases only take
+    /// effect after FEE_INCREASE_DELAY.
+    pub fn update_config(
+        ctx: Context<UpdateConfig>,
+        params: UpdateConfigParams,
+    ) -> Result<()> {
+        let bridge = &mut ctx.accounts.bridge_state;
+        let asset = &mut ctx.accounts.asset_config;
+        require!(ctx.accounts.authority.key() == bridge.authority, BridgeError::Unauthorized);
+        
+        require!(params.deposit_timeout > 0, BridgeError::InvalidConfig);
+        require!(params.min_deposit <= params.max_deposit, BridgeError::InvalidConfig);
+        require!(params.protocol_fee_bps <= MAX_PROTOCOL_FEE_BPS, BridgeError::FeeTooHigh);
+        
+        let now = Clock::get()?.unix_timestamp;
+        
+        // Settle a scheduled increase that is already due before comparing
+        asset.protocol_fee_bps = asset.fee_bps_at(now);
+        asset.pending_fee_bps = 0;
+        asset.pending_fee_effective_at = 0;
+        
+        let old_min_deposit = asset.min_deposit;
+        let old_max_deposit = asset.max_deposit;
+        let old_protocol_fee_bps = asset.protocol_fee_bps;
+        let old_deposit_timeout = bridge.deposit_timeout;
+        
+        asset.min_deposit = params.min_deposit;
+        asset.max_deposit = params.max_deposit;
+        bridge.deposit_timeout = params.deposit_timeout;
+        
+        let fee_effective_at = if params.protocol_fee_bps > asset.protocol_fee_bps {
+            asset.pending_fee_bps = params.protocol_fee_bps;
+            asset.pending_fee_effective_at = now.checked_add(FEE_INCREASE_DELAY).unwrap();
+            asset.pending_fee_effective_at
+        } else {
+            asset.protocol_fee_bps = params.protocol_fee_bps;
+            now
+        };
+        
+        emit!(ConfigUpdated {
+            mint: asset.mint,
+            old_min_deposit,
+            new_min_deposit: asset.min_deposit,
+            old_max_deposit,
+            new_max_deposit: asset.max_deposit,
+            old_protocol_fee_bps,
+            new_protocol_fee_bps: params.protocol_fee_bps,
+            fee_effective_at,
+            old_deposit_timeout,
+            new_deposit_timeout: bridge.deposit_timeout,
+            timestamp: now,
+        });
+        
+        Ok(())
+    }
+
+    pub fn propose_authority(
+        ctx: Context<ProposeAuthority>,
+        new_authority: Pubkey,
+    ) -> Result<()> {
+        let bridge = &mut ctx.accounts.bridge_state;
+        require!(ctx.accounts.authority.key() == bridge.authority, BridgeError::Unauthorized);
+        
+        bridge.pending_authority = new_authority;
+        
+        emit!(AuthorityProposed {
+            authority: bridge.authority,
+            pending_authority: new_authority,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+        
+        Ok(())
+    }
+
+    pub fn accept_authority(ctx: Context<AcceptAuthority>) -> Result<()> {
+        let bridge = &mut ctx.accounts.bridge_state;
+        require!(bridge.pending_authority != Pubkey::default(), BridgeError::NoPendingAuthority);
+        require!(ctx.accounts.new_authority.key() == bridge.pending_authority, BridgeError::Unauthorized);
+        
+        let old_authority = bridge.authority;
+        bridge.authority = bridge.pending_authority;
+        bridge.pending_authority = Pubkey::default();
+        
+        emit!(AuthorityTransferred {
+            old_authority,
+            new_authority: bridge.authority,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+        
+        Ok(())
+    }
+
+    pub fn set_guardian(
+        ctx: Context<SetGuardian>,
+        guardian: Pubkey,
+    ) -> Result<()> {
+        let bridge = &mut ctx.accounts.bridge_state;
+        require!(ctx.accounts.authority.key() == bridge.authority, BridgeError::Unauthorized);
+        
+        let old_guardian = bridge.guardian;
+        bridge.guardian = guardian;
+        
+        emit!(GuardianUpdated {
+            old_guardian,
+            new_guardian: guardian,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+        
+        Ok(())
+    }
+
+    /// Sets the ceiling on outstanding supply of a mint-and-burn asset.
+    /// Deposits that would mint past it are rejected at confirmation; a cap
+    /// below the current supply only stops new mints.
+    pub fn set_supply_cap(ctx: Context<SetSupplyCap>, supply_cap: u64) -> Result<()> {
+        require!(
+            ctx.accounts.authority.key() == ctx.accounts.bridge_state.authority,
+            BridgeError::Unauthorized
+        );
+        
+        let asset = &mut ctx.accounts.asset_config;
+        require!(asset.custody_mode == CustodyMode::MintBurn, BridgeError::InvalidConfig);
+        
+        let old_supply_cap = asset.supply_cap;
+        asset.supply_cap = supply_cap;
+        
+        emit!(SupplyCapUpdated {
+            mint: asset.mint,
+            old_supply_cap,
+            new_supply_cap: supply_cap,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+        
+        Ok(())
+    }
+
+    /// Folds the deposit shards of an asset into `total_deposited`. Every
+    /// shard PDA must be passed in index order through `remaining_accounts`;
+    /// shards nobody has deposited into yet may be passed uninitialized.
+    pub fn sync_deposit_totals(ctx: Context<SyncDepositTotals>) -> Result<()> {
+        let asset = &mut ctx.accounts.asset_config;
+        let totals = sum_deposit_shards(ctx.remaining_accounts, &asset.mint, ctx.program_id)?;
+        
+        asset.total_deposited = totals.total_deposited;
+        
+        emit!(DepositTotalsSynced {
+            mint: asset.mint,
+            total_deposited: totals.total_deposited,
+            deposit_count: totals.deposit_count,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+        
+        Ok(())
+    }
+
+    /// Checks that an asset's vault still covers everything it may have to
+    /// pay out: refundable deposits and withdrawals plus uncollected fees.
+    /// In mint-and-burn mode the wrapped supply must also stay within the
+    /// ZEC locked on the Zcash side. Anyone may call this; a failed check
+    /// pauses the bridge. Deposit shards are passed as for
+    /// `sync_deposit_totals`.
+    pub fn audit_reserves(ctx: Context<AuditReserves>) -> Result<()> {
+        let asset = &ctx.accounts.asset_config;
+        let pending_deposits = sum_deposit_shards(ctx.remaining_accounts, &asset.mint, ctx.program_id)?.pending_escrow;
+        
+        let liabilities = pending_deposits
+            .checked_add(asset.pending_withdrawals)
+            .and_then(|total| total.checked_add(asset.accrued_fees))
+            .ok_or(BridgeError::Overflow)?;
+        let reserves = ctx.accounts.vault.amount;
+        let supply = ctx.accounts.mint.supply;
+        let solvent = reserves >= liabilities
+            && (asset.custody_mode != CustodyMode::MintBurn || supply <= asset.locked_amount);
+        let now = Clock::get()?.unix_timestamp;
+        
+        emit!(ReservesAudited {
+            mint: asset.mint,
+            reserves,
+            pending_deposits,
+            pending_withdrawals: asset.pending_withdrawals,
+            accrued_fees: asset.accrued_fees,
+            liabilities,
+            supply,
+            locked_amount: asset.locked_amount,
+            solvent,
+            timestamp: now,
+        });
+        
+        let bridge = &mut ctx.accounts.bridge_state;
+        if !solvent && !bridge.is_paused {
+            bridge.is_paused = true;
+            
+            emit!(BridgePausedEvent {
+                paused_by: ctx.accounts.caller.key(),
+                timestamp: now,
+            });
+        }
+        
+        Ok(())
+    }
+
+    /// Sets the rolling-window cap on completed withdrawals of one asset;
+    /// a zero window or cap disables it
+    pub fn set_outflow_limit(
+        ctx: Context<SetOutflowLimit>,
+        window_seconds: i64,
+        max_outflow: u64,
+    ) -> Result<()> {
+        require!(
+            ctx.accounts.authority.key() == ctx.accounts.bridge_state.authority,
+            BridgeError::Unauthorized
+        );
+        require!(window_seconds >= 0, BridgeError::InvalidConfig);
+        
+        let asset = &mut ctx.accounts.asset_config;
+        let old_max_outflow = asset.outflow_limiter.max_outflow;
+        let old_window_seconds = asset.outflow_limiter.window_seconds;
+        
+        // Bucket boundaries depend on the window, so start from an empty window
+        asset.outflow_limiter = OutflowLimiter {
+            window_seconds,
+            max_outflow,
+            ..OutflowLimiter::default()
+        };
+        
+        emit!(OutflowLimitUpdated {
+            mint: asset.mint,
+            old_window_seconds,
+            new_window_seconds: window_seconds,
+            old_max_outflow,
+            new_max_outflow: max_outflow,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+        
+        Ok(())
+    }
+
+    /// Enables optimistic processing with a non-zero challenge period, or
+    /// disables it with zero. Only tickets reaching their threshold
+    /// afterwards are affected.
+    pub fn set_optimistic_mode(
+        ctx: Context<SetOptimisticMode>,
+        challenge_period: i64,
+        watcher_bond: u64,
+    ) -> Result<()> {
+        let bridge = &mut ctx.accounts.bridge_state;
+        require!(ctx.accounts.authority.key() == bridge.authority, BridgeError::Unauthorized);
+        require!(challenge_period >= 0, BridgeError::InvalidConfig);
+        require!(challenge_period == 0 || watcher_bond > 0, BridgeError::InvalidConfig);
+        
+        bridge.challenge_period = challenge_period;
+        bridge.watcher_bond = watcher_bond;
+        
+        emit!(OptimisticModeUpdated {
+            challenge_period,
+            watcher_bond,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+        
+        Ok(())
+    }
+
+    /// Either the authority or the guardian may pause; only the authority can unpause
+    pub fn pause_bridge(ctx: Context<PauseBridge>) -> Result<()> {
+        let bridge = &mut ctx.accounts.bridge_state;
+        let pauser = ctx.accounts.pauser.key();
+        require!(
+            pauser == bridge.authority
+                || (bridge.guardian != Pubkey::default() && pauser == bridge.guardian),
+            BridgeError::Unauthorized
+        );
+        
+        bridge.is_paused = true;
+        
+        emit!(BridgePausedEvent {
+            paused_by: pauser,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+        
+        Ok(())
+    }
+
+    pub fn unpause_bridge(ctx: Context<UnpauseBridge>) -> Result<()> {
+        let bridge = &mut ctx.accounts.bridge_state;
+        require!(ctx.accounts.authority.key() == bridge.authority, BridgeError::Unauthorized);
+        
+        bridge.is_paused = false;
+        
+        emit!(BridgeUnpausedEvent {
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+        
+        Ok(())
+    }
+}
+
+fn hash_zcash_receiver(receiver: &[u8; zcash_address::SHIELDED_RECEIVER_LEN]) -> [u8; 32] {
+    use anchor_lang::solana_program::keccak::hash;
+    hash(receiver).to_bytes()
+}
+
+/// Completes a proven withdrawal, or defers it if the outflow limiter has
+/// no room left in the current window
+fn release_withdrawal(
+    asset: &mut AssetConfig,
+    tree: &mut CommitmentTree,
+    withdrawal: &mut Account<WithdrawalTicket>,
+    nullifier_record: &mut NullifierRecord,
+) -> Result<()> {
+    let now = Clock::get()?.unix_timestamp;
+    if !asset.outflow_limiter.try_record(now, withdrawal.amount) {
+        withdrawal.status = WithdrawalStatus::Deferred;
+        // Start over so every operator can still vote to fail the ticket
+        withdrawal.attestations = Attestations::default();
+        
+        emit!(WithdrawalDeferred {
+            ticket_id: withdrawal.ticket_id,
+            amount: withdrawal.amount,
+            window_outflow: asset.outflow_limiter.window_outflow(now),
+            max_outflow: asset.outflow_limiter.max_outflow,
+            timestamp: now,
+        });
+        
+        return Ok(());
+    }
+    
+    complete_withdrawal(asset, tree, withdrawal, nullifier_record)
+}
+
+fn complete_withdrawal(
+    asset: &mut AssetConfig,
+    tree: &mut CommitmentTree,
+    withdrawal: &mut Account<WithdrawalTicket>,
+    nullifier_record: &mut NullifierRecord,
+) -> Result<()> {
+    require!(
+        nullifier_record.withdrawal_ticket == withdrawal.key() && !nullifier_record.is_spent,
+        BridgeError::NullifierAlreadySpent
+    );
+    nullifier_record.is_spent = true;
+    
+    // The tokens were escrowed in the asset vault at initiation, so the
+    // Zcash-side payout only needs to be recorded here
+    withdrawal.status = WithdrawalStatus::Completed;
+    withdrawal.processed_at = Clock::get()?.unix_timestamp;
+    
+    asset.total_withdrawn = asset.total_withdrawn.checked_add(withdrawal.amount).unwrap();
+    asset.accrued_fees = asset.accrued_fees.checked_add(withdrawal.fee).unwrap();
+    asset.release_withdrawal_escrow(withdrawal);
+    
+    append_commitment(tree, withdrawal.partial_note_commitment, withdrawal.processed_at)?;
+    
+    emit!(NullifierSpent {
+        nullifier: withdrawal.partial_note_nullifier,
+        ticket_id: withdrawal.ticket_id,
+        timestamp: withdrawal.processed_at,
+    });
+    
+    emit!(WithdrawalProcessed {
+        ticket_id: withdrawal.ticket_id,
+        recipient: withdrawal.recipient,
+        amount: withdrawal.amount,
+        timestamp: withdrawal.processed_at,
+    });
+    
+    Ok(())
+}
+
+/// Fee a Token-2022 mint withholds when `amount` is transferred in the
+/// current epoch; zero for SPL Token mints and mints without the extension.
+/// The fee never reaches the vault, so tickets are sized on what is left.
+fn transfer_fee(mint: &InterfaceAccount<Mint>, amount: u64) -> Result<u64> {
+    use spl_token_2022::extension::transfer_fee::TransferFeeConfig;
+    use spl_token_2022::extension::{BaseStateWithExtensions, StateWithExtensions};
+    
+    let mint_info = mint.to_account_info();
+    if *mint_info.owner != spl_token_2022::ID {
+        return Ok(0);
+    }
+    
+    let data = mint_info.try_borrow_data()?;
+    let state = StateWithExtensions::<spl_token_2022::state::Mint>::unpack(&data)?;
+    match state.get_extension::<TransferFeeConfig>() {
+        Ok(config) => Ok(config
+            .calculate_epoch_fee(Clock::get()?.epoch, amount)
+            .ok_or(BridgeError::Overflow)?),
+        Err(_) => Ok(0),
+    }
+}
+
+/// Rejects Token-2022 mints with extensions that would let someone other
+/// than the bridge move, freeze or block the tokens it holds
+fn check_mint_extensions(mint_data: &[u8]) -> Result<()> {
+    use spl_token_2022::extension::default_account_state::DefaultAccountState;
+    use spl_token_2022::extension::{BaseStateWithExtensions, ExtensionType, StateWithExtensions};
+    use spl_token_2022::state::AccountState;
+    
+    let state = StateWithExtensions::<spl_token_2022::state::Mint>::unpack(mint_data)?;
+    for extension in state.get_extension_types()? {
+        match extension {
+            ExtensionType::TransferFeeConfig
+            | ExtensionType::MetadataPointer
+            | ExtensionType::TokenMetadata => {}
+            // The vault and depositor accounts must not start out frozen
+            ExtensionType::DefaultAccountState => {
+                let default = state.get_extension::<DefaultAccountState>()?;
+                require!(
+                    default.state == AccountState::Initialized as u8,
+                    BridgeError::UnsupportedMintExtension
+                );
+            }
+            _ => return err!(BridgeError::UnsupportedMintExtension),
+        }
+    }
+    
+    Ok(())
+}
+
+/// Mints a confirmed deposit to the depositor, and its fee into the vault
+/// where it accrues, if the asset uses mint-and-burn custody. The ZEC is
+/// already in custody by then, so the deposit completes here.
+#[allow(clippy::too_many_arguments)]
+fn mint_confirmed_deposit<'info>(
+    asset: &mut AssetConfig,
+    shard: &mut DepositShard,
+    tree: &mut CommitmentTree,
+    deposit: &mut DepositTicket,
+    bridge_state: &Account<'info, BridgeState>,
+    mint: &InterfaceAccount<'info, Mint>,
+    vault: &InterfaceAccount<'info, TokenAccount>,
+    depositor_token_account: &InterfaceAccount<'info, TokenAccount>,
+    token_program: &Interface<'info, TokenInterface>,
+) -> Result<()> {
+    if asset.custody_mode != CustodyMode::MintBurn {
+        return Ok(());
+    }
+    
+    let minted = deposit.amount.checked_add(deposit.fee).unwrap();
+    let supply = mint.supply.checked_add(minted).ok_or(BridgeError::Overflow)?;
+    require!(supply <= asset.supply_cap, BridgeError::SupplyCapExceeded);
+    
+    let bump = bridge_state.bump;
+    let seeds: &[&[u8]] = &[
+        b"bridge",
+        &[bump],
+    ];
+    let signer_seeds = &[seeds];
+    
+    token_interface::mint_to(
+        CpiContext::new_with_signer(
+            token_program.to_account_info(),
+            MintTo {
+                mint: mint.to_account_info(),
+                to: depositor_token_account.to_account_info(),
+                authority: bridge_state.to_account_info(),
+            },
+            signer_seeds,
+        ),
+        deposit.amount,
+    )?;
+    
+    if deposit.fee > 0 {
+        token_interface::mint_to(
+            CpiContext::new_with_signer(
+                token_program.to_account_info(),
+                MintTo {
+                    mint: mint.to_account_info(),
+                    to: vault.to_account_info(),
+                    authority: bridge_state.to_account_info(),
+                },
+                signer_seeds,
+            ),
+            deposit.fee,
+        )?;
+    }
+    
+    asset.locked_amount = asset.locked_amount.checked_add(minted).unwrap();
+    
+    emit!(WrappedSupplyChanged {
+        mint: asset.mint,
+        supply,
+        locked_amount: asset.locked_amount,
+        timestamp: Clock::get()?.unix_timestamp,
+    });
+    
+    // No note is delivered on Zcash for a minted deposit
+    record_deposit_completion(shard, tree, deposit, [0u8; 32], [0u8; 32])
+}
+
+/// Burns the escrowed amount of a completed withdrawal, leaving its fee in
+/// the vault, if the asset uses mint-and-burn custody
+fn burn_completed_withdrawal<'info>(
+    asset: &mut AssetConfig,
+    withdrawal: &WithdrawalTicket,
+    bridge_state: &Account<'info, BridgeState>,
+    mint: &InterfaceAccount<'info, Mint>,
+    vault: &InterfaceAccount<'info, TokenAccount>,
+    token_program: &Interface<'info, TokenInterface>,
+) -> Result<()> {
+    if asset.custody_mode != CustodyMode::MintBurn || withdrawal.status != WithdrawalStatus::Completed {
+        return Ok(());
+    }
+    
+    let bump = bridge_state.bump;
+    let seeds: &[&[u8]] = &[
+        b"bridge",
+        &[bump],
+    ];
+    let signer_seeds = &[seeds];
+    
+    token_interface::burn(
+        CpiContext::new_with_signer(
+            token_program.to_account_info(),
+            Burn {
+                mint: mint.to_account_info(),
+                from: vault.to_account_info(),
+                authority: bridge_state.to_account_info(),
+            },
+            signer_seeds,
+        ),
+        withdrawal.amount,
+    )?;
+    
+    asset.locked_amount = asset.locked_amount.saturating_sub(withdrawal.amount);
+    
+    emit!(WrappedSupplyChanged {
+        mint: asset.mint,
+        supply: mint.supply.saturating_sub(withdrawal.amount),
+        locked_amount: asset.locked_amount,
+        timestamp: Clock::get()?.unix_timestamp,
+    });
+    
+    Ok(())
+}
+
+/// Checks that a mint-and-burn deposit was paid in: `funding` must open a
+/// note of at least the amount to mint, sent to the asset's custody address
+/// by the confirming transaction. Vault deposits carry no funding.
+fn verify_deposit_funding(
+    asset: &AssetConfig,
+    deposit: &DepositTicket,
+    zcash_tx_id: &[u8; 32],
+    funding: &Option<DeliveryProof>,
+) -> Result<()> {
+    let funding = match (asset.custody_mode, funding) {
+        (CustodyMode::Vault, None) => return Ok(()),
+        (CustodyMode::MintBurn, Some(funding)) => funding,
+        _ => return err!(BridgeError::FundingMismatch),
+    };
+    
+    require!(
+        funding.zcash_tx_id == *zcash_tx_id
+            && funding.receiver == asset.custody_receiver
+            && funding.value >= deposit.amount.checked_add(deposit.fee).unwrap()
+            && funding.note_commitment != [0u8; 32],
+        BridgeError::FundingMismatch
+    );
+    verify_note_in_transaction(funding, deposit.zcash_pool)
+}
+
+/// Checks that the opened note is an output, in `pool`, of the transaction
+/// it names
+fn verify_note_in_transaction(note: &DeliveryProof, pool: ZcashPool) -> Result<()> {
+    require!(
+        note.tx.bundle.pool() == pool
+            && zcash_tx::has_output(&note.tx.bundle, &note.note_commitment)
+            && zcash_tx::tx_id(&note.tx) == note.zcash_tx_id,
+        BridgeError::NoteNotInTransaction
+    );
+    Ok(())
+}
+
+/// Binds a Zcash transaction to the deposit it confirms, failing if it
+/// already confirmed another one
+fn consume_zcash_tx(record: &mut ZcashTxRecord, zcash_tx_id: &[u8; 32], deposit: &Pubkey, bump: u8) -> Result<()> {
+    require!(record.deposit_ticket == Pubkey::default(), BridgeError::ZcashTxAlreadyUsed);
+    record.zcash_tx_id = *zcash_tx_id;
+    record.deposit_ticket = *deposit;
+    record.consumed_at = Clock::get()?.unix_timestamp;
+    record.bump = bump;
+    Ok(())
+}
+
+/// Loads the record of a Zcash transaction from a batch, creating it at the
+/// operator's expense the first time the transaction is seen. Returns the
+/// record with its bump.
+fn load_batch_zcash_tx_record<'info>(
+    info: &'info AccountInfo<'info>,
+    zcash_tx_id: &[u8; 32],
+    payer: &Signer<'info>,
+    system_program: &Program<'info, System>,
+    program_id: &Pubkey,
+) -> Result<(Account<'info, ZcashTxRecord>, u8)> {
+    let (expected, bump) = Pubkey::find_program_address(&[b"zcash_tx", zcash_tx_id], program_id);
+    require!(info.key() == expected, BridgeError::InvalidBatchTicket);
+    
+    if info.owner == &system_program::ID {
+        let seeds: &[&[u8]] = &[b"zcash_tx", zcash_tx_id, &[bump]];
+        let signer_seeds = &[seeds];
+        
+        // Anyone may have sent lamports to the address, so top it up rather
+        // than creating it outright
+        let shortfall = Rent::get()?.minimum_balance(ZcashTxRecord::LEN).saturating_sub(info.lamports());
+        if shortfall > 0 {
+            system_program::transfer(
+                CpiContext::new(
+                    system_program.to_account_info(),
+                    system_program::Transfer {
+                        from: payer.to_account_info(),
+                        to: info.clone(),
+                    },
+                ),
+                shortfall,
+            )?;
+        }
+        system_program::allocate(
+            CpiContext::new_with_signer(
+                system_program.to_account_info(),
+                system_program::Allocate { account_to_allocate: info.clone() },
+                signer_seeds,
+            ),
+            ZcashTxRecord::LEN as u64,
+        )?;
+        system_program::assign(
+            CpiContext::new_with_signer(
+                system_program.to_account_info(),
+                system_program::Assign { account_to_assign: info.clone() },
+                signer_seeds,
+            ),
+            program_id,
+        )?;
+        info.try_borrow_mut_data()?[..8].copy_from_slice(&ZcashTxRecord::DISCRIMINATOR);
+    }
+    
+    Ok((Account::try_from(info)?, bump))
+}
+
+/// Checks a confirmation of a leased deposit and records the operator's
+/// attestation, returning the number of operators that have attested so
+/// far. The threshold only counts once the lease holder is among them.
+#[allow(clippy::too_many_arguments)]
+fn attest_deposit(
+    deposit: &mut DepositTicket,
+    asset: &AssetConfig,
+    operator_set: &OperatorSet,
+    light_client: &ZcashLightClient,
+    bond: &OperatorBond,
+    min_bond: u64,
+    zcash_tx_id: &[u8; 32],
+    proof: &DepositProof,
+    inclusion: &ZcashTxInclusion,
+    funding: &Option<DeliveryProof>,
+) -> Result<u8> {
+    require!(deposit.status == DepositStatus::Processing, BridgeError::InvalidDepositStatus);
+    require!(
+        Clock::get()?.unix_timestamp < deposit.lease_expires_at,
+        BridgeError::LeaseExpired
+    );
+    
+    require!(verify_deposit_proof(proof, deposit), BridgeError::InvalidProof);
+    verify_deposit_funding(asset, deposit, zcash_tx_id, funding)?;
+    light_client.verify_inclusion(zcash_tx_id, inclusion)?;
+    
+    let digest = hash_deposit_attestation(zcash_tx_id, proof, funding);
+    let count = deposit.attestations.record(operator_set, bond, min_bond, digest)?;
+    if count >= operator_set.threshold {
+        require!(
+            deposit.attestations.has_attested(operator_set, &deposit.claimed_by, &digest),
+            BridgeError::LeaseHolderNotAttested
+        );
+    }
+    
+    emit!(DepositAttested {
+        ticket_id: deposit.ticket_id,
+        depositor: deposit.depositor,
+        operator: bond.operator,
+        attestations: count,
+        threshold: operator_set.threshold,
+        timestamp: Clock::get()?.unix_timestamp,
+    });
+    
+    Ok(count)
+}
+
+/// Records the operator's attestation on a leased withdrawal. Returns true
+/// once the threshold is reached, the lease holder is among the attesters
+/// and the proof has been verified.
+fn attest_withdrawal(
+    withdrawal: &mut WithdrawalTicket,
+    operator_set: &OperatorSet,
+    verifying_key: &WithdrawalVerifyingKey,
+    bond: &OperatorBond,
+    min_bond: u64,
+    proof: &WithdrawalProof,
+) -> Result<bool> {
+    require!(withdrawal.status == WithdrawalStatus::Processing, BridgeError::InvalidWithdrawalStatus);
+    require!(
+        Clock::get()?.unix_timestamp < withdrawal.lease_expires_at,
+        BridgeError::LeaseExpired
+    );
+    
+    let digest = hash_withdrawal_attestation(proof);
+    let count = withdrawal.attestations.record(operator_set, bond, min_bond, digest)?;
+    
+    // The proof is only checked once the threshold is reached; every
+    // attestation is bound to the same proof through the digest
+    let proven = count >= operator_set.threshold;
+    if proven {
+        require!(
+            withdrawal.attestations.has_attested(operator_set, &withdrawal.claimed_by, &digest),
+            BridgeError::LeaseHolderNotAttested
+        );
+        require!(
+            verify_withdrawal_proof(proof, withdrawal, verifying_key),
+            BridgeError::InvalidProof
+        );
+    }
+    
+    emit!(WithdrawalAttested {
+        ticket_id: withdrawal.ticket_id,
+        operator: bond.operator,
+        attestations: count,
+        threshold: operator_set.threshold,
+        timestamp: Clock::get()?.unix_timestamp,
+    });
+    
+    Ok(proven)
+}
+
+/// Loads a deposit ticket, its depositor's token account and deposit shard
+/// from a batch
+fn load_batch_deposit<'info>(
+    accounts: &'info [AccountInfo<'info>],
+    mint: &Pubkey,
+    program_id: &Pubkey,
+) -> Result<(
+    Account<'info, DepositTicket>,
+    InterfaceAccount<'info, TokenAccount>,
+    Account<'info, DepositShard>,
+)> {
+    let deposit = Account::<DepositTicket>::try_from(&accounts[0])?;
+    let expected = Pubkey::create_program_address(
+        &[b"deposit", deposit.depositor.as_ref(), &deposit.ticket_id.to_le_bytes(), &[deposit.bump]],
+        program_id,
+    )
+    .map_err(|_| BridgeError::InvalidBatchTicket)?;
+    require!(
+        accounts[0].is_writable && deposit.key() == expected && deposit.mint == *mint,
+        BridgeError::InvalidBatchTicket
+    );
+    
+    let depositor_token_account = InterfaceAccount::<TokenAccount>::try_from(&accounts[1])?;
+    require!(
+        depositor_token_account.owner == deposit.depositor && depositor_token_account.mint == *mint,
+        BridgeError::InvalidBatchTicket
+    );
+    
+    let deposit_shard = Account::<DepositShard>::try_from(&accounts[2])?;
+    let expected = Pubkey::create_program_address(
+        &[b"deposit_shard", mint.as_ref(), &[deposit_shard_index(&deposit.depositor)], &[deposit_shard.bump]],
+        program_id,
+    )
+    .map_err(|_| BridgeError::InvalidBatchTicket)?;
+    require!(
+        accounts[2].is_writable && deposit_shard.key() == expected,
+        BridgeError::InvalidBatchTicket
+    );
+    
+    Ok((deposit, depositor_token_account, deposit_shard))
+}
+
+/// Sums every deposit shard of `mint`, passed in index order. Shards nobody
+/// has deposited into yet may be passed uninitialized.
+fn sum_deposit_shards(shards: &[AccountInfo], mint: &Pubkey, program_id: &Pubkey) -> Result<DepositShardTotals> {
+    require!(shards.len() == DEPOSIT_SHARDS as usize, BridgeError::InvalidDepositShard);
+    
+    let mut totals = DepositShardTotals::default();
+    for (index, info) in shards.iter().enumerate() {
+        let (expected, _) = Pubkey::find_program_address(
+            &[b"deposit_shard", mint.as_ref(), &[index as u8]],
+            program_id,
+        );
+        require!(info.key() == expected, BridgeError::InvalidDepositShard);
+        if info.data_is_empty() {
+            continue;
+        }
+        
+        require!(info.owner == program_id, BridgeError::InvalidDepositShard);
+        let shard = DepositShard::try_deserialize(&mut &info.try_borrow_data()?[..])?;
+        totals.deposit_count = totals.deposit_count.checked_add(shard.deposit_count).unwrap();
+        totals.total_deposited = totals.total_deposited.checked_add(shard.total_deposited).unwrap();
+        totals.pending_escrow = totals.pending_escrow.checked_add(shard.pending_escrow).unwrap();
+    }
+    
+    Ok(totals)
+}
+
+/// Loads an operator's bond passed outside the typed accounts
+fn load_operator_bond<'info>(
+    info: &'info AccountInfo<'info>,
+    program_id: &Pubkey,
+) -> Result<Account<'info, OperatorBond>> {
+    let bond = Account::<OperatorBond>::try_from(info)?;
+    let expected = Pubkey::create_program_address(
+        &[b"operator_bond", bond.operator.as_ref(), &[bond.bump]],
+        program_id,
+    )
+    .map_err(|_| BridgeError::InvalidOperatorBond)?;
+    require!(bond.key() == expected, BridgeError::InvalidOperatorBond);
+    Ok(bond)
+}
+
+/// Loads a withdrawal ticket and the nullifier record it reserved from a batch
+fn load_batch_withdrawal<'info>(
+    accounts: &'info [AccountInfo<'info>],
+    mint: &Pubkey,
+    program_id: &Pubkey,
+) -> Result<(Account<'info, WithdrawalTicket>, Account<'info, NullifierRecord>)> {
+    let withdrawal = Account::<WithdrawalTicket>::try_from(&accounts[0])?;
+    let expected = Pubkey::create_program_address(
+        &[b"withdrawal", &withdrawal.ticket_id.to_le_bytes(), &[withdrawal.bump]],
+        program_id,
+    )
+    .map_err(|_| BridgeError::InvalidBatchTicket)?;
+    require!(
+        accounts[0].is_writable && withdrawal.key() == expected && withdrawal.mint == *mint,
+        BridgeError::InvalidBatchTicket
+    );
+    
+    let nullifier_record = Account::<NullifierRecord>::try_from(&accounts[1])?;
+    let expected = Pubkey::create_program_address(
+        &[b"nullifier", withdrawal.partial_note_nullifier.as_ref(), &[nullifier_record.bump]],
+        program_id,
+    )
+    .map_err(|_| BridgeError::InvalidBatchTicket)?;
+    require!(
+        accounts[1].is_writable && nullifier_record.key() == expected,
+        BridgeError::InvalidBatchTicket
+    );
+    // Checked here so that settling the ticket cannot fail on it later
+    require!(
+        nullifier_record.withdrawal_ticket == withdrawal.key() && !nullifier_record.is_spent,
+        BridgeError::NullifierAlreadySpent
+    );
+    
+    Ok((withdrawal, nullifier_record))
+}
+
+fn start_withdrawal_challenge_period(bridge: &BridgeState, withdrawal: &mut WithdrawalTicket) -> Result<()> {
+    let now = Clock::get()?.unix_timestamp;
+    withdrawal.status = WithdrawalStatus::AwaitingFinality;
+    withdrawal.finalizes_at = now.checked_add(bridge.challenge_period).unwrap();
+    
+    emit!(WithdrawalAwaitingFinality {
+        ticket_id: withdrawal.ticket_id,
+        finalizes_at: withdrawal.finalizes_at,
+        timestamp: now,
+    });
+    
+    Ok(())
+}
+
+fn start_deposit_challenge_period(
+    bridge: &BridgeState,
+    deposit: &mut DepositTicket,
+    zcash_tx_id: [u8; 32],
+) -> Result<()> {
+    let now = Clock::get()?.unix_timestamp;
+    deposit.status = DepositStatus::AwaitingFinality;
+    deposit.zcash_tx_id = zcash_tx_id;
+    deposit.finalizes_at = now.checked_add(bridge.challenge_period).unwrap();
+    
+    emit!(DepositAwaitingFinality {
+        ticket_id: deposit.ticket_id,
+        depositor: deposit.depositor,
+        zcash_tx_id,
+        finalizes_at: deposit.finalizes_at,
+        timestamp: now,
+    });
+    
+    Ok(())
+}
+
+/// Moves up to `amount` of a watcher's bond out of its record, which stays
+/// open with whatever bond remains
+fn forfeit_watcher_bond(record: &mut Account<WatcherRecord>, recipient: &AccountInfo, amount: u64) -> Result<()> {
+    let forfeited = record.bond.min(amount);
+    let record_info = record.to_account_info();
+    **record_info.try_borrow_mut_lamports()? = record_info.lamports().checked_sub(forfeited).unwrap();
+    **recipient.try_borrow_mut_lamports()? = recipient.lamports().checked_add(forfeited).unwrap();
+    record.bond -= forfeited;
+    Ok(())
+}
+
+/// Moves up to `amount` of an operator's bond, active stake first and then
+/// unbonding stake, from the bond vault to `recipient`. Returns the amount moved.
+fn slash_bond<'info>(
+    bond: &mut OperatorBond,
+    amount: u64,
+    bridge_state: &Account<'info, BridgeState>,
+    bond_vault: &InterfaceAccount<'info, TokenAccount>,
+    recipient: &InterfaceAccount<'info, TokenAccount>,
+    mint: &InterfaceAccount<'info, Mint>,
+    token_program: &Interface<'info, TokenInterface>,
+) -> Result<u64> {
+    let from_active = amount.min(bond.amount);
+    let from_unbonding = (amount - from_active).min(bond.unbonding_amount);
+    let slashed = from_active + from_unbonding;
+    if slashed == 0 {
+        return Ok(0);
+    }
+    
+    bond.amount -= from_active;
+    bond.unbonding_amount -= from_unbonding;
+    
+    let bump = bridge_state.bump;
+    let seeds: &[&[u8]] = &[
+        b"bridge",
+        &[bump],
+    ];
+    let signer_seeds = &[seeds];
+    
+    token_interface::transfer_checked(
+        CpiContext::new_with_signer(
+            token_program.to_account_info(),
+            TransferChecked {
+                from: bond_vault.to_account_info(),
+                mint: mint.to_account_info(),
+                to: recipient.to_account_info(),
+                authority: bridge_state.to_account_info(),
+            },
+            signer_seeds,
+        ),
+        slashed,
+        mint.decimals,
+    )?;
+    
+    emit!(OperatorSlashed {
+        operator: bond.operator,
+        amount: slashed,
+        recipient: recipient.key(),
+        remaining_bond: bond.amount,
+        timestamp: Clock::get()?.unix_timestamp,
+    });
+    
+    Ok(slashed)
+}
+
+/// Removes an operator from the set if present. If this leaves fewer
+/// operators than the threshold, nothing settles until the authority adds
+/// operators or lowers the threshold.
+fn eject_operator(operator_set: &mut OperatorSet, operator: &Pubkey) -> Result<()> {
+    if !operator_set.remove(operator) {
+        return Ok(());
+    }
+    
+    emit!(OperatorRemoved {
+        operator: *operator,
+        operator_count: operator_set.operators.len() as u8,
+        threshold: operator_set.threshold,
+        timestamp: Clock::get()?.unix_timestamp,
+    });
+    
+    Ok(())
+}
+
+/// Slashes the whole stake of every operator that attested to the ticket's
+/// settled digest and ejects them. `bonds` must hold their bonds in
+/// operator-set order. Attesters can only be identified while the operator
+/// set is in the epoch they attested in; otherwise `bonds` must be empty
+/// and the authority falls back to `slash_operator`.
+#[allow(clippy::too_many_arguments)]
+fn slash_attesters<'info>(
+    attestations: &Attestations,
+    operator_set: &mut OperatorSet,
+    bonds: &'info [AccountInfo<'info>],
+    program_id: &Pubkey,
+    bridge_state: &Account<'info, BridgeState>,
+    bond_vault: &InterfaceAccount<'info, TokenAccount>,
+    recipient: &InterfaceAccount<'info, TokenAccount>,
+    mint: &InterfaceAccount<'info, Mint>,
+    token_program: &Interface<'info, TokenInterface>,
+) -> Result<()> {
+    let attesters: Vec<Pubkey> = match attestations.settled_digest(operator_set.threshold) {
+        Some(digest) if attestations.epoch == operator_set.epoch => attestations
+            .attesters(&digest)
+            .map(|index| operator_set.operators[index])
+            .collect(),
+        _ => Vec::new(),
+    };
+    require!(bonds.len() == attesters.len(), BridgeError::InvalidOperatorBond);
+    
+    for (operator, info) in attesters.iter().zip(bonds) {
+        let mut bond = load_operator_bond(info, program_id)?;
+        require!(info.is_writable && bond.operator == *operator, BridgeError::InvalidOperatorBond);
+        slash_bond(&mut bond, u64::MAX, bridge_state, bond_vault, recipient, mint, token_program)?;
+        bond.exit(program_id)?;
+        eject_operator(operator_set, operator)?;
+    }
+    
+    Ok(())
+}
+
+/// Returns a deposit's escrow to the depositor, holding back the fee when
+/// `retain_fee` is set, and drops the escrow from its shard. Mint-and-burn
+/// deposits take no tokens up front, so there is nothing to return. Yields
+/// the refunded amount and the retained fee.
+#[allow(clippy::too_many_arguments)]
+fn refund_deposit_escrow<'info>(
+    asset: &AssetConfig,
+    shard: &mut DepositShard,
+    deposit: &DepositTicket,
+    retain_fee: bool,
+    bridge_state: &Account<'info, BridgeState>,
+    mint: &InterfaceAccount<'info, Mint>,
+    vault: &InterfaceAccount<'info, TokenAccount>,
+    depositor_token_account: &InterfaceAccount<'info, TokenAccount>,
+    token_program: &Interface<'info, TokenInterface>,
+) -> Result<(u64, u64)> {
+    let escrow = asset.deposit_escrow(deposit);
+    if escrow == 0 {
+        return Ok((0, 0));
+    }
+    
+    let fee_retained = if retain_fee { deposit.fee } else { 0 };
+    let refund = escrow.checked_sub(fee_retained).unwrap();
+    shard.release_escrow(asset, deposit);
+    
+    if refund > 0 {
+        let bump = bridge_state.bump;
+        let seeds: &[&[u8]] = &[
+            b"bridge",
+            &[bump],
+        ];
+        let signer_seeds = &[seeds];
+        
+        token_interface::transfer_checked(
+            CpiContext::new_with_signer(
+                token_program.to_account_info(),
+                TransferChecked {
+                    from: vault.to_account_info(),
+                    mint: mint.to_account_info(),
+                    to: depositor_token_account.to_account_info(),
+                    authority: bridge_state.to_account_info(),
+                },
+                signer_seeds,
+            ),
+            refund,
+            mint.decimals,
+        )?;
+    }
+    
+    Ok((refund, fee_retained))
+}
+
+fn mark_deposit_confirmed(
+    asset: &mut AssetConfig,
+    shard: &mut DepositShard,
+    deposit: &mut DepositTicket,
+    zcash_tx_id: [u8; 32],
+) -> Result<()> {
+    deposit.status = DepositStatus::Confirmed;
+    deposit.processed_at = Clock::get()?.unix_timestamp;
+    deposit.zcash_tx_id = zcash_tx_id;
+    // Delivery of the payout is attested afresh
+    deposit.attestations = Attestations::default();
+    
+    // The fee is no longer refundable once the deposit is confirmed
+    asset.accrued_fees = asset.accrued_fees.checked_add(deposit.fee).unwrap();
+    shard.release_escrow(asset, deposit);
+    
+    emit!(DepositConfirmed {
+        ticket_id: deposit.ticket_id,
+        depositor: deposit.depositor,
+        zcash_tx_id,
+        timestamp: deposit.processed_at,
+    });
+    
+    Ok(())
+}
+
+/// Marks a deposit completed and counts it towards its shard's totals
+fn record_deposit_completion(
+    shard: &mut DepositShard,
+    tree: &mut CommitmentTree,
+    deposit: &mut DepositTicket,
+    note_commitment: [u8; 32],
+    transcript_hash: [u8; 32],
+) -> Result<()> {
+    deposit.status = DepositStatus::Completed;
+    deposit.processed_at = Clock::get()?.unix_timestamp;
+    deposit.delivery_note_commitment = note_commitment;
+    
+    shard.record_completion(deposit);
+    
+    append_commitment(tree, hash_deposit_commitment(deposit), deposit.processed_at)?;
+    
+    emit!(DepositCompleted {
+        ticket_id: deposit.ticket_id,
+        depositor: deposit.depositor,
+        amount: deposit.amount,
+        note_commitment,
+        transcript_hash,
+        timestamp: deposit.processed_at,
+    });
+    
+    Ok(())
+}
+
+fn append_commitment(tree: &mut CommitmentTree, leaf: [u8; 32], timestamp: i64) -> Result<()> {
+    let (leaf_index, root) = tree.append(leaf)?;
+    
+    emit!(CommitmentAppended {
+        leaf,
+        leaf_index,
+        root,
+        timestamp,
+    });
+    
+    Ok(())
+}
+
+/// Hash of an account's final state, emitted when the account is closed
+fn hash_closed_record<T: AnchorSerialize>(record: &T) -> Result<[u8; 32]> {
+    use anchor_lang::solana_program::keccak::hash;
+    Ok(hash(&record.try_to_vec()?).to_bytes())
+}
+
+/// Leaf committing to a completed deposit and the Zcash transaction that funded it
+fn hash_deposit_commitment(deposit: &DepositTicket) -> [u8; 32] {
+    use anchor_lang::solana_program::keccak::hashv;
+    hashv(&[
+        b"zcash_bridge:deposit",
+        deposit.depositor.as_ref(),
+        &deposit.ticket_id.to_le_bytes(),
+        &deposit.zcash_tx_id,
+        &deposit.zcash_receiver,
+        &deposit.amount.to_le_bytes(),
+    ])
+    .to_bytes()
+}
+
+/// Canonical message operators sign off-chain to confirm a deposit
+pub fn deposit_signing_digest(
+    depositor: &Pubkey,
+    ticket_id: u64,
+    zcash_tx_id: &[u8; 32],
+    amount: u64,
+    funding: &Option<DeliveryProof>,
+) -> [u8; 32] {
+    use anchor_lang::solana_program::keccak::hashv;
+    hashv(&[
+        b"zcash_bridge:confirm_deposit",
+        crate::ID.as_ref(),
+        depositor.as_ref(),
+        &ticket_id.to_le_bytes(),
+        zcash_tx_id,
+        &amount.to_le_bytes(),
+        &hash_funding(funding),
+    ])
+    .to_bytes()
+}
+
+fn hash_deposit_attestation(
+    zcash_tx_id: &[u8; 32],
+    proof: &DepositProof,
+    funding: &Option<DeliveryProof>,
+) -> [u8; 32] {
+    use anchor_lang::solana_program::keccak::hashv;
+    hashv(&[zcash_tx_id, &proof.proof_data, &hash_funding(funding)]).to_bytes()
+}
+
+// Operators vouch for the decrypted receiver and value of a funding note
+// by attesting to its hash; vault deposits hash to zero
+fn hash_funding(funding: &Option<DeliveryProof>) -> [u8; 32] {
+    funding.as_ref().map_or([0u8; 32], hash_delivery_attestation)
+}
+
+fn hash_delivery_attestation(delivery: &DeliveryProof) -> [u8; 32] {
+    use anchor_lang::solana_program::keccak::hashv;
+    hashv(&[
+        &delivery.zcash_tx_id,
+        &delivery.note_commitment,
+        &delivery.receiver,
+        &delivery.value.to_le_bytes(),
+        &delivery.transcript_hash,
+    ])
+    .to_bytes()
+}
+
+fn hash_withdrawal_attestation(proof: &WithdrawalProof) -> [u8; 32] {
+    use anchor_lang::solana_program::keccak::hashv;
+    hashv(&[&proof.proof_data, &proof.commitment, &proof.nullifier]).to_bytes()
+}
+
+/// Digest operators attest to when voting to fail a ticket
+fn hash_failure_attestation(ticket: &Pubkey) -> [u8; 32] {
+    use anchor_lang::solana_program::keccak::hashv;
+    hashv(&[b"zcash_bridge:fail", ticket.as_ref()]).to_bytes()
+}
+
+fn hash_pubkey(pubkey: &Pubkey) -> [u8; 32] {
+    use anchor_lang::solana_program::keccak::hash;
+    hash(pubkey.as_ref()).to_bytes()
+}
+
+fn verify_deposit_proof(proof: &DepositProof, deposit: &DepositTicket) -> bool {
+    // Proof data must not be empty (all zeros is invalid)
+    if proof.proof_data.iter().all(|&x| x == 0) {
+        return false;
+    }
+    
+    // Verify the proof contains valid structure
+    // First 32 bytes: commitment hash
+    // Next 32 bytes: nullifier
+    // Remaining: zk-SNARK proof data
+    let commitment_slice = &proof.proof_data[0..32];
+    
+    // Verify commitment matches expected receiver hash
+    let expected_hash = hash_zcash_receiver(&deposit.zcash_receiver);
+    if commitment_slice != expected_hash.as_slice() {
+        return false;
+    }
+    
+    // Verify public inputs encode the deposit amount correctly
+    let amount_bytes = deposit.amount.to_le_bytes();
+    if proof.public_inputs[0] != amount_bytes {
+        return false;
+    }
+    
+    // Verify depositor pubkey hash is in public inputs
+    let depositor_hash = hash_pubkey(&deposit.depositor);
+    if proof.public_inputs[1] != depositor_hash[0..8].try_into().unwrap_or([0u8; 8]) {
+        return false;
+    }
+    
+    // Verify zk-SNARK proof structure (non-empty proof section)
+    let proof_section = &proof.proof_data[64..];
+    if proof_section.iter().all(|&x| x == 0) {
+        return false;
+    }
+    
+    true
+}
+
+fn hash_verifying_key(vk: &WithdrawalVerifyingKey) -> [u8; 32] {
+    use anchor_lang::solana_program::keccak::hashv;
+    let mut parts: Vec<&[u8]> = vec![&vk.alpha_g1, &vk.beta_g2, &vk.gamma_g2, &vk.delta_g2];
+    parts.extend(vk.ic.iter().map(|point| point.as_slice()));
+    hashv(&parts).to_bytes()
+}
+
+fn verify_withdrawal_proof(
+    proof: &WithdrawalProof,
+    withdrawal: &WithdrawalTicket,
+    vk: &WithdrawalVerifyingKey,
+) -> bool {
+    // Verify commitment matches the withdrawal ticket
+    if proof.commitment != withdrawal.partial_note_commitment {
+        return false;
+    }
+    
+    // Verify nullifier matches the withdrawal ticket
+    if proof.nullifier != withdrawal.partial_note_nullifier {
+        return false;
+    }
+    
+    // Verify nullifier is not all zeros (indicates valid note spend)
+    if proof.nullifier.iter().all(|&x| x == 0) {
+        return false;
+    }
+    
+    // Groth16 pairing check with public inputs bound to the ticket:
+    // [commitment, nullifier, amount]
+    let public_inputs = [
+        withdrawal.partial_note_commitment,
+        withdrawal.partial_note_nullifier,
+        groth16::scalar_from_u64(withdrawal.amount),
+    ];
+    
+    groth16::verify(
+        &groth16::VerifyingKeyRef {
+            alpha_g1: &vk.alpha_g1,
+            beta_g2: &vk.beta_g2,
+            gamma_g2: &vk.gamma_g2,
+            delta_g2: &vk.delta_g2,
+            ic: &vk.ic,
+        },
+        &proof.proof_data,
+        &public_inputs,
+    )
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct BridgeConfig {
+    pub operator: Pubkey,
+    pub min_deposit: u64,
+    pub max_deposit: u64,
+    pub protocol_fee_bps: u16,
+    /// Seconds a deposit may stay pending before anyone can refund it
+    pub deposit_timeout: i64,
+    pub zcash_network: ZcashNetwork,
+    pub custody_mode: CustodyMode,
+    /// Ceiling on wrapped ZEC supply, only used in mint-and-burn mode
+    pub supply_cap: u64,
+    /// Shielded address holding the ZEC behind minted supply, empty in vault mode
+    pub custody_address: String,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct AssetParams {
+    pub min_deposit: u64,
+    pub max_deposit: u64,
+    pub protocol_fee_bps: u16,
+    pub custody_mode: CustodyMode,
+    pub supply_cap: u64,
+    pub custody_address: String,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct ZcashTxInclusion {
+    pub block_hash: [u8; 32],
+    pub tx_index: u32,
+    pub merkle_branch: Vec<[u8; 32]>,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct StoredHeader {
+    pub hash: [u8; 32],
+    pub prev_hash: [u8; 32],
+    pub merkle_root: [u8; 32],
+    pub height: u32,
+    pub time: u32,
+    pub bits: u32,
+    pub cumulative_work: u128,
+}
+
+impl StoredHeader {
+    pub const LEN: usize = 32 + 32 + 32 + 4 + 4 + 4 + 16;
+}
+
+/// The fields of a header the difficulty adjustment reads
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy)]
+pub struct BlockTiming {
+    pub time: u32,
+    pub bits: u32,
+}
+
+impl BlockTiming {
+    pub const LEN: usize = 4 + 4;
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct UpdateConfigParams {
+    pub min_deposit: u64,
+    pub max_deposit: u64,
+    pub protocol_fee_bps: u16,
+    pub deposit_timeout: i64,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct DepositProof {
+    pub proof_data: [u8; 256],
+    pub public_inputs: [[u8; 8]; 4],
+}
+
+/// Evidence that a shielded note reached a Zcash address: a vault deposit's
+/// payout, recovered with the operators' outgoing viewing key, or the note
+/// funding a mint-and-burn deposit, recovered with the custody address's
+/// incoming viewing key
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct DeliveryProof {
+    /// Transaction carrying the note, must match the one the deposit was
+    /// confirmed with
+    pub zcash_tx_id: [u8; 32],
+    /// Note commitment of the payout output
+    pub note_commitment: [u8; 32],
+    /// Receiver and value from the decrypted note plaintext
+    pub receiver: [u8; 43],
+    pub value: u64,
+    /// Hash of the full decryption transcript, kept for auditors
+    pub transcript_hash: [u8; 32],
+    /// The payout transaction, opened far enough to show the note is one
+    /// of its outputs
+    pub tx: PayoutTx,
+}
+
+/// A v5 Zcash transaction reduced to its ZIP 244 digests, with the
+/// shielded pool of the payout note opened
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct PayoutTx {
+    pub consensus_branch_id: u32,
+    pub header_digest: [u8; 32],
+    pub transparent_digest: [u8; 32],
+    /// Digest of the shielded pool that is not opened
+    pub other_pool_digest: [u8; 32],
+    pub bundle: PayoutBundle,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub enum PayoutBundle {
+    Sapling {
+        spends_digest: [u8; 32],
+        /// Every output of the bundle, in transaction order
+        outputs: Vec<SaplingCompactOutput>,
+        memos_digest: [u8; 32],
+        noncompact_digest: [u8; 32],
+        value_balance: i64,
+    },
+    Orchard {
+        /// Every action of the bundle, in transaction order
+        actions: Vec<OrchardCompactAction>,
+        memos_digest: [u8; 32],
+        noncompact_digest: [u8; 32],
+        flags: u8,
+        value_balance: i64,
+        anchor: [u8; 32],
+    },
+}
+
+impl PayoutBundle {
+    pub fn pool(&self) -> ZcashPool {
+        match self {
+            PayoutBundle::Sapling { .. } => ZcashPool::Sapling,
+            PayoutBundle::Orchard { .. } => ZcashPool::Orchard,
+        }
+    }
+}
+
+/// The part of a Sapling output ZIP 244 hashes as compact
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct SaplingCompactOutput {
+    pub cmu: [u8; 32],
+    pub ephemeral_key: [u8; 32],
+    pub enc_ciphertext: [u8; 52],
+}
+
+/// The part of an Orchard action ZIP 244 hashes as compact
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct OrchardCompactAction {
+    pub nullifier: [u8; 32],
+    pub cmx: [u8; 32],
+    pub ephemeral_key: [u8; 32],
+    pub enc_ciphertext: [u8; 52],
+}
+
+/// One entry of `batch_confirm_deposits`
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct DepositConfirmation {
+    pub zcash_tx_id: [u8; 32],
+    pub proof: DepositProof,
+    pub inclusion: ZcashTxInclusion,
+    pub funding: Option<DeliveryProof>,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq)]
+pub enum BatchMode {
+    /// Any rejected ticket fails the whole batch
+    AllOrNothing,
+    /// Rejected tickets are skipped and reported in the batch event
+    BestEffort,
+}
+
+impl BatchMode {
+    /// Passes an accepted ticket through. A rejected ticket comes back as
+    /// None to be skipped in best-effort mode and fails the batch otherwise.
+    pub fn admit<T>(self, ticket: Result<T>) -> Result<Option<T>> {
+        match ticket {
+            Ok(ticket) => Ok(Some(ticket)),
+            Err(_) if self == BatchMode::BestEffort => Ok(None),
+            Err(err) => Err(err),
+        }
+    }
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct VerifyingKeyData {
+    pub alpha_g1: [u8; 64],
+    pub beta_g2: [u8; 128],
+    pub gamma_g2: [u8; 128],
+    pub delta_g2: [u8; 128],
+    pub ic: [[u8; 64]; groth16::NR_PUBLIC_INPUTS + 1],
+}
+
+/// Groth16 proof for the withdrawal circuit.
+/// `proof_data` holds A (G1) || B (G2) || C (G1), uncompressed big-endian.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct WithdrawalProof {
+    pub proof_data: [u8; 256],
+    pub commitment: [u8; 32],
+    pub nullifier: [u8; 32],
+}
+
+/// Operator approvals collected for a ticket. Bit `i` of `mask` is set once
+/// the operator at index `i` of the operator set has attested, and
+/// `digests[i]` holds what it attested to. Operators may disagree; a ticket
+/// settles once any one digest is backed by the threshold.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Default)]
+pub struct Attestations {
+    pub mask: u16,
+    pub epoch: u32,
+    pub digests: [[u8; 32]; MAX_OPERATORS],
+}
+
+impl Attestations {
+    pub const LEN: usize = 2 + 4 + 32 * MAX_OPERATORS;
+
+    /// Records an attestation from the operator owning `bond` and returns
+    /// the number of distinct operators that have attested to `digest` so
+    /// far. Operators bonded below `min_bond` cannot attest.
+    pub fn record(
+        &mut self,
+        operator_set: &OperatorSet,
+        bond: &OperatorBond,
+        min_bond: u64,
+        digest: [u8; 32],
+    ) -> Result<u8> {
+        let index = operator_set
+            .operators
+            .iter()
+            .position(|key| key == &bond.operator)
+            .ok_or(BridgeError::Unauthorized)?;
+        require!(bond.amount >= min_bond, BridgeError::InsufficientOperatorBond);
+        
+        // Attestations from an older operator set no longer count
+        if self.epoch != operator_set.epoch {
+            *self = Attestations {
+                epoch: operator_set.epoch,
+                ..Attestations::default()
+            };
+        }
+        
+        require!(self.mask & (1 << index) == 0, BridgeError::AlreadyAttested);
+        
+        self.mask |= 1 << index;
+        self.digests[index] = digest;
+        Ok(self.count(&digest))
+    }
+
+    /// Whether any operator of the current set has attested. Attestations
+    /// from an older epoch were invalidated by a membership change.
+    pub fn is_current(&self, operator_set: &OperatorSet) -> bool {
+        self.mask != 0 && self.epoch == operator_set.epoch
+    }
+
+    /// Whether `operator` is among those that attested to `digest`
+    pub fn has_attested(&self, operator_set: &OperatorSet, operator: &Pubkey, digest: &[u8; 32]) -> bool {
+        operator_set
+            .operators
+            .iter()
+            .position(|key| key == operator)
+            .is_some_and(|position| self.attesters(digest).any(|index| index == position))
+    }
+
+    /// Number of operators that have attested to `digest`
+    pub fn count(&self, digest: &[u8; 32]) -> u8 {
+        self.attesters(digest).count() as u8
+    }
+
+    /// Operator-set positions of the operators that attested to `digest`
+    pub fn attesters<'a>(&'a self, digest: &'a [u8; 32]) -> impl Iterator<Item = usize> + 'a {
+        (0..MAX_OPERATORS).filter(move |&index| self.mask & (1 << index) != 0 && &self.digests[index] == digest)
+    }
+
+    /// Digest that at least `threshold` operators agreed on, if any
+    pub fn settled_digest(&self, threshold: u8) -> Option<[u8; 32]> {
+        (0..MAX_OPERATORS)
+            .filter(|&index| self.mask & (1 << index) != 0)
+            .map(|index| self.digests[index])
+            .find(|digest| self.count(digest) >= threshold)
+    }
+}
+
+/// How an asset's tokens are held. In Vault mode deposits and withdrawals
+/// escrow tokens in the vault and are paid out on Zcash. In MintBurn mode
+/// the bridge PDA is the mint authority: a deposit is ZEC sent to the
+/// custody address, minted on confirmation, and a completed withdrawal
+/// burns its escrow as the ZEC leaves custody.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CustodyMode {
+    #[default]
+    Vault,
+    MintBurn,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ZcashNetwork {
+    #[default]
+    Mainnet,
+    Testnet,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq)]
+pub enum ZcashPool {
+    Sapling,
+    Orchard,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq)]
+pub enum DepositStatus {
+    Pending,
+    Confirmed,
+    Completed,
+    Failed,
+    /// Confirmed by the operators, waiting out the challenge period
+    AwaitingFinality,
+    /// Challenged by a watcher, waiting for the authority to resolve it
+    Disputed,
+    /// Withdrawn by the user before any operator picked it up
+    Cancelled,
+    /// Leased to an operator paying it out on Zcash
+    Processing,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq)]
+pub enum WithdrawalStatus {
+    Pending,
+    Processing,
+    Completed,
+    Failed,
+    /// Attested and proven, but held back by the outflow limiter
+    Deferred,
+    /// Attested and proven, waiting out the challenge period
+    AwaitingFinality,
+    /// Challenged by a watcher, waiting for the authority to resolve it
+    Disputed,
+    /// Withdrawn by the user before any operator picked it up
+    Cancelled,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq)]
+pub enum FraudClaimKind {
+    /// The Zcash transaction is not on the canonical chain
+    ConflictingHeader,
+    /// The nullifier was already spent on the Zcash side
+    NullifierReuse,
+    /// The attested transaction does not pay the ticket
+    AmountMismatch,
+    Other,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct FraudClaim {
+    pub kind: FraudClaimKind,
+    /// Hash of the off-chain evidence submitted to the authority
+    pub evidence_hash: [u8; 32],
+}
+
+pub const OUTFLOW_BUCKETS: usize = 12;
+
+/// Rolling-window cap on withdrawal outflows. The window is split into
+/// OUTFLOW_BUCKETS buckets kept in a ring buffer; slot `i` holds the amount
+/// released during bucket number `bucket_ids[i]`. A zero cap disables it.
+///
+/// Completing a withdrawal moves no vault tokens: the escrow was taken at
+/// initiation and the payout happens on Zcash. The limiter therefore caps
+/// how much value the program may mark as paid out per window, which bounds
+/// what a compromised operator set or a forged proof can release, rather
+/// than the vault balance itself.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Default)]
+pub struct OutflowLimiter {
+    pub window_seconds: i64,
+    pub max_outflow: u64,
+    pub bucket_amounts: [u64; OUTFLOW_BUCKETS],
+    pub bucket_ids: [i64; OUTFLOW_BUCKETS],
+}
+
+impl OutflowLimiter {
+    pub const LEN: usize = 8 + 8 + 8 * OUTFLOW_BUCKETS + 8 * OUTFLOW_BUCKETS;
+
+    pub fn is_enabled(&self) -> bool {
+        self.window_seconds > 0 && self.max_outflow > 0
+    }
+
+    /// Whether a single withdrawal of `amount` could ever fit in the window
+    pub fn admits(&self, amount: u64) -> bool {
+        !self.is_enabled() || amount <= self.max_outflow
+    }
+
+    fn bucket_len(&self) -> i64 {
+        (self.window_seconds / OUTFLOW_BUCKETS as i64).max(1)
+    }
+
+    /// Total released during the window ending at `now`
+    pub fn window_outflow(&self, now: i64) -> u64 {
+        if !self.is_enabled() {
+            return 0;
+        }
+        let current = now / self.bucket_len();
+        self.bucket_ids
+            .iter()
+            .zip(self.bucket_amounts.iter())
+            .filter(|(id, _)| current - **id < OUTFLOW_BUCKETS as i64)
+            .fold(0u64, |total, (_, amount)| total.saturating_add(*amount))
+    }
+
+    /// Records `amount` against the current bucket if the window has room
+    pub fn try_record(&mut self, now: i64, amount: u64) -> bool {
+        if !self.is_enabled() {
+            return true;
+        }
+        if self.window_outflow(now).saturating_add(amount) > self.max_outflow {
+            return false;
+        }
+        
+        let current = now / self.bucket_len();
+        let slot = current.rem_euclid(OUTFLOW_BUCKETS as i64) as usize;
+        if self.bucket_ids[slot] != current {
+            self.bucket_ids[slot] = current;
+            self.bucket_amounts[slot] = 0;
+        }
+        self.bucket_amounts[slot] = self.bucket_amounts[slot].saturating_add(amount);
+        true
+    }
+}
+
+#[account]
+#[derive(Default)]
+pub struct BridgeState {
+    pub authority: Pubkey,
+    /// Mint of the first registered asset, also used for operator bonds
+    pub wrapped_zec_mint: Pubkey,
+    pub withdrawal_nonce: u64,
+    pub is_paused: bool,
+    pub bump: u8,
+    pub deposit_timeout: i64,
+    /// Set by propose_authority, cleared once accepted
+    pub pending_authority: Pubkey,
+    /// May pause the bridge but not unpause or reconfigure it
+    pub guardian: Pubkey,
+    pub zcash_network: ZcashNetwork,
+    /// Delay before confirmations become final, zero when optimistic mode is off
+    pub challenge_period: i64,
+    /// Lamports a watcher must bond before it may challenge tickets
+    pub watcher_bond: u64,
+    /// Wrapped ZEC an operator must have bonded to be added to the set
+    pub min_operator_bond: u64,
+    pub unbonding_delay: i64,
+}
+
+/// Per-mint bridging configuration and accounting, seeded by the mint
+#[account]
+pub struct AssetConfig {
+    pub mint: Pubkey,
+    pub vault: Pubkey,
     pub min_deposit: u64,
     pub max_deposit: u64,
     pub protocol_fee_bps: u16,
+    /// Scheduled fee increase, zero when none is pending
+    pub pending_fee_bps: u16,
+    pub pending_fee_effective_at: i64,
+    /// Fees earned by settled tickets that are still held in the vault
+    pub accrued_fees: u64,
+    pub total_fees_collected: u64,
+    /// Sum of the deposit shards as of the last `sync_deposit_totals`
+    pub total_deposited: u64,
+    pub total_withdrawn: u64,
+    pub outflow_limiter: OutflowLimiter,
+    pub bump: u8,
+    pub custody_mode: CustodyMode,
+    /// Ceiling on outstanding supply in mint-and-burn mode
+    pub supply_cap: u64,
+    /// ZEC locked on the Zcash side backing the minted supply
+    pub locked_amount: u64,
+    /// Vault tokens backing withdrawals that may still be refunded
+    pub pending_withdrawals: u64,
+    /// Receiver of the bridge's shielded custody address in mint-and-burn
+    /// mode; deposits mint against ZEC sent here
+    pub custody_receiver: [u8; zcash_address::SHIELDED_RECEIVER_LEN],
+}
+
+impl AssetConfig {
+    pub const LEN: usize = 8 + 32 + 32 + 8 + 8 + 2 + 2 + 8 + 8 + 8 + 8 + 8 + OutflowLimiter::LEN + 1 + 1 + 8 + 8 + 8
+        + zcash_address::SHIELDED_RECEIVER_LEN;
+
+    fn init(
+        &mut self,
+        mint: &InterfaceAccount<Mint>,
+        bridge_state: &Pubkey,
+        network: ZcashNetwork,
+        vault: Pubkey,
+        params: &AssetParams,
+        bump: u8,
+    ) -> Result<()> {
+        require!(params.min_deposit <= params.max_deposit, BridgeError::InvalidConfig);
+        require!(params.protocol_fee_bps <= MAX_PROTOCOL_FEE_BPS, BridgeError::FeeTooHigh);
+        let mint_info = mint.to_account_info();
+        if *mint_info.owner == spl_token_2022::ID {
+            check_mint_extensions(&mint_info.try_borrow_data()?)?;
+        }
+        if params.custody_mode == CustodyMode::MintBurn {
+            require!(
+                mint.mint_authority == COption::Some(*bridge_state),
+                BridgeError::InvalidMintAuthority
+            );
+            require!(params.supply_cap >= mint.supply, BridgeError::InvalidConfig);
+        }
+        let custody_receiver = match params.custody_mode {
+            CustodyMode::MintBurn => {
+                let decoded = zcash_address::decode(&params.custody_address)
+                    .ok_or(BridgeError::InvalidZcashAddress)?;
+                require!(decoded.network == network, BridgeError::ZcashNetworkMismatch);
+                decoded.receiver
+            }
+            CustodyMode::Vault => [0u8; zcash_address::SHIELDED_RECEIVER_LEN],
+        };
+        
+        self.mint = mint.key();
+        self.vault = vault;
+        self.min_deposit = params.min_deposit;
+        self.max_deposit = params.max_deposit;
+        self.protocol_fee_bps = params.protocol_fee_bps;
+        self.pending_fee_bps = 0;
+        self.pending_fee_effective_at = 0;
+        self.accrued_fees = 0;
+        self.total_fees_collected = 0;
+        self.total_deposited = 0;
+        self.total_withdrawn = 0;
+        self.outflow_limiter = OutflowLimiter::default();
+        self.bump = bump;
+        self.custody_mode = params.custody_mode;
+        self.supply_cap = params.supply_cap;
+        // Supply minted before the bridge took over is assumed to be backed
+        self.locked_amount = match params.custody_mode {
+            CustodyMode::MintBurn => mint.supply,
+            CustodyMode::Vault => 0,
+        };
+        self.pending_withdrawals = 0;
+        self.custody_receiver = custody_receiver;
+        Ok(())
+    }
+
+    /// Vault tokens a deposit holds until it is confirmed or returned;
+    /// mint-and-burn deposits take none
+    fn deposit_escrow(&self, deposit: &DepositTicket) -> u64 {
+        match self.custody_mode {
+            CustodyMode::Vault => deposit.amount.checked_add(deposit.fee).unwrap(),
+            CustodyMode::MintBurn => 0,
+        }
+    }
+    
+    /// Drops a withdrawal's escrow once it is paid out or returned
+    fn release_withdrawal_escrow(&mut self, withdrawal: &WithdrawalTicket) {
+        let escrow = withdrawal.amount.checked_add(withdrawal.fee).unwrap();
+        self.pending_withdrawals = self.pending_withdrawals.checked_sub(escrow).unwrap();
+    }
+    
+    /// Protocol fee in force at `now`, taking a due scheduled increase into account
+    pub fn fee_bps_at(&self, now: i64) -> u16 {
+        if self.pending_fee_effective_at != 0 && now >= self.pending_fee_effective_at {
+            self.pending_fee_bps
+        } else {
+            self.protocol_fee_bps
+        }
+    }
+}
+
+pub const COMMITMENT_TREE_DEPTH: usize = 20;
+pub const ROOT_HISTORY_SIZE: usize = 32;
+
+/// Append-only Keccak Merkle tree over every completed deposit and
+/// withdrawal. Only the right-most path is stored; the last
+/// ROOT_HISTORY_SIZE roots are kept so off-chain provers can check
+/// against a slightly stale root.
+#[account]
+pub struct CommitmentTree {
+    pub next_index: u32,
+    pub filled_subtrees: [[u8; 32]; COMMITMENT_TREE_DEPTH],
+    /// Root of an empty subtree at each level
+    pub zeros: [[u8; 32]; COMMITMENT_TREE_DEPTH],
+    pub roots: [[u8; 32]; ROOT_HISTORY_SIZE],
+    pub current_root_index: u32,
+    pub bump: u8,
+}
+
+impl CommitmentTree {
+    pub const LEN: usize = 8 + 4 + 32 * COMMITMENT_TREE_DEPTH * 2 + 32 * ROOT_HISTORY_SIZE + 4 + 1;
+
+    fn hash_pair(left: &[u8; 32], right: &[u8; 32]) -> [u8; 32] {
+        use anchor_lang::solana_program::keccak::hashv;
+        hashv(&[left, right]).to_bytes()
+    }
+
+    fn init(&mut self) {
+        use anchor_lang::solana_program::keccak::hash;
+        let mut zero = hash(b"zcash_bridge:empty_leaf").to_bytes();
+        for level in 0..COMMITMENT_TREE_DEPTH {
+            self.zeros[level] = zero;
+            self.filled_subtrees[level] = zero;
+            zero = Self::hash_pair(&zero, &zero);
+        }
+        self.next_index = 0;
+        self.current_root_index = 0;
+        self.roots = [[0u8; 32]; ROOT_HISTORY_SIZE];
+        self.roots[0] = zero;
+    }
+
+    /// Appends a leaf and returns its index and the new root
+    fn append(&mut self, leaf: [u8; 32]) -> Result<(u32, [u8; 32])> {
+        let leaf_index = self.next_index;
+        require!((leaf_index as u64) < 1u64 << COMMITMENT_TREE_DEPTH, BridgeError::CommitmentTreeFull);
+        
+        let mut node = leaf;
+        let mut position = leaf_index;
+        for level in 0..COMMITMENT_TREE_DEPTH {
+            node = if position & 1 == 0 {
+                self.filled_subtrees[level] = node;
+                Self::hash_pair(&node, &self.zeros[level])
+            } else {
+                Self::hash_pair(&self.filled_subtrees[level], &node)
+            };
+            position >>= 1;
+        }
+        
+        self.current_root_index = (self.current_root_index + 1) % ROOT_HISTORY_SIZE as u32;
+        self.roots[self.current_root_index as usize] = node;
+        self.next_index = leaf_index + 1;
+        Ok((leaf_index, node))
+    }
+}
+
+pub const MAX_OPERATORS: usize = 16;
+pub const MAX_ZCASH_ADDRESS_LEN: usize = 256;
+pub const HEADER_WINDOW: usize = 64;
+/// Ancestors of the checkpoint needed to check the difficulty of its children
+pub const CHECKPOINT_HISTORY_LEN: usize = zcash_header::DIFFICULTY_WINDOW - 1;
+/// Forks branching at most this many blocks below the tip can always be relayed
+pub const MAX_REORG_DEPTH: usize = 8;
+pub const MAX_HEADER_FUTURE_DRIFT: i64 = 2 * 60 * 60;
+pub const MAX_PROTOCOL_FEE_BPS: u16 = 1000;
+pub const FEE_INCREASE_DELAY: i64 = 2 * 24 * 60 * 60;
+pub const DEPOSIT_SHARDS: u8 = 16;
+/// Time a ticket must sit before its owner may cancel it
+pub const CANCEL_LOCK_PERIOD: i64 = 10 * 60;
+/// How long a claimed withdrawal stays reserved for the claiming operator
+pub const WITHDRAWAL_LEASE_PERIOD: i64 = 30 * 60;
+/// How long a claimed deposit stays reserved for the claiming operator
+pub const DEPOSIT_LEASE_PERIOD: i64 = 30 * 60;
+pub const MAX_BATCH_TICKETS: usize = 16;
+
+#[account]
+pub struct OperatorSet {
+    pub operators: Vec<Pubkey>,
+    pub threshold: u8,
+    /// Bumped whenever an operator is removed so stale attestations are discarded
+    pub epoch: u32,
+    pub bump: u8,
+}
+
+impl OperatorSet {
+    pub const LEN: usize = 8 + 4 + 32 * MAX_OPERATORS + 1 + 4 + 1;
+
+    pub fn contains(&self, key: &Pubkey) -> bool {
+        self.operators.contains(key)
+    }
+
+    /// Removes `key` if present. Attestation bitmaps are indexed by
+    /// position, so the epoch is bumped to invalidate them.
+    fn remove(&mut self, key: &Pubkey) -> bool {
+        let index = match self.operators.iter().position(|operator| operator == key) {
+            Some(index) => index,
+            None => return false,
+        };
+        self.operators.remove(index);
+        self.epoch = self.epoch.checked_add(1).unwrap();
+        true
+    }
+}
+
+/// Window of the most recent relayed Zcash headers, possibly spanning
+/// several forks. The best chain is the one ending at `tip_index`.
+#[account]
+pub struct ZcashLightClient {
+    pub headers: Vec<StoredHeader>,
+    pub tip_index: u32,
+    /// Trusted header the window was seeded with
+    pub checkpoint_hash: [u8; 32],
+    /// Timing of the checkpoint's ancestors, parent first
+    pub checkpoint_history: Vec<BlockTiming>,
+    pub min_confirmations: u32,
+    pub bump: u8,
+}
+
+impl ZcashLightClient {
+    pub const LEN: usize = 8
+        + 4 + StoredHeader::LEN * HEADER_WINDOW
+        + 4
+        + 32
+        + 4 + BlockTiming::LEN * CHECKPOINT_HISTORY_LEN
+        + 4
+        + 1;
+
+    pub fn tip(&self) -> &StoredHeader {
+        &self.headers[self.tip_index as usize]
+    }
+
+    pub fn find(&self, hash: &[u8; 32]) -> Option<usize> {
+        self.headers.iter().position(|header| &header.hash == hash)
+    }
+
+    /// Checks a parsed header against its parent and the difficulty rule,
+    /// stores it and returns it along with whether it became the new tip
+    pub fn accept(
+        &mut self,
+        header: &zcash_header::ParsedHeader,
+        now: i64,
+        is_mainnet: bool,
+    ) -> Result<(StoredHeader, bool)> {
+        require!(self.find(&header.hash).is_none(), BridgeError::HeaderAlreadyKnown);
+        let parent_index = self.find(&header.prev_hash).ok_or(BridgeError::UnknownParentHeader)?;
+        let parent = self.headers[parent_index].clone();
+        
+        // Equihash is not verified, so the target itself must be the
+        // one the chain dictates rather than whatever the relayer picked
+        let window = self
+            .difficulty_window(parent_index)
+            .ok_or(BridgeError::UnknownParentHeader)?;
+        require!(
+            zcash_header::required_bits(&window, header.time, is_mainnet) == Some(header.bits),
+            BridgeError::InvalidDifficulty
+        );
+        require!(
+            zcash_header::meets_target(&header.hash, header.bits, is_mainnet),
+            BridgeError::InsufficientWork
+        );
+        require!(
+            (header.time as i64) <= now.checked_add(MAX_HEADER_FUTURE_DRIFT).unwrap(),
+            BridgeError::InvalidHeader
+        );
+        
+        let stored = StoredHeader {
+            hash: header.hash,
+            prev_hash: header.prev_hash,
+            merkle_root: header.merkle_root,
+            height: parent.height.checked_add(1).unwrap(),
+            time: header.time,
+            bits: header.bits,
+            cumulative_work: parent
+                .cumulative_work
+                .saturating_add(zcash_header::work_from_bits(header.bits)),
+        };
+        
+        let is_new_tip = stored.cumulative_work > self.tip().cumulative_work;
+        let index = self.insert(stored.clone())?;
+        if is_new_tip {
+            self.tip_index = index as u32;
+        }
+        Ok((stored, is_new_tip))
+    }
+
+    /// (time, bits) of the DIFFICULTY_WINDOW headers ending at `index`,
+    /// newest first, falling back to the checkpoint history. None if an
+    /// ancestor has been evicted.
+    fn difficulty_window(&self, index: usize) -> Option<Vec<(u32, u32)>> {
+        let mut window = Vec::with_capacity(zcash_header::DIFFICULTY_WINDOW);
+        let mut cursor = Some(index);
+        while let Some(index) = cursor {
+            if window.len() == zcash_header::DIFFICULTY_WINDOW {
+                break;
+            }
+            let header = &self.headers[index];
+            window.push((header.time, header.bits));
+            if header.hash == self.checkpoint_hash {
+                window.extend(self.checkpoint_history.iter().map(|timing| (timing.time, timing.bits)));
+                break;
+            }
+            cursor = self.find(&header.prev_hash);
+        }
+        window.truncate(zcash_header::DIFFICULTY_WINDOW);
+        (window.len() == zcash_header::DIFFICULTY_WINDOW).then_some(window)
+    }
+
+    /// Indices of up to `count` best-chain headers, starting at the tip
+    fn best_chain(&self, count: usize) -> Vec<usize> {
+        let mut chain = Vec::with_capacity(count);
+        let mut cursor = Some(self.tip_index as usize);
+        while let Some(index) = cursor {
+            if chain.len() == count {
+                break;
+            }
+            chain.push(index);
+            cursor = self.find(&self.headers[index].prev_hash);
+        }
+        chain
+    }
+
+    /// Stores a header, evicting the one with the least cumulative work
+    /// once the window is full. The top of the best chain is never evicted,
+    /// so the difficulty of its successors and of shallow forks can always
+    /// be checked, and a header must outweigh the one it replaces, so cheap
+    /// forks off old blocks cannot flush the window.
+    fn insert(&mut self, header: StoredHeader) -> Result<usize> {
+        if self.headers.len() < HEADER_WINDOW {
+            self.headers.push(header);
+            return Ok(self.headers.len() - 1);
+        }
+        
+        let protected = self.best_chain(zcash_header::DIFFICULTY_WINDOW + MAX_REORG_DEPTH);
+        let victim = (0..self.headers.len())
+            .filter(|index| !protected.contains(index))
+            .min_by_key(|&index| self.headers[index].cumulative_work)
+            .ok_or(BridgeError::InvalidHeader)?;
+        require!(
+            header.cumulative_work > self.headers[victim].cumulative_work,
+            BridgeError::LightClientFull
+        );
+        self.headers[victim] = header;
+        Ok(victim)
+    }
+
+    /// Checks that `tx_id` is included in a block on the best chain that
+    /// has at least `min_confirmations` confirmations
+    pub fn verify_inclusion(&self, tx_id: &[u8; 32], inclusion: &ZcashTxInclusion) -> Result<()> {
+        require!(
+            inclusion.merkle_branch.len() < 32
+                && inclusion.tx_index >> inclusion.merkle_branch.len() == 0,
+            BridgeError::InvalidInclusionProof
+        );
+        
+        // Walk back from the tip so headers on abandoned forks are ignored
+        let tip = self.tip();
+        let mut cursor = Some(self.tip_index as usize);
+        let mut block = None;
+        while let Some(index) = cursor {
+            let header = &self.headers[index];
+            if header.hash == inclusion.block_hash {
+                block = Some(header);
+                break;
+            }
+            cursor = self.find(&header.prev_hash);
+        }
+        let block = block.ok_or(BridgeError::UnknownBlock)?;
+        
+        let confirmations = tip.height - block.height + 1;
+        require!(confirmations >= self.min_confirmations, BridgeError::InsufficientConfirmations);
+        
+        let root = zcash_header::merkle_root_from_branch(tx_id, inclusion.tx_index, &inclusion.merkle_branch);
+        require!(root == block.merkle_root, BridgeError::InvalidInclusionProof);
+        
+        Ok(())
+    }
+}
+
+/// Marks a key as allowed to relay Zcash headers
+#[account]
+pub struct RelayerRecord {
+    pub relayer: Pubkey,
+    pub added_at: i64,
+    pub bump: u8,
+}
+
+/// Staging area for a raw header written over several transactions
+#[account]
+pub struct HeaderBuffer {
+    pub relayer: Pubkey,
+    pub data: Vec<u8>,
+    pub bump: u8,
+}
+
+impl HeaderBuffer {
+    pub const LEN: usize = 8 + 32 + 4 + zcash_header::HEADER_LEN + 1;
+}
+
+#[account]
+pub struct MetaAddress {
+    pub owner: Pubkey,
+    pub spend_pub_key: [u8; 33],
+    pub viewing_pub_key: [u8; 33],
+    pub is_active: bool,
+    pub created_at: i64,
+    pub bump: u8,
+}
+
+#[account]
+pub struct DepositTicket {
+    /// Position among the depositor's deposits, unique per depositor
+    pub ticket_id: u64,
+    pub depositor: Pubkey,
+    pub mint: Pubkey,
+    pub amount: u64,
+    pub fee: u64,
+    /// Validated Sapling or Unified Address encoding
+    pub zcash_shielded_address: String,
+    pub zcash_pool: ZcashPool,
+    /// Shielded receiver extracted from the address
+    pub zcash_receiver: [u8; 43],
+    pub memo: [u8; 64],
+    pub status: DepositStatus,
+    pub created_at: i64,
+    pub processed_at: i64,
+    pub zcash_tx_id: [u8; 32],
+    pub bump: u8,
+    pub expires_at: i64,
+    pub attestations: Attestations,
+    pub finalizes_at: i64,
+    /// Watcher that disputed the ticket, default when undisputed
+    pub challenger: Pubkey,
+    /// Commitment of the delivered shielded note, set on completion
+    pub delivery_note_commitment: [u8; 32],
+    /// Operator holding the processing lease, default until claimed
+    pub claimed_by: Pubkey,
+    pub lease_expires_at: i64,
+}
+
+impl DepositTicket {
+    /// Fails unless the ticket is pending or its lease has lapsed, so no
+    /// operator may be paying it out
+    fn require_unclaimed(&self, now: i64) -> Result<()> {
+        match self.status {
+            DepositStatus::Pending => Ok(()),
+            DepositStatus::Processing => {
+                require!(now >= self.lease_expires_at, BridgeError::LeaseActive);
+                Ok(())
+            }
+            _ => err!(BridgeError::InvalidDepositStatus),
+        }
+    }
+}
+
+#[account]
+pub struct WithdrawalTicket {
+    pub ticket_id: u64,
+    pub recipient: Pubkey,
+    pub mint: Pubkey,
+    pub amount: u64,
+    pub fee: u64,
+    pub partial_note_commitment: [u8; 32],
+    pub partial_note_nullifier: [u8; 32],
+    pub encrypted_value: [u8; 32],
+    pub status: WithdrawalStatus,
+    pub created_at: i64,
+    pub processed_at: i64,
+    pub bump: u8,
+    pub attestations: Attestations,
+    pub finalizes_at: i64,
+    /// Watcher that disputed the ticket, default when undisputed
+    pub challenger: Pubkey,
+    /// Operator holding the processing lease, default until claimed
+    pub claimed_by: Pubkey,
+    pub lease_expires_at: i64,
+}
+
+/// Next deposit nonce of a single depositor, so tickets from different
+/// users never contend on a shared counter
+#[account]
+pub struct UserDepositCounter {
+    pub depositor: Pubkey,
+    pub next_nonce: u64,
+    pub bump: u8,
+}
+
+impl UserDepositCounter {
+    pub const LEN: usize = 8 + 32 + 8 + 1;
+}
+
+/// One slice of an asset's completed deposit totals. Depositors are spread
+/// across `DEPOSIT_SHARDS` shards by the first byte of their key.
+#[account]
+pub struct DepositShard {
+    pub mint: Pubkey,
+    pub index: u8,
+    pub deposit_count: u64,
+    pub total_deposited: u64,
+    pub bump: u8,
+    /// Vault tokens backing deposits that may still be refunded
+    pub pending_escrow: u64,
+}
+
+impl DepositShard {
+    pub const LEN: usize = 8 + 32 + 1 + 8 + 8 + 1 + 8;
+
+    /// Tracks the escrow a new deposit put in the vault
+    fn hold_escrow(&mut self, asset: &AssetConfig, deposit: &DepositTicket) {
+        self.pending_escrow = self.pending_escrow.checked_add(asset.deposit_escrow(deposit)).unwrap();
+    }
+    
+    /// Drops a deposit's escrow once it is confirmed or returned
+    fn release_escrow(&mut self, asset: &AssetConfig, deposit: &DepositTicket) {
+        self.pending_escrow = self.pending_escrow.checked_sub(asset.deposit_escrow(deposit)).unwrap();
+    }
+    
+    /// Counts a completed deposit towards the shard's totals
+    fn record_completion(&mut self, deposit: &DepositTicket) {
+        self.deposit_count = self.deposit_count.checked_add(1).unwrap();
+        self.total_deposited = self.total_deposited.checked_add(deposit.amount).unwrap();
+    }
+}
+
+/// Deposit shard fields summed over every shard of an asset
+#[derive(Default)]
+pub struct DepositShardTotals {
+    pub deposit_count: u64,
+    pub total_deposited: u64,
+    pub pending_escrow: u64,
+}
+
+pub fn deposit_shard_index(depositor: &Pubkey) -> u8 {
+    depositor.as_ref()[0] % DEPOSIT_SHARDS
+}
+
+/// Wrapped ZEC staked by an operator in the bond vault
+#[account]
+pub struct OperatorBond {
+    pub operator: Pubkey,
+    pub amount: u64,
+    /// Stake on its way out; still slashable until withdrawn
+    pub unbonding_amount: u64,
+    pub unbonding_available_at: i64,
+    pub bump: u8,
+}
+
+#[account]
+pub struct WatcherRecord {
+    pub watcher: Pubkey,
+    /// Lamports held by this account on top of its rent exemption
+    pub bond: u64,
+    pub open_challenges: u32,
+    pub registered_at: i64,
+    pub bump: u8,
+}
+
+impl WatcherRecord {
+    /// Bond needed to keep every open challenge covered and open `extra` more
+    pub fn required_bond(&self, required: u64, extra: u32) -> Option<u64> {
+        required.checked_mul(self.open_challenges as u64 + extra as u64)
+    }
+
+    pub fn can_challenge(&self, required: u64) -> bool {
+        required > 0 && self.required_bond(required, 1).is_some_and(|needed| self.bond >= needed)
+    }
+}
+
+/// One record per partial-note nullifier. The record is reserved by the
+/// withdrawal ticket that first presents the nullifier and marked spent
+/// once that ticket is processed.
+#[account]
+pub struct NullifierRecord {
+    pub nullifier: [u8; 32],
+    pub withdrawal_ticket: Pubkey,
+    pub is_spent: bool,
+    pub created_at: i64,
+    pub bump: u8,
+}
+
+/// Spent marker for a Zcash transaction that confirmed a deposit. Created
+/// when an operator first confirms with the transaction and bound to the
+/// deposit reaching the threshold with it.
+#[account]
+pub struct ZcashTxRecord {
+    pub zcash_tx_id: [u8; 32],
+    /// Deposit confirmed with the transaction, default until then
+    pub deposit_ticket: Pubkey,
+    pub consumed_at: i64,
+    pub bump: u8,
+}
+
+impl ZcashTxRecord {
+    pub const LEN: usize = 8 + 32 + 32 + 8 + 1;
+}
+
+#[account]
+pub struct WithdrawalVerifyingKey {
+    pub alpha_g1: [u8; 64],
+    pub beta_g2: [u8; 128],
+    pub gamma_g2: [u8; 128],
+    pub delta_g2: [u8; 128],
+    pub ic: [[u8; 64]; groth16::NR_PUBLIC_INPUTS + 1],
+    pub bump: u8,
+}
+
+#[account]
+pub struct StealthAddress {
+    pub owner: Pubkey,
+    pub ephemeral_pub_key: [u8; 33],
+    pub view_hint: u8,
+    pub k: u32,
+    pub is_spent: bool,
+    pub created_at: i64,
+    pub bump: u8,
+    /// Refunded the account rent once the address is spent and closed
+    pub payer: Pubkey,
+}
+
+#[derive(Accounts)]
+pub struct Initialize<'info> {
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + 32 + 32 + 8 + 1 + 1 + 8 + 32 + 32 + 1 + 8 + 8 + 8 + 8,
+        seeds = [b"bridge"],
+        bump
+    )]
+    pub bridge_state: Account<'info, BridgeState>,
+    
+    #[account(
+        init,
+        payer = authority,
+        space = OperatorSet::LEN,
+        seeds = [b"operator_set"],
+        bump
+    )]
+    pub operator_set: Box<Account<'info, OperatorSet>>,
+    
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    
+    pub wrapped_zec_mint: InterfaceAccount<'info, Mint>,
+    
+    #[account(
+        init,
+        payer = authority,
+        space = AssetConfig::LEN,
+        seeds = [b"asset", wrapped_zec_mint.key().as_ref()],
+        bump
+    )]
+    pub asset_config: Box<Account<'info, AssetConfig>>,
+    
+    #[account(
+        init,
+        payer = authority,
+        token::mint = wrapped_zec_mint,
+        token::authority = bridge_state,
+        token::token_program = token_program,
+        seeds = [b"vault", wrapped_zec_mint.key().as_ref()],
+        bump
+    )]
+    pub vault: InterfaceAccount<'info, TokenAccount>,
+    
+    pub system_program: Program<'info, System>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub rent: Sysvar<'info, Rent>,
+}
+
+#[derive(Accounts)]
+pub struct RegisterAsset<'info> {
+    #[account(
+        seeds = [b"bridge"],
+        bump = bridge_state.bump
+    )]
+    pub bridge_state: Account<'info, BridgeState>,
+    
+    pub mint: InterfaceAccount<'info, Mint>,
+    
+    #[account(
+        init,
+        payer = authority,
+        space = AssetConfig::LEN,
+        seeds = [b"asset", mint.key().as_ref()],
+        bump
+    )]
+    pub asset_config: Box<Account<'info, AssetConfig>>,
+    
+    #[account(
+        init,
+        payer = authority,
+        token::mint = mint,
+        token::authority = bridge_state,
+        token::token_program = token_program,
+        seeds = [b"vault", mint.key().as_ref()],
+        bump
+    )]
+    pub vault: InterfaceAccount<'info, TokenAccount>,
+    
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    
+    pub system_program: Program<'info, System>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub rent: Sysvar<'info, Rent>,
+}
+
+#[derive(Accounts)]
+pub struct RegisterStealthMetaAddress<'info> {
+    #[account(
+        init,
+        payer = user,
+        space = 8 + 32 + 33 + 33 + 1 + 8 + 1,
+        seeds = [b"meta", user.key().as_ref()],
+        bump
+    )]
+    pub meta_address: Account<'info, MetaAddress>,
+    
+    #[account(mut)]
+    pub user: Signer<'info>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct InitiateDeposit<'info> {
+    #[account(
+        seeds = [b"bridge"],
+        bump = bridge_state.bump
+    )]
+    pub bridge_state: Account<'info, BridgeState>,
+    
+    #[account(
+        seeds = [b"asset", asset_config.mint.as_ref()],
+        bump = asset_config.bump
+    )]
+    pub asset_config: Box<Account<'info, AssetConfig>>,
+    
+    #[account(
+        init_if_needed,
+        payer = user,
+        space = UserDepositCounter::LEN,
+        seeds = [b"user_deposits", user.key().as_ref()],
+        bump
+    )]
+    pub user_deposit_counter: Account<'info, UserDepositCounter>,
+    
+    #[account(
+        init,
+        payer = user,
+        space = 8 + 8 + 32 + 32 + 8 + 8 + (4 + MAX_ZCASH_ADDRESS_LEN) + 1 + 43 + 64 + 1 + 8 + 8 + 32 + 1 + 8 + Attestations::LEN + 8 + 32 + 32 + 32 + 8,
+        seeds = [b"deposit", user.key().as_ref(), user_deposit_counter.next_nonce.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub deposit_ticket: Box<Account<'info, DepositTicket>>,
+    
+    #[account(
+        init_if_needed,
+        payer = user,
+        space = DepositShard::LEN,
+        seeds = [b"deposit_shard", asset_config.mint.as_ref(), &[deposit_shard_index(&user.key())]],
+        bump
+    )]
+    pub deposit_shard: Box<Account<'info, DepositShard>>,
+    
+    #[account(mut)]
+    pub user: Signer<'info>,
+    
+    #[account(
+        mut,
+        constraint = user_token_account.owner == user.key()
+    )]
+    pub user_token_account: InterfaceAccount<'info, TokenAccount>,
+    
+    #[account(
+        mut,
+        address = asset_config.vault
+    )]
+    pub vault: InterfaceAccount<'info, TokenAccount>,
+    
+    #[account(address = asset_config.mint)]
+    pub mint: InterfaceAccount<'info, Mint>,
+    
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(zcash_tx_id: [u8; 32])]
+pub struct ConfirmDeposit<'info> {
+    #[account(
+        mut,
+        seeds = [b"bridge"],
+        bump = bridge_state.bump
+    )]
+    pub bridge_state: Account<'info, BridgeState>,
+    
+    #[account(mut)]
+    pub deposit_ticket: Box<Account<'info, DepositTicket>>,
+    
+    #[account(
+        mut,
+        seeds = [b"asset", deposit_ticket.mint.as_ref()],
+        bump = asset_config.bump
+    )]
+    pub asset_config: Box<Account<'info, AssetConfig>>,
+    
+    #[account(
+        mut,
+        seeds = [b"deposit_shard", deposit_ticket.mint.as_ref(), &[deposit_shard_index(&deposit_ticket.depositor)]],
+        bump = deposit_shard.bump
+    )]
+    pub deposit_shard: Box<Account<'info, DepositShard>>,
+    
+    #[account(
+        mut,
+        seeds = [b"commitment_tree"],
+        bump = commitment_tree.bump
+    )]
+    pub commitment_tree: Box<Account<'info, CommitmentTree>>,
+    
+    #[account(
+        seeds = [b"zcash_light_client"],
+        bump = light_client.bump
+    )]
+    pub light_client: Box<Account<'info, ZcashLightClient>>,
+    
+    #[account(
+        seeds = [b"operator_set"],
+        bump = operator_set.bump
+    )]
+    pub operator_set: Box<Account<'info, OperatorSet>>,
+    
+    #[account(
+        seeds = [b"operator_bond", operator.key().as_ref()],
+        bump = operator_bond.bump
+    )]
+    pub operator_bond: Account<'info, OperatorBond>,
+    
+    #[account(mut)]
+    pub operator: Signer<'info>,
+    
+    #[account(
+        init_if_needed,
+        payer = operator,
+        space = ZcashTxRecord::LEN,
+        seeds = [b"zcash_tx", zcash_tx_id.as_ref()],
+        bump
+    )]
+    pub zcash_tx_record: Account<'info, ZcashTxRecord>,
+    
+    #[account(
+        mut,
+        address = asset_config.mint
+    )]
+    pub mint: InterfaceAccount<'info, Mint>,
+    
+    #[account(
+        mut,
+        address = asset_config.vault
+    )]
+    pub vault: InterfaceAccount<'info, TokenAccount>,
+    
+    #[account(
+        mut,
+        constraint = depositor_token_account.owner == deposit_ticket.depositor
+    )]
+    pub depositor_token_account: InterfaceAccount<'info, TokenAccount>,
+    
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct BatchConfirmDeposits<'info> {
+    #[account(
+        seeds = [b"bridge"],
+        bump = bridge_state.bump
+    )]
+    pub bridge_state: Account<'info, BridgeState>,
+    
+    #[account(
+        mut,
+        seeds = [b"asset", asset_config.mint.as_ref()],
+        bump = asset_config.bump
+    )]
+    pub asset_config: Box<Account<'info, AssetConfig>>,
+    
+    #[account(
+        mut,
+        seeds = [b"commitment_tree"],
+        bump = commitment_tree.bump
+    )]
+    pub commitment_tree: Box<Account<'info, CommitmentTree>>,
+    
+    #[account(
+        seeds = [b"zcash_light_client"],
+        bump = light_client.bump
+    )]
+    pub light_client: Box<Account<'info, ZcashLightClient>>,
+    
+    #[account(
+        seeds = [b"operator_set"],
+        bump = operator_set.bump
+    )]
+    pub operator_set: Box<Account<'info, OperatorSet>>,
+    
+    #[account(
+        seeds = [b"operator_bond", operator.key().as_ref()],
+        bump = operator_bond.bump
+    )]
+    pub operator_bond: Account<'info, OperatorBond>,
+    
+    /// Pays for the records of Zcash transactions seen for the first time
+    #[account(mut)]
+    pub operator: Signer<'info>,
+    
+    #[account(
+        mut,
+        address = asset_config.mint
+    )]
+    pub mint: InterfaceAccount<'info, Mint>,
+    
+    #[account(
+        mut,
+        address = asset_config.vault
+    )]
+    pub vault: InterfaceAccount<'info, TokenAccount>,
+    
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(zcash_tx_id: [u8; 32])]
+pub struct ConfirmDepositWithSignatures<'info> {
+    #[account(
+        mut,
+        seeds = [b"bridge"],
+        bump = bridge_state.bump
+    )]
+    pub bridge_state: Account<'info, BridgeState>,
+    
+    #[account(
+        seeds = [b"operator_set"],
+        bump = operator_set.bump
+    )]
+    pub operator_set: Box<Account<'info, OperatorSet>>,
+    
+    #[account(mut)]
+    pub deposit_ticket: Box<Account<'info, DepositTicket>>,
+    
+    #[account(
+        mut,
+        seeds = [b"asset", deposit_ticket.mint.as_ref()],
+        bump = asset_config.bump
+    )]
+    pub asset_config: Box<Account<'info, AssetConfig>>,
+    
+    #[account(
+        mut,
+        seeds = [b"deposit_shard", deposit_ticket.mint.as_ref(), &[deposit_shard_index(&deposit_ticket.depositor)]],
+        bump = deposit_shard.bump
+    )]
+    pub deposit_shard: Box<Account<'info, DepositShard>>,
+    
+    #[account(
+        mut,
+        seeds = [b"commitment_tree"],
+        bump = commitment_tree.bump
+    )]
+    pub commitment_tree: Box<Account<'info, CommitmentTree>>,
+    
+    #[account(
+        seeds = [b"zcash_light_client"],
+        bump = light_client.bump
+    )]
+    pub light_client: Box<Account<'info, ZcashLightClient>>,
+    
+    /// Relayer submitting the aggregated signatures
+    #[account(mut)]
+    pub relayer: Signer<'info>,
+    
+    #[account(
+        init_if_needed,
+        payer = relayer,
+        space = ZcashTxRecord::LEN,
+        seeds = [b"zcash_tx", zcash_tx_id.as_ref()],
+        bump
+    )]
+    pub zcash_tx_record: Account<'info, ZcashTxRecord>,
+    
+    /// CHECK: address is checked against the instructions sysvar id
+    #[account(address = anchor_lang::solana_program::sysvar::instructions::ID)]
+    pub instructions: UncheckedAccount<'info>,
+    
+    #[account(
+        mut,
+        address = asset_config.mint
+    )]
+    pub mint: InterfaceAccount<'info, Mint>,
+    
+    #[account(
+        mut,
+        address = asset_config.vault
+    )]
+    pub vault: InterfaceAccount<'info, TokenAccount>,
+    
+    #[account(
+        mut,
+        constraint = depositor_token_account.owner == deposit_ticket.depositor
+    )]
+    pub depositor_token_account: InterfaceAccount<'info, TokenAccount>,
+    
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct CompleteDeposit<'info> {
+    #[account(
+        seeds = [b"bridge"],
+        bump = bridge_state.bump
+    )]
+    pub bridge_state: Account<'info, BridgeState>,
+    
+    #[account(mut)]
+    pub deposit_ticket: Box<Account<'info, DepositTicket>>,
+    
+    #[account(
+        mut,
+        seeds = [b"deposit_shard", deposit_ticket.mint.as_ref(), &[deposit_shard_index(&deposit_ticket.depositor)]],
+        bump = deposit_shard.bump
+    )]
+    pub deposit_shard: Box<Account<'info, DepositShard>>,
+    
+    #[account(
+        mut,
+        seeds = [b"commitment_tree"],
+        bump = commitment_tree.bump
+    )]
+    pub commitment_tree: Box<Account<'info, CommitmentTree>>,
+    
+    #[account(
+        seeds = [b"zcash_light_client"],
+        bump = light_client.bump
+    )]
+    pub light_client: Box<Account<'info, ZcashLightClient>>,
+    
+    #[account(
+        seeds = [b"operator_set"],
+        bump = operator_set.bump
+    )]
+    pub operator_set: Box<Account<'info, OperatorSet>>,
+    
+    #[account(
+        seeds = [b"operator_bond", operator.key().as_ref()],
+        bump = operator_bond.bump
+    )]
+    pub operator_bond: Account<'info, OperatorBond>,
+    
+    pub operator: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct FailDeposit<'info> {
+    #[account(
+        mut,
+        seeds = [b"bridge"],
+        bump = bridge_state.bump
+    )]
+    pub bridge_state: Account<'info, BridgeState>,
+    
+    #[account(mut)]
+    pub deposit_ticket: Box<Account<'info, DepositTicket>>,
+    
+    #[account(
+        seeds = [b"asset", deposit_ticket.mint.as_ref()],
+        bump = asset_config.bump
+    )]
+    pub asset_config: Box<Account<'info, AssetConfig>>,
+    
+    #[account(
+        mut,
+        seeds = [b"deposit_shard", deposit_ticket.mint.as_ref(), &[deposit_shard_index(&deposit_ticket.depositor)]],
+        bump = deposit_shard.bump
+    )]
+    pub deposit_shard: Box<Account<'info, DepositShard>>,
+    
+    #[account(
+        seeds = [b"operator_set"],
+        bump = operator_set.bump
+    )]
+    pub operator_set: Box<Account<'info, OperatorSet>>,
+    
+    #[account(
+        seeds = [b"operator_bond", operator.key().as_ref()],
+        bump = operator_bond.bump
+    )]
+    pub operator_bond: Account<'info, OperatorBond>,
+    
+    pub operator: Signer<'info>,
+    
+    #[account(
+        mut,
+        address = asset_config.vault
+    )]
+    pub vault: InterfaceAccount<'info, TokenAccount>,
+    
+    #[account(
+        mut,
+        constraint = depositor_token_account.owner == deposit_ticket.depositor
+    )]
+    pub depositor_token_account: InterfaceAccount<'info, TokenAccount>,
+    
+    #[account(address = asset_config.mint)]
+    pub mint: InterfaceAccount<'info, Mint>,
+    
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+#[derive(Accounts)]
+pub struct RefundDeposit<'info> {
+    #[account(
+        mut,
+        seeds = [b"bridge"],
+        bump = bridge_state.bump
+    )]
+    pub bridge_state: Account<'info, BridgeState>,
+    
+    #[account(mut)]
+    pub deposit_ticket: Box<Account<'info, DepositTicket>>,
+    
+    #[account(
+        seeds = [b"asset", deposit_ticket.mint.as_ref()],
+        bump = asset_config.bump
+    )]
+    pub asset_config: Box<Account<'info, AssetConfig>>,
+    
+    #[account(
+        mut,
+        seeds = [b"deposit_shard", deposit_ticket.mint.as_ref(), &[deposit_shard_index(&deposit_ticket.depositor)]],
+        bump = deposit_shard.bump
+    )]
+    pub deposit_shard: Box<Account<'info, DepositShard>>,
+    
+    pub caller: Signer<'info>,
+    
+    #[account(
+        mut,
+        address = asset_config.vault
+    )]
+    pub vault: InterfaceAccount<'info, TokenAccount>,
+    
+    #[account(
+        mut,
+        constraint = depositor_token_account.owner == deposit_ticket.depositor
+    )]
+    pub depositor_token_account: InterfaceAccount<'info, TokenAccount>,
+    
+    #[account(address = asset_config.mint)]
+    pub mint: InterfaceAccount<'info, Mint>,
+    
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+#[derive(Accounts)]
+pub struct CancelDeposit<'info> {
+    #[account(
+        seeds = [b"bridge"],
+        bump = bridge_state.bump
+    )]
+    pub bridge_state: Account<'info, BridgeState>,
+    
+    #[account(
+        mut,
+        has_one = depositor
+    )]
+    pub deposit_ticket: Box<Account<'info, DepositTicket>>,
+    
+    #[account(
+        mut,
+        seeds = [b"asset", deposit_ticket.mint.as_ref()],
+        bump = asset_config.bump
+    )]
+    pub asset_config: Box<Account<'info, AssetConfig>>,
+    
+    #[account(
+        mut,
+        seeds = [b"deposit_shard", deposit_ticket.mint.as_ref(), &[deposit_shard_index(&deposit_ticket.depositor)]],
+        bump = deposit_shard.bump
+    )]
+    pub deposit_shard: Box<Account<'info, DepositShard>>,
+    
+    #[account(
+        seeds = [b"operator_set"],
+        bump = operator_set.bump
+    )]
+    pub operator_set: Box<Account<'info, OperatorSet>>,
+    
+    pub depositor: Signer<'info>,
+    
+    #[account(
+        mut,
+        address = asset_config.vault
+    )]
+    pub vault: InterfaceAccount<'info, TokenAccount>,
+    
+    #[account(
+        mut,
+        constraint = depositor_token_account.owner == deposit_ticket.depositor
+    )]
+    pub depositor_token_account: InterfaceAccount<'info, TokenAccount>,
+    
+    #[account(address = asset_config.mint)]
+    pub mint: InterfaceAccount<'info, Mint>,
+    
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+#[derive(Accounts)]
+pub struct CloseDepositTicket<'info> {
+    #[account(
+        mut,
+        close = depositor,
+        has_one = depositor
+    )]
+    pub deposit_ticket: Box<Account<'info, DepositTicket>>,
+    
+    #[account(mut)]
+    pub depositor: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(amount: u64, partial_note_commitment: [u8; 32], partial_note_nullifier: [u8; 32])]
+pub struct InitiateWithdrawal<'info> {
+    #[account(
+        mut,
+        seeds = [b"bridge"],
+        bump = bridge_state.bump
+    )]
+    pub bridge_state: Account<'info, BridgeState>,
+    
+    #[account(
+        mut,
+        seeds = [b"asset", asset_config.mint.as_ref()],
+        bump = asset_config.bump
+    )]
+    pub asset_config: Box<Account<'info, AssetConfig>>,
+    
+    #[account(
+        init,
+        payer = user,
+        space = 8 + 8 + 32 + 32 + 8 + 8 + 32 + 32 + 32 + 1 + 8 + 8 + 1 + Attestations::LEN + 8 + 32 + 32 + 8,
+        seeds = [b"withdrawal", bridge_state.withdrawal_nonce.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub withdrawal_ticket: Box<Account<'info, WithdrawalTicket>>,
+    
+    #[account(
+        init_if_needed,
+        payer = user,
+        space = 8 + 32 + 32 + 1 + 8 + 1,
+        seeds = [b"nullifier", partial_note_nullifier.as_ref()],
+        bump
+    )]
+    pub nullifier_record: Account<'info, NullifierRecord>,
+    
+    #[account(mut)]
+    pub user: Signer<'info>,
+    
+    #[account(
+        mut,
+        constraint = user_token_account.owner == user.key()
+    )]
+    pub user_token_account: InterfaceAccount<'info, TokenAccount>,
+    
+    #[account(
+        mut,
+        address = asset_config.vault
+    )]
+    pub vault: InterfaceAccount<'info, TokenAccount>,
+    
+    #[account(address = asset_config.mint)]
+    pub mint: InterfaceAccount<'info, Mint>,
+    
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ClaimDeposit<'info> {
+    #[account(
+        seeds = [b"bridge"],
+        bump = bridge_state.bump
+    )]
+    pub bridge_state: Account<'info, BridgeState>,
+    
+    #[account(mut)]
+    pub deposit_ticket: Box<Account<'info, DepositTicket>>,
+    
+    #[account(
+        seeds = [b"operator_set"],
+        bump = operator_set.bump
+    )]
+    pub operator_set: Box<Account<'info, OperatorSet>>,
+    
+    #[account(
+        seeds = [b"operator_bond", operator.key().as_ref()],
+        bump = operator_bond.bump
+    )]
+    pub operator_bond: Account<'info, OperatorBond>,
+    
+    pub operator: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct ClaimWithdrawal<'info> {
+    #[account(
+        seeds = [b"bridge"],
+        bump = bridge_state.bump
+    )]
+    pub bridge_state: Account<'info, BridgeState>,
+    
+    #[account(mut)]
+    pub withdrawal_ticket: Box<Account<'info, WithdrawalTicket>>,
+    
+    #[account(
+        seeds = [b"operator_set"],
+        bump = operator_set.bump
+    )]
+    pub operator_set: Box<Account<'info, OperatorSet>>,
+    
+    #[account(
+        seeds = [b"operator_bond", operator.key().as_ref()],
+        bump = operator_bond.bump
+    )]
+    pub operator_bond: Account<'info, OperatorBond>,
+    
+    pub operator: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct ProcessWithdrawal<'info> {
+    #[account(
+        mut,
+        seeds = [b"bridge"],
+        bump = bridge_state.bump
+    )]
+    pub bridge_state: Account<'info, BridgeState>,
+    
+    #[account(mut)]
+    pub withdrawal_ticket: Box<Account<'info, WithdrawalTicket>>,
+    
+    #[account(
+        mut,
+        seeds = [b"asset", withdrawal_ticket.mint.as_ref()],
+        bump = asset_config.bump
+    )]
+    pub asset_config: Box<Account<'info, AssetConfig>>,
+    
+    #[account(
+        seeds = [b"verifying_key"],
+        bump = verifying_key.bump
+    )]
+    pub verifying_key: Box<Account<'info, WithdrawalVerifyingKey>>,
+    
+    #[account(
+        mut,
+        seeds = [b"commitment_tree"],
+        bump = commitment_tree.bump
+    )]
+    pub commitment_tree: Box<Account<'info, CommitmentTree>>,
+    
+    #[account(
+        mut,
+        seeds = [b"nullifier", withdrawal_ticket.partial_note_nullifier.as_ref()],
+        bump = nullifier_record.bump
+    )]
+    pub nullifier_record: Account<'info, NullifierRecord>,
+    
+    #[account(
+        seeds = [b"operator_set"],
+        bump = operator_set.bump
+    )]
+    pub operator_set: Box<Account<'info, OperatorSet>>,
+    
+    #[account(
+        seeds = [b"operator_bond", operator.key().as_ref()],
+        bump = operator_bond.bump
+    )]
+    pub operator_bond: Account<'info, OperatorBond>,
+    
+    pub operator: Signer<'info>,
+    
+    #[account(
+        mut,
+        address = asset_config.mint
+    )]
+    pub mint: InterfaceAccount<'info, Mint>,
+    
+    #[account(
+        mut,
+        address = asset_config.vault
+    )]
+    pub vault: InterfaceAccount<'info, TokenAccount>,
+    
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+#[derive(Accounts)]
+pub struct BatchProcessWithdrawals<'info> {
+    #[account(
+        seeds = [b"bridge"],
+        bump = bridge_state.bump
+    )]
+    pub bridge_state: Account<'info, BridgeState>,
+    
+    #[account(
+        mut,
+        seeds = [b"asset", asset_config.mint.as_ref()],
+        bump = asset_config.bump
+    )]
+    pub asset_config: Box<Account<'info, AssetConfig>>,
+    
+    #[account(
+        mut,
+        seeds = [b"commitment_tree"],
+        bump = commitment_tree.bump
+    )]
+    pub commitment_tree: Box<Account<'info, CommitmentTree>>,
+    
+    #[account(
+        seeds = [b"verifying_key"],
+        bump = verifying_key.bump
+    )]
+    pub verifying_key: Box<Account<'info, WithdrawalVerifyingKey>>,
+    
+    #[account(
+        seeds = [b"operator_set"],
+        bump = operator_set.bump
+    )]
+    pub operator_set: Box<Account<'info, OperatorSet>>,
+    
+    #[account(
+        seeds = [b"operator_bond", operator.key().as_ref()],
+        bump = operator_bond.bump
+    )]
+    pub operator_bond: Account<'info, OperatorBond>,
+    
+    pub operator: Signer<'info>,
+    
+    #[account(
+        mut,
+        address = asset_config.mint
+    )]
+    pub mint: InterfaceAccount<'info, Mint>,
+    
+    #[account(
+        mut,
+        address = asset_config.vault
+    )]
+    pub vault: InterfaceAccount<'info, TokenAccount>,
+    
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+#[derive(Accounts)]
+pub struct ReleaseDeferredWithdrawal<'info> {
+    #[account(
+        mut,
+        seeds = [b"bridge"],
+        bump = bridge_state.bump
+    )]
+    pub bridge_state: Account<'info, BridgeState>,
+    
+    #[account(mut)]
+    pub withdrawal_ticket: Box<Account<'info, WithdrawalTicket>>,
+    
+    #[account(
+        mut,
+        seeds = [b"commitment_tree"],
+        bump = commitment_tree.bump
+    )]
+    pub commitment_tree: Box<Account<'info, CommitmentTree>>,
+    
+    #[account(
+        mut,
+        seeds = [b"asset", withdrawal_ticket.mint.as_ref()],
+        bump = asset_config.bump
+    )]
+    pub asset_config: Box<Account<'info, AssetConfig>>,
+    
+    #[account(
+        mut,
+        seeds = [b"nullifier", withdrawal_ticket.partial_note_nullifier.as_ref()],
+        bump = nullifier_record.bump
+    )]
+    pub nullifier_record: Account<'info, NullifierRecord>,
+    
+    #[account(
+        seeds = [b"operator_set"],
+        bump = operator_set.bump
+    )]
+    pub operator_set: Box<Account<'info, OperatorSet>>,
+    
+    pub operator: Signer<'info>,
+    
+    #[account(
+        mut,
+        address = asset_config.mint
+    )]
+    pub mint: InterfaceAccount<'info, Mint>,
+    
+    #[account(
+        mut,
+        address = asset_config.vault
+    )]
+    pub vault: InterfaceAccount<'info, TokenAccount>,
+    
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+#[derive(Accounts)]
+pub struct FailWithdrawal<'info> {
+    #[account(
+        seeds = [b"bridge"],
+        bump = bridge_state.bump
+    )]
+    pub bridge_state: Account<'info, BridgeState>,
+    
+    #[account(mut)]
+    pub withdrawal_ticket: Box<Account<'info, WithdrawalTicket>>,
+    
+    #[account(
+        mut,
+        seeds = [b"asset", withdrawal_ticket.mint.as_ref()],
+        bump = asset_config.bump
+    )]
+    pub asset_config: Box<Account<'info, AssetConfig>>,
+    
+    #[account(
+        mut,
+        seeds = [b"nullifier", withdrawal_ticket.partial_note_nullifier.as_ref()],
+        bump = nullifier_record.bump
+    )]
+    pub nullifier_record: Account<'info, NullifierRecord>,
+    
+    #[account(
+        seeds = [b"operator_set"],
+        bump = operator_set.bump
+    )]
+    pub operator_set: Box<Account<'info, OperatorSet>>,
+    
+    #[account(
+        seeds = [b"operator_bond", operator.key().as_ref()],
+        bump = operator_bond.bump
+    )]
+    pub operator_bond: Account<'info, OperatorBond>,
+    
+    pub operator: Signer<'info>,
+    
+    #[account(
+        mut,
+        address = asset_config.vault
+    )]
+    pub vault: InterfaceAccount<'info, TokenAccount>,
+    
+    #[account(
+        mut,
+        constraint = recipient_token_account.owner == withdrawal_ticket.recipient
+    )]
+    pub recipient_token_account: InterfaceAccount<'info, TokenAccount>,
+    
+    #[account(address = asset_config.mint)]
+    pub mint: InterfaceAccount<'info, Mint>,
+    
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+#[derive(Accounts)]
+pub struct CancelWithdrawal<'info> {
+    #[account(
+        seeds = [b"bridge"],
+        bump = bridge_state.bump
+    )]
+    pub bridge_state: Account<'info, BridgeState>,
+    
+    #[account(
+        mut,
+        has_one = recipient
+    )]
+    pub withdrawal_ticket: Box<Account<'info, WithdrawalTicket>>,
+    
+    #[account(
+        mut,
+        seeds = [b"asset", withdrawal_ticket.mint.as_ref()],
+        bump = asset_config.bump
+    )]
+    pub asset_config: Box<Account<'info, AssetConfig>>,
+    
+    #[account(
+        mut,
+        seeds = [b"nullifier", withdrawal_ticket.partial_note_nullifier.as_ref()],
+        bump = nullifier_record.bump
+    )]
+    pub nullifier_record: Account<'info, NullifierRecord>,
+    
+    #[account(
+        seeds = [b"operator_set"],
+        bump = operator_set.bump
+    )]
+    pub operator_set: Box<Account<'info, OperatorSet>>,
+    
+    pub recipient: Signer<'info>,
+    
+    #[account(
+        mut,
+        address = asset_config.vault
+    )]
+    pub vault: InterfaceAccount<'info, TokenAccount>,
+    
+    #[account(
+        mut,
+        constraint = recipient_token_account.owner == withdrawal_ticket.recipient
+    )]
+    pub recipient_token_account: InterfaceAccount<'info, TokenAccount>,
+    
+    #[account(address = asset_config.mint)]
+    pub mint: InterfaceAccount<'info, Mint>,
+    
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+#[derive(Accounts)]
+pub struct CloseWithdrawalTicket<'info> {
+    #[account(
+        mut,
+        close = recipient,
+        has_one = recipient
+    )]
+    pub withdrawal_ticket: Box<Account<'info, WithdrawalTicket>>,
+    
+    #[account(mut)]
+    pub recipient: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct InitializeCommitmentTree<'info> {
+    #[account(
+        seeds = [b"bridge"],
+        bump = bridge_state.bump
+    )]
+    pub bridge_state: Account<'info, BridgeState>,
+    
+    #[account(
+        init,
+        payer = authority,
+        space = CommitmentTree::LEN,
+        seeds = [b"commitment_tree"],
+        bump
+    )]
+    pub commitment_tree: Box<Account<'info, CommitmentTree>>,
+    
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct InitializeLightClient<'info> {
+    #[account(
+        seeds = [b"bridge"],
+        bump = bridge_state.bump
+    )]
+    pub bridge_state: Account<'info, BridgeState>,
+    
+    #[account(
+        init,
+        payer = authority,
+        space = ZcashLightClient::LEN,
+        seeds = [b"zcash_light_client"],
+        bump
+    )]
+    pub light_client: Box<Account<'info, ZcashLightClient>>,
+    
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct WriteHeaderBuffer<'info> {
+    #[account(
+        init_if_needed,
+        payer = relayer,
+        space = HeaderBuffer::LEN,
+        seeds = [b"header_buffer", relayer.key().as_ref()],
+        bump
+    )]
+    pub header_buffer: Box<Account<'info, HeaderBuffer>>,
+    
+    #[account(mut)]
+    pub relayer: Signer<'info>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct SubmitZcashHeader<'info> {
+    #[account(
+        seeds = [b"bridge"],
+        bump = bridge_state.bump
+    )]
+    pub bridge_state: Account<'info, BridgeState>,
+    
+    #[account(
+        mut,
+        seeds = [b"zcash_light_client"],
+        bump = light_client.bump
+    )]
+    pub light_client: Box<Account<'info, ZcashLightClient>>,
+    
+    #[account(
+        mut,
+        seeds = [b"header_buffer", relayer.key().as_ref()],
+        bump = header_buffer.bump
+    )]
+    pub header_buffer: Box<Account<'info, HeaderBuffer>>,
+    
+    #[account(
+        seeds = [b"relayer", relayer.key().as_ref()],
+        bump = relayer_record.bump
+    )]
+    pub relayer_record: Account<'info, RelayerRecord>,
+    
+    pub relayer: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(relayer: Pubkey)]
+pub struct AddRelayer<'info> {
+    #[account(
+        seeds = [b"bridge"],
+        bump = bridge_state.bump
+    )]
+    pub bridge_state: Account<'info, BridgeState>,
+    
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + 32 + 8 + 1,
+        seeds = [b"relayer", relayer.as_ref()],
+        bump
+    )]
+    pub relayer_record: Account<'info, RelayerRecord>,
+    
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct RemoveRelayer<'info> {
+    #[account(
+        seeds = [b"bridge"],
+        bump = bridge_state.bump
+    )]
+    pub bridge_state: Account<'info, BridgeState>,
+    
+    #[account(
+        mut,
+        close = authority,
+        seeds = [b"relayer", relayer_record.relayer.as_ref()],
+        bump = relayer_record.bump
+    )]
+    pub relayer_record: Account<'info, RelayerRecord>,
+    
+    #[account(mut)]
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SetWithdrawalVerifyingKey<'info> {
+    #[account(
+        seeds = [b"bridge"],
+        bump = bridge_state.bump
+    )]
+    pub bridge_state: Account<'info, BridgeState>,
+    
+    #[account(
+        init_if_needed,
+        payer = authority,
+        space = 8 + 64 + 128 + 128 + 128 + 64 * (groth16::NR_PUBLIC_INPUTS + 1) + 1,
+        seeds = [b"verifying_key"],
+        bump
+    )]
+    pub verifying_key: Box<Account<'info, WithdrawalVerifyingKey>>,
+    
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(ephemeral_pub_key: [u8; 33])]
+pub struct GenerateStealthAddress<'info> {
+    #[account(
+        seeds = [b"meta", owner.key().as_ref()],
+        bump = meta_address.bump
+    )]
+    pub meta_address: Account<'info, MetaAddress>,
+    
+    #[account(
+        init,
+        payer = payer,
+        space = 8 + 32 + 33 + 1 + 4 + 1 + 8 + 1 + 32,
+        seeds = [b"stealth", owner.key().as_ref(), ephemeral_pub_key.as_ref()],
+        bump
+    )]
+    pub stealth_address: Account<'info, StealthAddress>,
+    
+    pub owner: SystemAccount<'info>,
+    
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct SendToStealth<'info> {
+    pub stealth_address: Account<'info, StealthAddress>,
+    
+    #[account(mut)]
+    pub sender: Signer<'info>,
+    
+    #[account(
+        mut,
+        constraint = sender_token_account.owner == sender.key()
+    )]
+    pub sender_token_account: InterfaceAccount<'info, TokenAccount>,
+    
+    #[account(mut)]
+    pub stealth_token_account: InterfaceAccount<'info, TokenAccount>,
+    
+    #[account(address = stealth_token_account.mint)]
+    pub mint: InterfaceAccount<'info, Mint>,
+    
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+#[derive(Accounts)]
+pub struct ClaimFromStealth<'info> {
+    #[account(
+        mut,
+        seeds = [b"stealth", owner.key().as_ref(), stealth_address.ephemeral_pub_key.as_ref()],
+        bump = stealth_address.bump
+    )]
+    pub stealth_address: Account<'info, StealthAddress>,
+    
+    #[account(mut)]
+    pub owner: Signer<'info>,
+    
+    #[account(
+        mut,
+        constraint = stealth_token_account.owner == stealth_address.key()
+    )]
+    pub stealth_token_account: InterfaceAccount<'info, TokenAccount>,
+    
+    #[account(
+        mut,
+        constraint = owner_token_account.owner == owner.key()
+    )]
+    pub owner_token_account: InterfaceAccount<'info, TokenAccount>,
+    
+    #[account(address = stealth_token_account.mint)]
+    pub mint: InterfaceAccount<'info, Mint>,
+    
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+#[derive(Accounts)]
+pub struct CloseStealthAddress<'info> {
+    #[account(
+        mut,
+        close = payer,
+        has_one = payer
+    )]
+    pub stealth_address: Account<'info, StealthAddress>,
+    
+    #[account(
+        mut,
+        constraint = stealth_token_account.owner == stealth_address.key()
+    )]
+    pub stealth_token_account: InterfaceAccount<'info, TokenAccount>,
+    
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+#[derive(Accounts)]
+pub struct ManageOperators<'info> {
+    #[account(
+        seeds = [b"bridge"],
+        bump = bridge_state.bump
+    )]
+    pub bridge_state: Account<'info, BridgeState>,
+    
+    #[account(
+        mut,
+        seeds = [b"operator_set"],
+        bump = operator_set.bump
+    )]
+    pub operator_set: Box<Account<'info, OperatorSet>>,
+    
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(operator: Pubkey)]
+pub struct AddOperator<'info> {
+    #[account(
+        seeds = [b"bridge"],
+        bump = bridge_state.bump
+    )]
+    pub bridge_state: Account<'info, BridgeState>,
+    
+    #[account(
+        mut,
+        seeds = [b"operator_set"],
+        bump = operator_set.bump
+    )]
+    pub operator_set: Box<Account<'info, OperatorSet>>,
+    
+    #[account(
+        seeds = [b"operator_bond", operator.as_ref()],
+        bump = operator_bond.bump
+    )]
+    pub operator_bond: Account<'info, OperatorBond>,
+    
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct InitializeBondVault<'info> {
+    #[account(
+        seeds = [b"bridge"],
+        bump = bridge_state.bump
+    )]
+    pub bridge_state: Account<'info, BridgeState>,
+    
+    #[account(
+        address = bridge_state.wrapped_zec_mint
+    )]
+    pub wrapped_zec_mint: InterfaceAccount<'info, Mint>,
+    
+    #[account(
+        init,
+        payer = authority,
+        token::mint = wrapped_zec_mint,
+        token::authority = bridge_state,
+        token::token_program = token_program,
+        seeds = [b"bond_vault"],
+        bump
+    )]
+    pub bond_vault: InterfaceAccount<'info, TokenAccount>,
+    
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    
+    pub system_program: Program<'info, System>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub rent: Sysvar<'info, Rent>,
+}
+
+#[derive(Accounts)]
+pub struct SetBondParams<'info> {
+    #[account(
+        mut,
+        seeds = [b"bridge"],
+        bump = bridge_state.bump
+    )]
+    pub bridge_state: Account<'info, BridgeState>,
+    
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct BondOperator<'info> {
+    #[account(
+        init_if_needed,
+        payer = operator,
+        space = 8 + 32 + 8 + 8 + 8 + 1,
+        seeds = [b"operator_bond", operator.key().as_ref()],
+        bump
+    )]
+    pub operator_bond: Account<'info, OperatorBond>,
+    
+    #[account(mut)]
+    pub operator: Signer<'info>,
+    
+    #[account(
+        mut,
+        constraint = operator_token_account.owner == operator.key()
+    )]
+    pub operator_token_account: InterfaceAccount<'info, TokenAccount>,
+    
+    #[account(
+        mut,
+        seeds = [b"bond_vault"],
+        bump
+    )]
+    pub bond_vault: InterfaceAccount<'info, TokenAccount>,
+    
+    #[account(address = bond_vault.mint)]
+    pub mint: InterfaceAccount<'info, Mint>,
+    
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct BeginUnbonding<'info> {
+    #[account(
+        seeds = [b"bridge"],
+        bump = bridge_state.bump
+    )]
+    pub bridge_state: Account<'info, BridgeState>,
+    
+    #[account(
+        seeds = [b"operator_set"],
+        bump = operator_set.bump
+    )]
+    pub operator_set: Box<Account<'info, OperatorSet>>,
+    
+    #[account(
+        mut,
+        seeds = [b"operator_bond", operator.key().as_ref()],
+        bump = operator_bond.bump
+    )]
+    pub operator_bond: Account<'info, OperatorBond>,
+    
+    pub operator: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct WithdrawUnbonded<'info> {
+    #[account(
+        seeds = [b"bridge"],
+        bump = bridge_state.bump
+    )]
+    pub bridge_state: Account<'info, BridgeState>,
+    
+    #[account(
+        mut,
+        seeds = [b"operator_bond", operator.key().as_ref()],
+        bump = operator_bond.bump
+    )]
+    pub operator_bond: Account<'info, OperatorBond>,
+    
+    pub operator: Signer<'info>,
+    
+    #[account(
+        mut,
+        constraint = operator_token_account.owner == operator.key()
+    )]
+    pub operator_token_account: InterfaceAccount<'info, TokenAccount>,
+    
+    #[account(
+        mut,
+        seeds = [b"bond_vault"],
+        bump
+    )]
+    pub bond_vault: InterfaceAccount<'info, TokenAccount>,
+    
+    #[account(address = bond_vault.mint)]
+    pub mint: InterfaceAccount<'info, Mint>,
+    
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+#[derive(Accounts)]
+pub struct EjectUnderbondedOperator<'info> {
+    #[account(
+        seeds = [b"bridge"],
+        bump = bridge_state.bump
+    )]
+    pub bridge_state: Account<'info, BridgeState>,
+    
+    #[account(
+        mut,
+        seeds = [b"operator_set"],
+        bump = operator_set.bump
+    )]
+    pub operator_set: Box<Account<'info, OperatorSet>>,
+    
+    #[account(
+        seeds = [b"operator_bond", operator_bond.operator.as_ref()],
+        bump = operator_bond.bump
+    )]
+    pub operator_bond: Account<'info, OperatorBond>,
+    
+    pub caller: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SlashOperator<'info> {
+    #[account(
+        seeds = [b"bridge"],
+        bump = bridge_state.bump
+    )]
+    pub bridge_state: Account<'info, BridgeState>,
+    
+    #[account(
+        mut,
+        seeds = [b"operator_set"],
+        bump = operator_set.bump
+    )]
+    pub operator_set: Box<Account<'info, OperatorSet>>,
+    
+    #[account(mut)]
+    pub operator_bond: Account<'info, OperatorBond>,
+    
+    pub authority: Signer<'info>,
+    
+    #[account(
+        mut,
+        seeds = [b"bond_vault"],
+        bump
+    )]
+    pub bond_vault: InterfaceAccount<'info, TokenAccount>,
+    
+    /// Affected user or treasury receiving the slashed stake
+    #[account(mut)]
+    pub recipient_token_account: InterfaceAccount<'info, TokenAccount>,
+    
+    #[account(address = bond_vault.mint)]
+    pub mint: InterfaceAccount<'info, Mint>,
+    
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+#[derive(Accounts)]
+pub struct CollectFees<'info> {
+    #[account(
+        mut,
+        seeds = [b"bridge"],
+        bump = bridge_state.bump
+    )]
+    pub bridge_state: Account<'info, BridgeState>,
+    
+    #[account(
+        mut,
+        seeds = [b"asset", asset_config.mint.as_ref()],
+        bump = asset_config.bump
+    )]
+    pub asset_config: Box<Account<'info, AssetConfig>>,
+    
+    pub authority: Signer<'info>,
+    
+    #[account(
+        mut,
+        address = asset_config.vault
+    )]
+    pub vault: InterfaceAccount<'info, TokenAccount>,
+    
+    #[account(mut)]
+    pub treasury_token_account: InterfaceAccount<'info, TokenAccount>,
+    
+    #[account(address = asset_config.mint)]
+    pub mint: InterfaceAccount<'info, Mint>,
+    
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateConfig<'info> {
+    #[account(
+        mut,
+        seeds = [b"bridge"],
+        bump = bridge_state.bump
+    )]
+    pub bridge_state: Account<'info, BridgeState>,
+    
+    #[account(
+        mut,
+        seeds = [b"asset", asset_config.mint.as_ref()],
+        bump = asset_config.bump
+    )]
+    pub asset_config: Box<Account<'info, AssetConfig>>,
+    
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct ProposeAuthority<'info> {
+    #[account(
+        mut,
+        seeds = [b"bridge"],
+        bump = bridge_state.bump
+    )]
+    pub bridge_state: Account<'info, BridgeState>,
+    
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct AcceptAuthority<'info> {
+    #[account(
+        mut,
+        seeds = [b"bridge"],
+        bump = bridge_state.bump
+    )]
+    pub bridge_state: Account<'info, BridgeState>,
+    
+    pub new_authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SetGuardian<'info> {
+    #[account(
+        mut,
+        seeds = [b"bridge"],
+        bump = bridge_state.bump
+    )]
+    pub bridge_state: Account<'info, BridgeState>,
+    
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct FinalizeDeposit<'info> {
+    #[account(
+        mut,
+        seeds = [b"bridge"],
+        bump = bridge_state.bump
+    )]
+    pub bridge_state: Account<'info, BridgeState>,
+    
+    #[account(mut)]
+    pub deposit_ticket: Box<Account<'info, DepositTicket>>,
+    
+    #[account(
+        mut,
+        seeds = [b"asset", deposit_ticket.mint.as_ref()],
+        bump = asset_config.bump
+    )]
+    pub asset_config: Box<Account<'info, AssetConfig>>,
+    
+    #[account(
+        mut,
+        seeds = [b"deposit_shard", deposit_ticket.mint.as_ref(), &[deposit_shard_index(&deposit_ticket.depositor)]],
+        bump = deposit_shard.bump
+    )]
+    pub deposit_shard: Box<Account<'info, DepositShard>>,
+    
+    #[account(
+        mut,
+        seeds = [b"commitment_tree"],
+        bump = commitment_tree.bump
+    )]
+    pub commitment_tree: Box<Account<'info, CommitmentTree>>,
+    
+    #[account(
+        mut,
+        address = asset_config.mint
+    )]
+    pub mint: InterfaceAccount<'info, Mint>,
+    
+    #[account(
+        mut,
+        address = asset_config.vault
+    )]
+    pub vault: InterfaceAccount<'info, TokenAccount>,
+    
+    #[account(
+        mut,
+        constraint = depositor_token_account.owner == deposit_ticket.depositor
+    )]
+    pub depositor_token_account: InterfaceAccount<'info, TokenAccount>,
+    
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+#[derive(Accounts)]
+pub struct FinalizeWithdrawal<'info> {
+    #[account(
+        mut,
+        seeds = [b"bridge"],
+        bump = bridge_state.bump
+    )]
+    pub bridge_state: Account<'info, BridgeState>,
+    
+    #[account(mut)]
+    pub withdrawal_ticket: Box<Account<'info, WithdrawalTicket>>,
+    
+    #[account(
+        mut,
+        seeds = [b"commitment_tree"],
+        bump = commitment_tree.bump
+    )]
+    pub commitment_tree: Box<Account<'info, CommitmentTree>>,
+    
+    #[account(
+        mut,
+        seeds = [b"asset", withdrawal_ticket.mint.as_ref()],
+        bump = asset_config.bump
+    )]
+    pub asset_config: Box<Account<'info, AssetConfig>>,
+    
+    #[account(
+        mut,
+        seeds = [b"nullifier", withdrawal_ticket.partial_note_nullifier.as_ref()],
+        bump = nullifier_record.bump
+    )]
+    pub nullifier_record: Account<'info, NullifierRecord>,
+    
+    #[account(
+        mut,
+        address = asset_config.mint
+    )]
+    pub mint: InterfaceAccount<'info, Mint>,
+    
+    #[account(
+        mut,
+        address = asset_config.vault
+    )]
+    pub vault: InterfaceAccount<'info, TokenAccount>,
+    
+    pub token_program: Interface<'info, TokenInterface>,
 }
 
-#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
-pub struct DepositProof {
-    pub proof_data: [u8; 256],
-    pub public_inputs: [[u8; 8]; 4],
+#[derive(Accounts)]
+pub struct RegisterWatcher<'info> {
+    #[account(
+        seeds = [b"bridge"],
+        bump = bridge_state.bump
+    )]
+    pub bridge_state: Account<'info, BridgeState>,
+    
+    #[account(
+        init_if_needed,
+        payer = watcher,
+        space = 8 + 32 + 8 + 4 + 8 + 1,
+        seeds = [b"watcher", watcher.key().as_ref()],
+        bump
+    )]
+    pub watcher_record: Account<'info, WatcherRecord>,
+    
+    #[account(mut)]
+    pub watcher: Signer<'info>,
+    
+    pub system_program: Program<'info, System>,
 }
 
-#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
-pub struct WithdrawalProof {
-    pub proof_data: [u8; 256],
-    pub commitment: [u8; 32],
-    pub nullifier: [u8; 32],
+#[derive(Accounts)]
+pub struct UnregisterWatcher<'info> {
+    #[account(
+        mut,
+        close = watcher,
+        seeds = [b"watcher", watcher.key().as_ref()],
+        bump = watcher_record.bump
+    )]
+    pub watcher_record: Account<'info, WatcherRecord>,
+    
+    #[account(mut)]
+    pub watcher: Signer<'info>,
 }
 
-#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq)]
-pub enum DepositStatus {
-    Pending,
-    Confirmed,
-    Completed,
-    Failed,
+#[derive(Accounts)]
+pub struct ChallengeDeposit<'info> {
+    #[account(
+        seeds = [b"bridge"],
+        bump = bridge_state.bump
+    )]
+    pub bridge_state: Account<'info, BridgeState>,
+    
+    #[account(mut)]
+    pub deposit_ticket: Box<Account<'info, DepositTicket>>,
+    
+    #[account(
+        mut,
+        seeds = [b"watcher", watcher.key().as_ref()],
+        bump = watcher_record.bump
+    )]
+    pub watcher_record: Account<'info, WatcherRecord>,
+    
+    pub watcher: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct ChallengeWithdrawal<'info> {
+    #[account(
+        seeds = [b"bridge"],
+        bump = bridge_state.bump
+    )]
+    pub bridge_state: Account<'info, BridgeState>,
+    
+    #[account(mut)]
+    pub withdrawal_ticket: Box<Account<'info, WithdrawalTicket>>,
+    
+    #[account(
+        mut,
+        seeds = [b"watcher", watcher.key().as_ref()],
+        bump = watcher_record.bump
+    )]
+    pub watcher_record: Account<'info, WatcherRecord>,
+    
+    pub watcher: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct ResolveDepositChallenge<'info> {
+    #[account(
+        mut,
+        seeds = [b"bridge"],
+        bump = bridge_state.bump
+    )]
+    pub bridge_state: Account<'info, BridgeState>,
+    
+    #[account(mut)]
+    pub deposit_ticket: Box<Account<'info, DepositTicket>>,
+    
+    #[account(
+        mut,
+        seeds = [b"operator_set"],
+        bump = operator_set.bump
+    )]
+    pub operator_set: Box<Account<'info, OperatorSet>>,
+    
+    #[account(
+        seeds = [b"asset", deposit_ticket.mint.as_ref()],
+        bump = asset_config.bump
+    )]
+    pub asset_config: Box<Account<'info, AssetConfig>>,
+    
+    #[account(
+        mut,
+        seeds = [b"deposit_shard", deposit_ticket.mint.as_ref(), &[deposit_shard_index(&deposit_ticket.depositor)]],
+        bump = deposit_shard.bump
+    )]
+    pub deposit_shard: Box<Account<'info, DepositShard>>,
+    
+    #[account(
+        mut,
+        seeds = [b"watcher", deposit_ticket.challenger.as_ref()],
+        bump = watcher_record.bump
+    )]
+    pub watcher_record: Account<'info, WatcherRecord>,
+    
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    
+    #[account(
+        mut,
+        address = asset_config.vault
+    )]
+    pub vault: InterfaceAccount<'info, TokenAccount>,
+    
+    #[account(
+        mut,
+        constraint = depositor_token_account.owner == deposit_ticket.depositor
+    )]
+    pub depositor_token_account: InterfaceAccount<'info, TokenAccount>,
+    
+    #[account(address = asset_config.mint)]
+    pub mint: InterfaceAccount<'info, Mint>,
+    
+    pub token_program: Interface<'info, TokenInterface>,
+    
+    #[account(
+        mut,
+        seeds = [b"bond_vault"],
+        bump
+    )]
+    pub bond_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+    
+    /// Affected user or treasury receiving the attesters' slashed stake
+    #[account(mut)]
+    pub slash_recipient: Box<InterfaceAccount<'info, TokenAccount>>,
+    
+    #[account(address = bond_vault.mint)]
+    pub bond_mint: Box<InterfaceAccount<'info, Mint>>,
+    
+    pub bond_token_program: Interface<'info, TokenInterface>,
+}
+
+#[derive(Accounts)]
+pub struct ResolveWithdrawalChallenge<'info> {
+    #[account(
+        seeds = [b"bridge"],
+        bump = bridge_state.bump
+    )]
+    pub bridge_state: Account<'info, BridgeState>,
+    
+    #[account(mut)]
+    pub withdrawal_ticket: Box<Account<'info, WithdrawalTicket>>,
+    
+    #[account(
+        mut,
+        seeds = [b"asset", withdrawal_ticket.mint.as_ref()],
+        bump = asset_config.bump
+    )]
+    pub asset_config: Box<Account<'info, AssetConfig>>,
+    
+    #[account(
+        mut,
+        seeds = [b"nullifier", withdrawal_ticket.partial_note_nullifier.as_ref()],
+        bump = nullifier_record.bump
+    )]
+    pub nullifier_record: Account<'info, NullifierRecord>,
+    
+    #[account(
+        mut,
+        seeds = [b"operator_set"],
+        bump = operator_set.bump
+    )]
+    pub operator_set: Box<Account<'info, OperatorSet>>,
+    
+    #[account(
+        mut,
+        seeds = [b"watcher", withdrawal_ticket.challenger.as_ref()],
+        bump = watcher_record.bump
+    )]
+    pub watcher_record: Account<'info, WatcherRecord>,
+    
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    
+    #[account(
+        mut,
+        address = asset_config.vault
+    )]
+    pub vault: InterfaceAccount<'info, TokenAccount>,
+    
+    #[account(
+        mut,
+        constraint = recipient_token_account.owner == withdrawal_ticket.recipient
+    )]
+    pub recipient_token_account: InterfaceAccount<'info, TokenAccount>,
+    
+    #[account(address = asset_config.mint)]
+    pub mint: InterfaceAccount<'info, Mint>,
+    
+    pub token_program: Interface<'info, TokenInterface>,
+    
+    #[account(
+        mut,
+        seeds = [b"bond_vault"],
+        bump
+    )]
+    pub bond_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+    
+    /// Affected user or treasury receiving the attesters' slashed stake
+    #[account(mut)]
+    pub slash_recipient: Box<InterfaceAccount<'info, TokenAccount>>,
+    
+    #[account(address = bond_vault.mint)]
+    pub bond_mint: Box<InterfaceAccount<'info, Mint>>,
+    
+    pub bond_token_program: Interface<'info, TokenInterface>,
+}
+
+#[derive(Accounts)]
+pub struct SetOptimisticMode<'info> {
+    #[account(
+        mut,
+        seeds = [b"bridge"],
+        bump = bridge_state.bump
+    )]
+    pub bridge_state: Account<'info, BridgeState>,
+    
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SetSupplyCap<'info> {
+    #[account(
+        seeds = [b"bridge"],
+        bump = bridge_state.bump
+    )]
+    pub bridge_state: Account<'info, BridgeState>,
+    
+    #[account(
+        mut,
+        seeds = [b"asset", asset_config.mint.as_ref()],
+        bump = asset_config.bump
+    )]
+    pub asset_config: Box<Account<'info, AssetConfig>>,
+    
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SyncDepositTotals<'info> {
+    #[account(
+        mut,
+        seeds = [b"asset", asset_config.mint.as_ref()],
+        bump = asset_config.bump
+    )]
+    pub asset_config: Box<Account<'info, AssetConfig>>,
+}
+
+#[derive(Accounts)]
+pub struct AuditReserves<'info> {
+    #[account(
+        mut,
+        seeds = [b"bridge"],
+        bump = bridge_state.bump
+    )]
+    pub bridge_state: Account<'info, BridgeState>,
+    
+    #[account(
+        seeds = [b"asset", asset_config.mint.as_ref()],
+        bump = asset_config.bump
+    )]
+    pub asset_config: Box<Account<'info, AssetConfig>>,
+    
+    #[account(address = asset_config.vault)]
+    pub vault: InterfaceAccount<'info, TokenAccount>,
+    
+    #[account(address = asset_config.mint)]
+    pub mint: InterfaceAccount<'info, Mint>,
+    
+    pub caller: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SetOutflowLimit<'info> {
+    #[account(
+        mut,
+        seeds = [b"bridge"],
+        bump = bridge_state.bump
+    )]
+    pub bridge_state: Account<'info, BridgeState>,
+    
+    #[account(
+        mut,
+        seeds = [b"asset", asset_config.mint.as_ref()],
+        bump = asset_config.bump
+    )]
+    pub asset_config: Box<Account<'info, AssetConfig>>,
+    
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct PauseBridge<'info> {
+    #[account(
+        mut,
+        seeds = [b"bridge"],
+        bump = bridge_state.bump
+    )]
+    pub bridge_state: Account<'info, BridgeState>,
+    
+    /// Authority or guardian
+    pub pauser: Signer<'info>,
 }
 
-#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq)]
-pub enum WithdrawalStatus {
-    Pending,
-    Processing,
-    Completed,
-    Failed,
+#[derive(Accounts)]
+pub struct UnpauseBridge<'info> {
+    #[account(
+        mut,
+        seeds = [b"bridge"],
+        bump = bridge_state.bump
+    )]
+    pub bridge_state: Account<'info, BridgeState>,
+    
+    pub authority: Signer<'info>,
 }
 
-#[account]
-#[derive(Default)]
-pub struct BridgeState {
+#[event]
+pub struct BridgeInitialized {
     pub authority: Pubkey,
     pub operator: Pubkey,
     pub wrapped_zec_mint: Pubkey,
-    pub vault: Pubkey,
-    pub deposit_nonce: u64,
-    pub withdrawal_nonce: u64,
-    pub total_deposited: u64,
-    pub total_withdrawn: u64,
-    pub is_paused: bool,
-    pub min_deposit: u64,
-    pub max_deposit: u64,
-    pub protocol_fee_bps: u16,
-    pub bump: u8,
+    pub timestamp: i64,
 }
 
-#[account]
-pub struct MetaAddress {
+#[event]
+pub struct MetaAddressRegistered {
     pub owner: Pubkey,
     pub spend_pub_key: [u8; 33],
     pub viewing_pub_key: [u8; 33],
-    pub is_active: bool,
-    pub created_at: i64,
-    pub bump: u8,
+    pub timestamp: i64,
 }
 
-#[account]
-pub struct DepositTicket {
+#[event]
+pub struct DepositInitiated {
     pub ticket_id: u64,
     pub depositor: Pubkey,
     pub amount: u64,
-    pub fee: u64,
-    pub zcash_shielded_address: [u8; 78],
-    pub memo: [u8; 64],
-    pub status: DepositStatus,
-    pub created_at: i64,
-    pub processed_at: i64,
+    pub zcash_address_hash: [u8; 32],
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct ReservesAudited {
+    pub mint: Pubkey,
+    /// Vault balance
+    pub reserves: u64,
+    pub pending_deposits: u64,
+    pub pending_withdrawals: u64,
+    pub accrued_fees: u64,
+    pub liabilities: u64,
+    pub supply: u64,
+    pub locked_amount: u64,
+    pub solvent: bool,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct DepositTotalsSynced {
+    pub mint: Pubkey,
+    pub total_deposited: u64,
+    pub deposit_count: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct DepositAttested {
+    pub ticket_id: u64,
+    pub depositor: Pubkey,
+    pub operator: Pubkey,
+    pub attestations: u8,
+    pub threshold: u8,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct DepositConfirmed {
+    pub ticket_id: u64,
+    pub depositor: Pubkey,
     pub zcash_tx_id: [u8; 32],
-    pub bump: u8,
+    pub timestamp: i64,
 }
 
-#[account]
-pub struct WithdrawalTicket {
+#[event]
+pub struct DepositCompleted {
+    pub ticket_id: u64,
+    pub depositor: Pubkey,
+    pub amount: u64,
+    /// Zero for minted deposits, which deliver no note
+    pub note_commitment: [u8; 32],
+    pub transcript_hash: [u8; 32],
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct DepositFailed {
+    pub ticket_id: u64,
+    pub depositor: Pubkey,
+    pub refunded: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct DepositRefunded {
+    pub ticket_id: u64,
+    pub depositor: Pubkey,
+    pub refunded: u64,
+    pub caller: Pubkey,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct WithdrawalInitiated {
     pub ticket_id: u64,
     pub recipient: Pubkey,
     pub amount: u64,
-    pub fee: u64,
-    pub partial_note_commitment: [u8; 32],
-    pub partial_note_nullifier: [u8; 32],
-    pub encrypted_value: [u8; 32],
-    pub status: WithdrawalStatus,
-    pub created_at: i64,
-    pub processed_at: i64,
-    pub bump: u8,
+    pub commitment: [u8; 32],
+    pub timestamp: i64,
 }
 
-#[account]
-pub struct StealthAddress {
-    pub owner: Pubkey,
-    pub ephemeral_pub_key: [u8; 33],
-    pub view_hint: u8,
-    pub k: u32,
-    pub is_spent: bool,
-    pub created_at: i64,
-    pub bump: u8,
+#[event]
+pub struct DepositClaimed {
+    pub ticket_id: u64,
+    pub depositor: Pubkey,
+    pub operator: Pubkey,
+    /// Holder of the lapsed lease being taken over, default on first claim
+    pub previous_operator: Pubkey,
+    pub lease_expires_at: i64,
+    pub timestamp: i64,
 }
 
-#[derive(Accounts)]
-pub struct Initialize<'info> {
-    #[account(
-        init,
-        payer = authority,
-        space = 8 + 32 + 32 + 32 + 32 + 8 + 8 + 8 + 8 + 1 + 8 + 8 + 2 + 1,
-        seeds = [b"bridge"],
-        bump
-    )]
-    pub bridge_state: Account<'info, BridgeState>,
-    
-    #[account(mut)]
-    pub authority: Signer<'info>,
-    
-    pub wrapped_zec_mint: Account<'info, Mint>,
-    
-    #[account(
-        init,
-        payer = authority,
-        token::mint = wrapped_zec_mint,
-        token::authority = bridge_state,
-        seeds = [b"vault"],
-        bump
-    )]
-    pub vault: Account<'info, TokenAccount>,
-    
-    pub system_program: Program<'info, System>,
-    pub token_program: Program<'info, Token>,
-    pub rent: Sysvar<'info, Rent>,
+#[event]
+pub struct WithdrawalClaimed {
+    pub ticket_id: u64,
+    pub operator: Pubkey,
+    /// Holder of the lapsed lease being taken over, default on first claim
+    pub previous_operator: Pubkey,
+    pub lease_expires_at: i64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct WithdrawalAttested {
+    pub ticket_id: u64,
+    pub operator: Pubkey,
+    pub attestations: u8,
+    pub threshold: u8,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct WithdrawalProcessed {
+    pub ticket_id: u64,
+    pub recipient: Pubkey,
+    pub amount: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct CommitmentAppended {
+    pub leaf: [u8; 32],
+    pub leaf_index: u32,
+    pub root: [u8; 32],
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct ZcashHeaderAccepted {
+    pub hash: [u8; 32],
+    pub height: u32,
+    pub cumulative_work: u128,
+    pub is_new_tip: bool,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct VerifyingKeyUpdated {
+    pub key_hash: [u8; 32],
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct WithdrawalDeferred {
+    pub ticket_id: u64,
+    pub amount: u64,
+    pub window_outflow: u64,
+    pub max_outflow: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct WithdrawalAwaitingFinality {
+    pub ticket_id: u64,
+    pub finalizes_at: i64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct WithdrawalChallenged {
+    pub ticket_id: u64,
+    pub watcher: Pubkey,
+    pub kind: FraudClaimKind,
+    pub evidence_hash: [u8; 32],
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct WithdrawalChallengeResolved {
+    pub ticket_id: u64,
+    pub watcher: Pubkey,
+    pub upheld: bool,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct DepositAwaitingFinality {
+    pub ticket_id: u64,
+    pub depositor: Pubkey,
+    pub zcash_tx_id: [u8; 32],
+    pub finalizes_at: i64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct DepositChallenged {
+    pub ticket_id: u64,
+    pub depositor: Pubkey,
+    pub watcher: Pubkey,
+    pub kind: FraudClaimKind,
+    pub evidence_hash: [u8; 32],
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct DepositChallengeResolved {
+    pub ticket_id: u64,
+    pub depositor: Pubkey,
+    pub watcher: Pubkey,
+    pub upheld: bool,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct WatcherBonded {
+    pub watcher: Pubkey,
+    pub bond: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct WithdrawalFailed {
+    pub ticket_id: u64,
+    pub recipient: Pubkey,
+    pub refunded: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct DepositBatchConfirmed {
+    pub mint: Pubkey,
+    pub operator: Pubkey,
+    pub submitted: u32,
+    pub applied: u32,
+    /// Tickets rejected in best-effort mode
+    pub skipped: Vec<Pubkey>,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct WithdrawalBatchProcessed {
+    pub mint: Pubkey,
+    pub operator: Pubkey,
+    pub submitted: u32,
+    pub applied: u32,
+    /// Tickets rejected in best-effort mode
+    pub skipped: Vec<Pubkey>,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct DepositCancelled {
+    pub ticket_id: u64,
+    pub depositor: Pubkey,
+    pub refunded: u64,
+    pub fee_retained: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct WithdrawalCancelled {
+    pub ticket_id: u64,
+    pub recipient: Pubkey,
+    pub refunded: u64,
+    pub fee_retained: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct DepositTicketClosed {
+    pub ticket: Pubkey,
+    pub ticket_id: u64,
+    pub depositor: Pubkey,
+    pub mint: Pubkey,
+    pub amount: u64,
+    pub fee: u64,
+    pub status: DepositStatus,
+    pub zcash_tx_id: [u8; 32],
+    /// Keccak hash of the ticket's final serialized state
+    pub record_hash: [u8; 32],
+    pub timestamp: i64,
 }
 
-#[derive(Accounts)]
-pub struct RegisterStealthMetaAddress<'info> {
-    #[account(
-        init,
-        payer = user,
-        space = 8 + 32 + 33 + 33 + 1 + 8 + 1,
-        seeds = [b"meta", user.key().as_ref()],
-        bump
-    )]
-    pub meta_address: Account<'info, MetaAddress>,
-    
-    #[account(mut)]
-    pub user: Signer<'info>,
-    
-    pub system_program: Program<'info, System>,
+#[event]
+pub struct WithdrawalTicketClosed {
+    pub ticket: Pubkey,
+    pub ticket_id: u64,
+    pub recipient: Pubkey,
+    pub mint: Pubkey,
+    pub amount: u64,
+    pub fee: u64,
+    pub status: WithdrawalStatus,
+    pub partial_note_nullifier: [u8; 32],
+    /// Keccak hash of the ticket's final serialized state
+    pub record_hash: [u8; 32],
+    pub timestamp: i64,
 }
 
-#[derive(Accounts)]
-pub struct InitiateDeposit<'info> {
-    #[account(
-        mut,
-        seeds = [b"bridge"],
-        bump = bridge_state.bump
-    )]
-    pub bridge_state: Account<'info, BridgeState>,
-    
-    #[account(
-        init,
-        payer = user,
-        space = 8 + 8 + 32 + 8 + 8 + 78 + 64 + 1 + 8 + 8 + 32 + 1,
-        seeds = [b"deposit", bridge_state.deposit_nonce.to_le_bytes().as_ref()],
-        bump
-    )]
-    pub deposit_ticket: Account<'info, DepositTicket>,
-    
-    #[account(mut)]
-    pub user: Signer<'info>,
-    
-    #[account(
-        mut,
-        constraint = user_token_account.owner == user.key()
-    )]
-    pub user_token_account: Account<'info, TokenAccount>,
-    
-    #[account(
-        mut,
-        seeds = [b"vault"],
-        bump
-    )]
-    pub vault: Account<'info, TokenAccount>,
-    
-    pub token_program: Program<'info, Token>,
-    pub system_program: Program<'info, System>,
+#[event]
+pub struct StealthAddressClosed {
+    pub owner: Pubkey,
+    pub stealth_address: Pubkey,
+    pub ephemeral_pub_key: [u8; 33],
+    pub payer: Pubkey,
+    pub timestamp: i64,
 }
 
-#[derive(Accounts)]
-pub struct ConfirmDeposit<'info> {
-    #[account(
-        seeds = [b"bridge"],
-        bump = bridge_state.bump
-    )]
-    pub bridge_state: Account<'info, BridgeState>,
-    
-    #[account(mut)]
-    pub deposit_ticket: Account<'info, DepositTicket>,
-    
-    pub operator: Signer<'info>,
+#[event]
+pub struct NullifierSpent {
+    pub nullifier: [u8; 32],
+    pub ticket_id: u64,
+    pub timestamp: i64,
 }
 
-#[derive(Accounts)]
-pub struct InitiateWithdrawal<'info> {
-    #[account(
-        mut,
-        seeds = [b"bridge"],
-        bump = bridge_state.bump
-    )]
-    pub bridge_state: Account<'info, BridgeState>,
-    
-    #[account(
-        init,
-        payer = user,
-        space = 8 + 8 + 32 + 8 + 8 + 32 + 32 + 32 + 1 + 8 + 8 + 1,
-        seeds = [b"withdrawal", bridge_state.withdrawal_nonce.to_le_bytes().as_ref()],
-        bump
-    )]
-    pub withdrawal_ticket: Account<'info, WithdrawalTicket>,
-    
-    #[account(mut)]
-    pub user: Signer<'info>,
-    
-    pub system_program: Program<'info, System>,
+#[event]
+pub struct StealthAddressGenerated {
+    pub owner: Pubkey,
+    pub ephemeral_pub_key: [u8; 33],
+    pub view_hint: u8,
+    pub k: u32,
+    pub timestamp: i64,
 }
 
-#[derive(Accounts)]
-pub struct ProcessWithdrawal<'info> {
-    #[account(
-        mut,
-        seeds = [b"bridge"],
-        bump = bridge_state.bump
-    )]
-    pub bridge_state: Account<'info, BridgeState>,
-    
-    #[account(mut)]
-    pub withdrawal_ticket: Account<'info, WithdrawalTicket>,
-    
-    pub operator: Signer<'info>,
-    
-    #[account(
-        mut,
-        seeds = [b"vault"],
-        bump
-    )]
-    pub vault: Account<'info, TokenAccount>,
-    
-    #[account(mut)]
-    pub recipient_token_account: Account<'info, TokenAccount>,
-    
-    pub token_program: Program<'info, Token>,
+#[event]
+pub struct StealthPaymentSent {
+    pub sender: Pubkey,
+    pub stealth_address: Pubkey,
+    pub ephemeral_pub_key: [u8; 33],
+    pub view_hint: u8,
+    pub amount: u64,
+    pub timestamp: i64,
 }
 
-#[derive(Accounts)]
-#[instruction(ephemeral_pub_key: [u8; 33])]
-pub struct GenerateStealthAddress<'info> {
-    #[account(
-        seeds = [b"meta", owner.key().as_ref()],
-        bump = meta_address.bump
-    )]
-    pub meta_address: Account<'info, MetaAddress>,
-    
-    #[account(
-        init,
-        payer = payer,
-        space = 8 + 32 + 33 + 1 + 4 + 1 + 8 + 1,
-        seeds = [b"stealth", owner.key().as_ref(), ephemeral_pub_key.as_ref()],
-        bump
-    )]
-    pub stealth_address: Account<'info, StealthAddress>,
-    
-    pub owner: SystemAccount<'info>,
-    
-    #[account(mut)]
-    pub payer: Signer<'info>,
-    
-    pub system_program: Program<'info, System>,
+#[event]
+pub struct StealthPaymentClaimed {
+    pub owner: Pubkey,
+    pub stealth_address: Pubkey,
+    pub amount: u64,
+    pub timestamp: i64,
 }
 
-#[derive(Accounts)]
-pub struct SendToStealth<'info> {
-    pub stealth_address: Account<'info, StealthAddress>,
-    
-    #[account(mut)]
-    pub sender: Signer<'info>,
-    
-    #[account(
-        mut,
-        constraint = sender_token_account.owner == sender.key()
-    )]
-    pub sender_token_account: Account<'info, TokenAccount>,
-    
-    #[account(mut)]
-    pub stealth_token_account: Account<'info, TokenAccount>,
-    
-    pub token_program: Program<'info, Token>,
+#[event]
+pub struct RelayerAdded {
+    pub relayer: Pubkey,
+    pub timestamp: i64,
 }
 
-#[derive(Accounts)]
-pub struct ClaimFromStealth<'info> {
-    #[account(
-        mut,
-        seeds = [b"stealth", owner.key().as_ref(), stealth_address.ephemeral_pub_key.as_ref()],
-        bump = stealth_address.bump
-    )]
-    pub stealth_address: Account<'info, StealthAddress>,
-    
-    #[account(mut)]
-    pub owner: Signer<'info>,
-    
-    #[account(
-        mut,
-        constraint = stealth_token_account.owner == stealth_address.key()
-    )]
-    pub stealth_token_account: Account<'info, TokenAccount>,
-    
-    #[account(
-        mut,
-        constraint = owner_token_account.owner == owner.key()
-    )]
-    pub owner_token_account: Account<'info, TokenAccount>,
-    
-    pub token_program: Program<'info, Token>,
+#[event]
+pub struct RelayerRemoved {
+    pub relayer: Pubkey,
+    pub timestamp: i64,
 }
 
-#[derive(Accounts)]
-pub struct UpdateOperator<'info> {
-    #[account(
-        mut,
-        seeds = [b"bridge"],
-        bump = bridge_state.bump
-    )]
-    pub bridge_state: Account<'info, BridgeState>,
-    
-    pub authority: Signer<'info>,
+#[event]
+pub struct OperatorAdded {
+    pub operator: Pubkey,
+    pub operator_count: u8,
+    pub threshold: u8,
+    pub timestamp: i64,
 }
 
-#[derive(Accounts)]
-pub struct PauseBridge<'info> {
-    #[account(
-        mut,
-        seeds = [b"bridge"],
-        bump = bridge_state.bump
-    )]
-    pub bridge_state: Account<'info, BridgeState>,
-    
-    pub authority: Signer<'info>,
+#[event]
+pub struct OperatorBonded {
+    pub operator: Pubkey,
+    pub amount: u64,
+    pub total_bond: u64,
+    pub timestamp: i64,
 }
 
-#[derive(Accounts)]
-pub struct UnpauseBridge<'info> {
-    #[account(
-        mut,
-        seeds = [b"bridge"],
-        bump = bridge_state.bump
-    )]
-    pub bridge_state: Account<'info, BridgeState>,
-    
-    pub authority: Signer<'info>,
+#[event]
+pub struct OperatorUnbonding {
+    pub operator: Pubkey,
+    pub amount: u64,
+    pub available_at: i64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct OperatorUnbonded {
+    pub operator: Pubkey,
+    pub amount: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct OperatorSlashed {
+    pub operator: Pubkey,
+    pub amount: u64,
+    pub recipient: Pubkey,
+    pub remaining_bond: u64,
+    pub timestamp: i64,
 }
 
 #[event]
-pub struct BridgeInitialized {
-    pub authority: Pubkey,
+pub struct BondParamsUpdated {
+    pub min_operator_bond: u64,
+    pub unbonding_delay: i64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct OperatorRemoved {
     pub operator: Pubkey,
-    pub wrapped_zec_mint: Pubkey,
+    pub operator_count: u8,
+    pub threshold: u8,
     pub timestamp: i64,
 }
 
 #[event]
-pub struct MetaAddressRegistered {
-    pub owner: Pubkey,
-    pub spend_pub_key: [u8; 33],
-    pub viewing_pub_key: [u8; 33],
+pub struct OperatorThresholdUpdated {
+    pub old_threshold: u8,
+    pub new_threshold: u8,
     pub timestamp: i64,
 }
 
 #[event]
-pub struct DepositInitiated {
-    pub ticket_id: u64,
-    pub depositor: Pubkey,
-    pub amount: u64,
-    pub zcash_address_hash: [u8; 32],
+pub struct AssetRegistered {
+    pub mint: Pubkey,
+    pub vault: Pubkey,
+    pub min_deposit: u64,
+    pub max_deposit: u64,
+    pub protocol_fee_bps: u16,
+    pub custody_mode: CustodyMode,
+    pub supply_cap: u64,
     pub timestamp: i64,
 }
 
 #[event]
-pub struct DepositConfirmed {
-    pub ticket_id: u64,
-    pub zcash_tx_id: [u8; 32],
+pub struct WrappedSupplyChanged {
+    pub mint: Pubkey,
+    pub supply: u64,
+    pub locked_amount: u64,
     pub timestamp: i64,
 }
 
 #[event]
-pub struct WithdrawalInitiated {
-    pub ticket_id: u64,
-    pub recipient: Pubkey,
-    pub amount: u64,
-    pub commitment: [u8; 32],
+pub struct SupplyCapUpdated {
+    pub mint: Pubkey,
+    pub old_supply_cap: u64,
+    pub new_supply_cap: u64,
     pub timestamp: i64,
 }
 
 #[event]
-pub struct WithdrawalProcessed {
-    pub ticket_id: u64,
-    pub recipient: Pubkey,
+pub struct FeesCollected {
+    pub mint: Pubkey,
     pub amount: u64,
+    pub treasury: Pubkey,
+    pub total_fees_collected: u64,
     pub timestamp: i64,
 }
 
 #[event]
-pub struct StealthAddressGenerated {
-    pub owner: Pubkey,
-    pub ephemeral_pub_key: [u8; 33],
-    pub view_hint: u8,
-    pub k: u32,
+pub struct ConfigUpdated {
+    pub mint: Pubkey,
+    pub old_min_deposit: u64,
+    pub new_min_deposit: u64,
+    pub old_max_deposit: u64,
+    pub new_max_deposit: u64,
+    pub old_protocol_fee_bps: u16,
+    pub new_protocol_fee_bps: u16,
+    pub fee_effective_at: i64,
+    pub old_deposit_timeout: i64,
+    pub new_deposit_timeout: i64,
     pub timestamp: i64,
 }
 
 #[event]
-pub struct StealthPaymentSent {
-    pub sender: Pubkey,
-    pub stealth_address: Pubkey,
-    pub ephemeral_pub_key: [u8; 33],
-    pub view_hint: u8,
-    pub amount: u64,
+pub struct AuthorityProposed {
+    pub authority: Pubkey,
+    pub pending_authority: Pubkey,
     pub timestamp: i64,
 }
 
 #[event]
-pub struct StealthPaymentClaimed {
-    pub owner: Pubkey,
-    pub stealth_address: Pubkey,
-    pub amount: u64,
+pub struct AuthorityTransferred {
+    pub old_authority: Pubkey,
+    pub new_authority: Pubkey,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct GuardianUpdated {
+    pub old_guardian: Pubkey,
+    pub new_guardian: Pubkey,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct OptimisticModeUpdated {
+    pub challenge_period: i64,
+    pub watcher_bond: u64,
     pub timestamp: i64,
 }
 
 #[event]
-pub struct OperatorUpdated {
-    pub old_operator: Pubkey,
-    pub new_operator: Pubkey,
+pub struct OutflowLimitUpdated {
+    pub mint: Pubkey,
+    pub old_window_seconds: i64,
+    pub new_window_seconds: i64,
+    pub old_max_outflow: u64,
+    pub new_max_outflow: u64,
     pub timestamp: i64,
 }
 
 #[event]
 pub struct BridgePausedEvent {
+    pub paused_by: Pubkey,
     pub timestamp: i64,
 }
 
@@ -986,4 +7164,1017 @@ pub enum BridgeError {
     
     #[msg("Arithmetic overflow")]
     Overflow,
+    
+    #[msg("Note commitment and nullifier must be BN254 scalars")]
+    InvalidPublicInput,
+    
+    #[msg("Nullifier has already been used")]
+    NullifierAlreadySpent,
+    
+    #[msg("Invalid bridge configuration")]
+    InvalidConfig,
+    
+    #[msg("Deposit has not expired yet")]
+    DepositNotExpired,
+    
+    #[msg("Operator is already in the operator set")]
+    OperatorAlreadyExists,
+    
+    #[msg("Operator is not in the operator set")]
+    OperatorNotFound,
+    
+    #[msg("Operator set is full")]
+    OperatorSetFull,
+    
+    #[msg("Threshold must be between 1 and the number of operators")]
+    InvalidThreshold,
+    
+    #[msg("Operator has already attested to this ticket")]
+    AlreadyAttested,
+    
+    #[msg("Attestation does not match the pending attestations")]
+    AttestationMismatch,
+    
+    #[msg("Missing or malformed Ed25519 signature instruction")]
+    InvalidSignatureInstruction,
+    
+    #[msg("Not enough operator signatures")]
+    InsufficientSignatures,
+    
+    #[msg("No accrued fees to collect")]
+    NoFeesToCollect,
+    
+    #[msg("Protocol fee exceeds the maximum")]
+    FeeTooHigh,
+    
+    #[msg("No authority transfer is pending")]
+    NoPendingAuthority,
+    
+    #[msg("Outflow limit for the current window exceeded")]
+    OutflowLimitExceeded,
+    
+    #[msg("Zcash address belongs to a different network")]
+    ZcashNetworkMismatch,
+    
+    #[msg("Malformed Zcash block header")]
+    InvalidHeader,
+    
+    #[msg("Zcash block header is already stored")]
+    HeaderAlreadyKnown,
+    
+    #[msg("Parent of the Zcash block header is not in the relay window")]
+    UnknownParentHeader,
+    
+    #[msg("Zcash block hash does not meet its difficulty target")]
+    InsufficientWork,
+    
+    #[msg("Block is not on the best relayed Zcash chain")]
+    UnknownBlock,
+    
+    #[msg("Block does not have enough confirmations")]
+    InsufficientConfirmations,
+    
+    #[msg("Invalid Merkle inclusion proof")]
+    InvalidInclusionProof,
+    
+    #[msg("Header bits do not match the difficulty adjustment")]
+    InvalidDifficulty,
+    
+    #[msg("Header carries no more work than any header it could replace")]
+    LightClientFull,
+    
+    #[msg("Commitment tree is full")]
+    CommitmentTreeFull,
+    
+    #[msg("Optimistic mode is disabled")]
+    OptimisticModeDisabled,
+    
+    #[msg("Challenge period has not ended")]
+    ChallengePeriodActive,
+    
+    #[msg("Challenge period has ended")]
+    ChallengePeriodOver,
+    
+    #[msg("Watcher bond is below the required amount")]
+    InsufficientWatcherBond,
+    
+    #[msg("Watcher still has open challenges")]
+    ChallengesOutstanding,
+    
+    #[msg("Operator bond is too small")]
+    InsufficientOperatorBond,
+    
+    #[msg("Unbonding delay has not passed")]
+    UnbondingNotReady,
+    
+    #[msg("Account is not the operator bond expected here")]
+    InvalidOperatorBond,
+    
+    #[msg("Operator is bonded at or above the minimum")]
+    OperatorSufficientlyBonded,
+    
+    #[msg("Bridge is not the mint authority")]
+    InvalidMintAuthority,
+    
+    #[msg("Mint would exceed the supply cap")]
+    SupplyCapExceeded,
+    
+    #[msg("Mint-and-burn deposits must be sent to the asset's custody address")]
+    NotCustodyAddress,
+    
+    #[msg("Zcash transaction already confirmed another deposit")]
+    ZcashTxAlreadyUsed,
+    
+    #[msg("Mint has a Token-2022 extension the bridge does not support")]
+    UnsupportedMintExtension,
+    
+    #[msg("Deposit shard accounts are missing or out of order")]
+    InvalidDepositShard,
+    
+    #[msg("Ticket has not reached a terminal state")]
+    TicketNotSettled,
+    
+    #[msg("Stealth address has not been claimed")]
+    StealthAddressNotSpent,
+    
+    #[msg("Ticket has already been attested by an operator")]
+    TicketAlreadyAttested,
+    
+    #[msg("Ticket cannot be cancelled yet")]
+    CancelLockActive,
+    
+    #[msg("Ticket is leased to an operator")]
+    LeaseActive,
+    
+    #[msg("Ticket lease has expired")]
+    LeaseExpired,
+    
+    #[msg("The operator holding the lease has not attested to this proof")]
+    LeaseHolderNotAttested,
+    
+    #[msg("Batch is empty, too large or its accounts do not match its entries")]
+    InvalidBatch,
+    
+    #[msg("Batch ticket account does not belong to this batch")]
+    InvalidBatchTicket,
+    
+    #[msg("Delivery evidence does not match the deposit")]
+    DeliveryMismatch,
+    
+    #[msg("Delivered note is not an output of the payout transaction")]
+    NoteNotInTransaction,
+    
+    #[msg("Funding note does not pay the deposit into the custody address")]
+    FundingMismatch,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn operator_set(size: usize, threshold: u8) -> OperatorSet {
+        OperatorSet {
+            operators: (0..size).map(|_| Pubkey::new_unique()).collect(),
+            threshold,
+            epoch: 0,
+            bump: 0,
+        }
+    }
+
+    const MIN_BOND: u64 = 10;
+
+    fn bond(operator: &Pubkey, amount: u64) -> OperatorBond {
+        OperatorBond {
+            operator: *operator,
+            amount,
+            unbonding_amount: 0,
+            unbonding_available_at: 0,
+            bump: 0,
+        }
+    }
+
+    fn limiter(window_seconds: i64, max_outflow: u64) -> OutflowLimiter {
+        OutflowLimiter {
+            window_seconds,
+            max_outflow,
+            ..OutflowLimiter::default()
+        }
+    }
+
+    #[test]
+    fn outflow_limiter_disabled_admits_everything() {
+        let mut limiter = limiter(0, 100);
+        assert!(limiter.admits(u64::MAX));
+        assert!(limiter.try_record(0, u64::MAX));
+        assert_eq!(limiter.window_outflow(0), 0);
+    }
+
+    #[test]
+    fn outflow_limiter_caps_window() {
+        let mut limiter = limiter(1200, 100);
+        assert!(limiter.try_record(1000, 60));
+        assert!(limiter.try_record(1050, 40));
+        assert!(!limiter.try_record(1100, 1));
+        assert_eq!(limiter.window_outflow(1100), 100);
+    }
+
+    #[test]
+    fn outflow_limiter_frees_expired_buckets() {
+        let mut limiter = limiter(1200, 100);
+        assert!(limiter.try_record(0, 100));
+        assert!(!limiter.try_record(1199, 1));
+        assert!(limiter.try_record(1200, 100));
+        assert_eq!(limiter.window_outflow(1200), 100);
+    }
+
+    #[test]
+    fn outflow_limiter_rejects_amounts_above_cap() {
+        let limiter = limiter(1200, 100);
+        assert!(limiter.admits(100));
+        assert!(!limiter.admits(101));
+    }
+
+    #[test]
+    fn attestations_count_per_digest() {
+        let set = operator_set(3, 2);
+        let mut attestations = Attestations::default();
+        
+        // A front-running operator cannot pin the digest for the others
+        assert_eq!(attestations.record(&set, &bond(&set.operators[0], MIN_BOND), MIN_BOND, [1u8; 32]).unwrap(), 1);
+        assert_eq!(attestations.record(&set, &bond(&set.operators[1], MIN_BOND), MIN_BOND, [2u8; 32]).unwrap(), 1);
+        assert_eq!(attestations.record(&set, &bond(&set.operators[2], MIN_BOND), MIN_BOND, [2u8; 32]).unwrap(), 2);
+        assert_eq!(attestations.count(&[1u8; 32]), 1);
+    }
+
+    #[test]
+    fn attestations_reject_repeat_and_outsiders() {
+        let set = operator_set(2, 2);
+        let mut attestations = Attestations::default();
+        
+        attestations.record(&set, &bond(&set.operators[0], MIN_BOND), MIN_BOND, [1u8; 32]).unwrap();
+        assert!(attestations.record(&set, &bond(&set.operators[0], MIN_BOND), MIN_BOND, [2u8; 32]).is_err());
+        assert!(attestations.record(&set, &bond(&Pubkey::new_unique(), MIN_BOND), MIN_BOND, [1u8; 32]).is_err());
+    }
+
+    #[test]
+    fn attestations_reset_on_new_epoch() {
+        let mut set = operator_set(3, 2);
+        let mut attestations = Attestations::default();
+        attestations.record(&set, &bond(&set.operators[0], MIN_BOND), MIN_BOND, [1u8; 32]).unwrap();
+        
+        set.epoch += 1;
+        assert_eq!(attestations.record(&set, &bond(&set.operators[1], MIN_BOND), MIN_BOND, [1u8; 32]).unwrap(), 1);
+        assert_eq!(attestations.record(&set, &bond(&set.operators[0], MIN_BOND), MIN_BOND, [1u8; 32]).unwrap(), 2);
+    }
+
+    #[test]
+    fn attestations_require_a_bond() {
+        let set = operator_set(2, 1);
+        let mut attestations = Attestations::default();
+        
+        let result = attestations.record(&set, &bond(&set.operators[0], MIN_BOND - 1), MIN_BOND, [1u8; 32]);
+        assert_error(result, BridgeError::InsufficientOperatorBond);
+        assert_eq!(attestations.mask, 0);
+        
+        let outsider = bond(&Pubkey::new_unique(), MIN_BOND);
+        assert_error(attestations.record(&set, &outsider, MIN_BOND, [1u8; 32]), BridgeError::Unauthorized);
+    }
+
+    #[test]
+    fn attestations_identify_the_settling_operators() {
+        let set = operator_set(4, 2);
+        let mut attestations = Attestations::default();
+        
+        attestations.record(&set, &bond(&set.operators[0], MIN_BOND), MIN_BOND, [1u8; 32]).unwrap();
+        attestations.record(&set, &bond(&set.operators[1], MIN_BOND), MIN_BOND, [2u8; 32]).unwrap();
+        assert_eq!(attestations.settled_digest(2), None);
+        
+        attestations.record(&set, &bond(&set.operators[3], MIN_BOND), MIN_BOND, [2u8; 32]).unwrap();
+        assert_eq!(attestations.settled_digest(2), Some([2u8; 32]));
+        assert_eq!(attestations.attesters(&[2u8; 32]).collect::<Vec<_>>(), vec![1, 3]);
+    }
+
+    #[test]
+    fn removing_an_operator_invalidates_attestations() {
+        let mut set = operator_set(3, 2);
+        let removed = set.operators[1];
+        let mut attestations = Attestations::default();
+        attestations.record(&set, &bond(&set.operators[2], MIN_BOND), MIN_BOND, [1u8; 32]).unwrap();
+        
+        assert!(set.remove(&removed));
+        assert_eq!(set.operators.len(), 2);
+        assert!(!set.contains(&removed));
+        assert_eq!(set.epoch, 1);
+        assert!(!set.remove(&removed));
+        assert_eq!(set.epoch, 1);
+        
+        // The old bitmap slot 2 no longer exists; the re-indexed operator starts over
+        assert!(!attestations.is_current(&set));
+        assert_eq!(attestations.record(&set, &bond(&set.operators[1], MIN_BOND), MIN_BOND, [1u8; 32]).unwrap(), 1);
+        assert!(attestations.is_current(&set));
+    }
+
+    #[test]
+    fn leased_deposits_are_held_until_the_lease_lapses() {
+        let (_, mut deposit, _, _) = batch_deposit(Pubkey::new_unique());
+        assert!(deposit.require_unclaimed(0).is_ok());
+        
+        deposit.status = DepositStatus::Processing;
+        deposit.claimed_by = Pubkey::new_unique();
+        deposit.lease_expires_at = DEPOSIT_LEASE_PERIOD;
+        assert_error(deposit.require_unclaimed(DEPOSIT_LEASE_PERIOD - 1), BridgeError::LeaseActive);
+        assert!(deposit.require_unclaimed(DEPOSIT_LEASE_PERIOD).is_ok());
+        
+        deposit.status = DepositStatus::Confirmed;
+        assert_error(deposit.require_unclaimed(DEPOSIT_LEASE_PERIOD), BridgeError::InvalidDepositStatus);
+    }
+
+    #[test]
+    fn attestations_identify_the_lease_holder() {
+        let set = operator_set(3, 2);
+        let (holder, other) = (set.operators[0], set.operators[1]);
+        let mut attestations = Attestations::default();
+        attestations.record(&set, &bond(&other, MIN_BOND), MIN_BOND, [1u8; 32]).unwrap();
+        attestations.record(&set, &bond(&holder, MIN_BOND), MIN_BOND, [2u8; 32]).unwrap();
+        
+        assert!(attestations.has_attested(&set, &holder, &[2u8; 32]));
+        assert!(!attestations.has_attested(&set, &holder, &[1u8; 32]));
+        assert!(!attestations.has_attested(&set, &Pubkey::new_unique(), &[2u8; 32]));
+    }
+
+    #[test]
+    fn watcher_bond_covers_every_open_challenge() {
+        let mut record = WatcherRecord {
+            watcher: Pubkey::new_unique(),
+            bond: 100,
+            open_challenges: 0,
+            registered_at: 0,
+            bump: 0,
+        };
+        assert!(record.can_challenge(100));
+        assert!(!record.can_challenge(0));
+        
+        record.open_challenges = 1;
+        assert!(!record.can_challenge(100));
+        assert_eq!(record.required_bond(100, 1), Some(200));
+        record.bond = 200;
+        assert!(record.can_challenge(100));
+        assert!(!record.can_challenge(u64::MAX));
+    }
+
+    fn commitment_tree() -> CommitmentTree {
+        let mut tree = CommitmentTree {
+            next_index: 0,
+            filled_subtrees: [[0; 32]; COMMITMENT_TREE_DEPTH],
+            zeros: [[0; 32]; COMMITMENT_TREE_DEPTH],
+            roots: [[0; 32]; ROOT_HISTORY_SIZE],
+            current_root_index: 0,
+            bump: 0,
+        };
+        tree.init();
+        tree
+    }
+
+    /// Root of a full tree over `leaves`, padded with empty leaves
+    fn naive_root(tree: &CommitmentTree, leaves: &[[u8; 32]]) -> [u8; 32] {
+        let mut level: Vec<[u8; 32]> = leaves.to_vec();
+        for depth in 0..COMMITMENT_TREE_DEPTH {
+            if level.len() % 2 == 1 {
+                level.push(tree.zeros[depth]);
+            }
+            level = level
+                .chunks(2)
+                .map(|pair| CommitmentTree::hash_pair(&pair[0], &pair[1]))
+                .collect();
+        }
+        level[0]
+    }
+
+    #[test]
+    fn commitment_tree_matches_full_recomputation() {
+        let mut tree = commitment_tree();
+        assert_eq!(tree.roots[0], naive_root(&tree, &[tree.zeros[0]]));
+        
+        let leaves: Vec<[u8; 32]> = (1..=5u8).map(|i| [i; 32]).collect();
+        for (i, leaf) in leaves.iter().enumerate() {
+            let (index, root) = tree.append(*leaf).unwrap();
+            assert_eq!(index, i as u32);
+            assert_eq!(root, naive_root(&tree, &leaves[..=i]));
+            assert_eq!(tree.roots[tree.current_root_index as usize], root);
+        }
+    }
+
+    #[test]
+    fn commitment_tree_keeps_recent_roots() {
+        let mut tree = commitment_tree();
+        let mut roots = Vec::new();
+        for i in 0..ROOT_HISTORY_SIZE as u8 + 3 {
+            roots.push(tree.append([i; 32]).unwrap().1);
+        }
+        for root in &roots[roots.len() - ROOT_HISTORY_SIZE..] {
+            assert!(tree.roots.contains(root));
+        }
+        assert!(!tree.roots.contains(&roots[0]));
+    }
+
+    #[test]
+    fn commitment_tree_rejects_leaves_when_full() {
+        let mut tree = commitment_tree();
+        tree.next_index = 1 << COMMITMENT_TREE_DEPTH;
+        assert_error(tree.append([1; 32]), BridgeError::CommitmentTreeFull);
+    }
+
+    const TESTNET_MIN_BITS: u32 = 0x2007ffff;
+    const CHECKPOINT_TIME: u32 = 1_700_000_000;
+
+    /// Light client seeded with a testnet checkpoint whose history ran at
+    /// minimum difficulty and exact 75 second spacing
+    fn light_client() -> ZcashLightClient {
+        let checkpoint = StoredHeader {
+            hash: [0xc0; 32],
+            prev_hash: [0; 32],
+            merkle_root: [0; 32],
+            height: 1000,
+            time: CHECKPOINT_TIME,
+            bits: TESTNET_MIN_BITS,
+            cumulative_work: 1 << 64,
+        };
+        ZcashLightClient {
+            checkpoint_hash: checkpoint.hash,
+            checkpoint_history: (1..=CHECKPOINT_HISTORY_LEN as u32)
+                .map(|i| BlockTiming {
+                    time: CHECKPOINT_TIME - 75 * i,
+                    bits: TESTNET_MIN_BITS,
+                })
+                .collect(),
+            headers: vec![checkpoint],
+            tip_index: 0,
+            min_confirmations: 3,
+            bump: 0,
+        }
+    }
+
+    /// Builds a raw testnet header on `parent` and grinds the nonce until
+    /// it meets the target `bits` encodes
+    fn mine(parent: &StoredHeader, merkle_root: [u8; 32], bits: u32) -> zcash_header::ParsedHeader {
+        let mut raw = vec![0u8; zcash_header::HEADER_LEN];
+        raw[..4].copy_from_slice(&4u32.to_le_bytes());
+        raw[4..36].copy_from_slice(&parent.hash);
+        raw[36..68].copy_from_slice(&merkle_root);
+        raw[100..104].copy_from_slice(&(parent.time + 75).to_le_bytes());
+        raw[104..108].copy_from_slice(&bits.to_le_bytes());
+        raw[zcash_header::HEADER_PREFIX_LEN..zcash_header::HEADER_PREFIX_LEN + 3]
+            .copy_from_slice(&[0xfd, 0x40, 0x05]);
+        
+        for nonce in 0u64.. {
+            raw[108..116].copy_from_slice(&nonce.to_le_bytes());
+            let header = zcash_header::parse(&raw).unwrap();
+            if zcash_header::meets_target(&header.hash, bits, false) {
+                return header;
+            }
+        }
+        unreachable!()
+    }
+
+    fn next_bits(client: &ZcashLightClient, parent: &StoredHeader) -> u32 {
+        let window = client.difficulty_window(client.find(&parent.hash).unwrap()).unwrap();
+        zcash_header::required_bits(&window, parent.time + 75, false).unwrap()
+    }
+
+    fn extend(client: &mut ZcashLightClient, parent: &StoredHeader, merkle_root: [u8; 32]) -> StoredHeader {
+        let header = mine(parent, merkle_root, next_bits(client, parent));
+        client.accept(&header, i64::MAX / 2, false).unwrap().0
+    }
+
+    fn assert_error(result: Result<impl Sized>, expected: BridgeError) {
+        match result {
+            Err(Error::AnchorError(error)) => assert_eq!(error.error_code_number, u32::from(expected)),
+            _ => panic!("expected {:?}", expected),
+        }
+    }
+
+    #[test]
+    fn light_client_follows_the_heaviest_chain() {
+        let mut client = light_client();
+        let checkpoint = client.tip().clone();
+        let first = extend(&mut client, &checkpoint, [1; 32]);
+        let second = extend(&mut client, &first, [2; 32]);
+        assert_eq!(client.tip().hash, second.hash);
+        assert_eq!(client.tip().height, 1002);
+        
+        // An equal-work fork is stored but does not take over
+        let fork = mine(&first, [3; 32], next_bits(&client, &first));
+        let (_, is_new_tip) = client.accept(&fork, i64::MAX / 2, false).unwrap();
+        assert!(!is_new_tip);
+        assert_eq!(client.tip().hash, second.hash);
+    }
+
+    #[test]
+    fn light_client_rejects_bad_headers() {
+        let mut client = light_client();
+        let checkpoint = client.tip().clone();
+        
+        let bits = next_bits(&client, &checkpoint);
+        
+        // Valid work, but not at the target the chain dictates
+        let off_target = mine(&checkpoint, [1; 32], 0x1f07ffff);
+        assert_error(client.accept(&off_target, i64::MAX / 2, false), BridgeError::InvalidDifficulty);
+        
+        let orphan = mine(&StoredHeader { hash: [7; 32], ..checkpoint.clone() }, [1; 32], bits);
+        assert_error(client.accept(&orphan, i64::MAX / 2, false), BridgeError::UnknownParentHeader);
+        
+        let header = mine(&checkpoint, [1; 32], bits);
+        assert_error(client.accept(&header, CHECKPOINT_TIME as i64 - 3 * 60 * 60, false), BridgeError::InvalidHeader);
+        client.accept(&header, i64::MAX / 2, false).unwrap();
+        assert_error(client.accept(&header, i64::MAX / 2, false), BridgeError::HeaderAlreadyKnown);
+    }
+
+    #[test]
+    fn light_client_survives_fork_flooding() {
+        let mut client = light_client();
+        let mut tip = client.tip().clone();
+        for i in 1..HEADER_WINDOW as u8 {
+            tip = extend(&mut client, &tip, [i; 32]);
+        }
+        assert_eq!(client.headers.len(), HEADER_WINDOW);
+        
+        // Siblings of the tip take every evictable slot, then are refused
+        // since they cannot outweigh each other
+        let parent = client.headers[client.find(&tip.prev_hash).unwrap()].clone();
+        let bits = next_bits(&client, &parent);
+        let mut stored = 0;
+        for i in 0..HEADER_WINDOW as u8 {
+            match client.accept(&mine(&parent, [0x80 | i; 32], bits), i64::MAX / 2, false) {
+                Ok(_) => stored += 1,
+                Err(error) => assert_error(Err::<(), _>(error), BridgeError::LightClientFull),
+            }
+        }
+        assert_eq!(stored, HEADER_WINDOW - zcash_header::DIFFICULTY_WINDOW - MAX_REORG_DEPTH);
+        assert_eq!(client.tip().hash, tip.hash);
+        
+        // The best chain can still be extended and checked
+        let next = extend(&mut client, &tip, [0xff; 32]);
+        assert_eq!(client.tip().hash, next.hash);
+        assert_eq!(
+            client.best_chain(HEADER_WINDOW).len(),
+            zcash_header::DIFFICULTY_WINDOW + MAX_REORG_DEPTH + 1
+        );
+    }
+
+    #[test]
+    fn light_client_keeps_heavier_headers_when_full() {
+        let mut client = light_client();
+        let mut tip = client.tip().clone();
+        for i in 1..HEADER_WINDOW as u8 {
+            tip = extend(&mut client, &tip, [i; 32]);
+        }
+        
+        let lightest = client.headers.iter().map(|header| header.cumulative_work).min().unwrap();
+        let cheap = StoredHeader {
+            hash: [0xee; 32],
+            cumulative_work: lightest,
+            ..tip
+        };
+        assert_error(client.insert(cheap), BridgeError::LightClientFull);
+    }
+
+    #[test]
+    fn light_client_verifies_inclusion_on_the_best_chain() {
+        let mut client = light_client();
+        let checkpoint = client.tip().clone();
+        let tx_id = [0x42; 32];
+        let block = extend(&mut client, &checkpoint, tx_id);
+        let fork = extend(&mut client, &checkpoint, [0x43; 32]);
+        let inclusion = |block_hash| ZcashTxInclusion {
+            block_hash,
+            tx_index: 0,
+            merkle_branch: vec![],
+        };
+        
+        let mut tip = block.clone();
+        assert_error(client.verify_inclusion(&tx_id, &inclusion(block.hash)), BridgeError::InsufficientConfirmations);
+        for i in 0..2 {
+            tip = extend(&mut client, &tip, [i; 32]);
+        }
+        client.verify_inclusion(&tx_id, &inclusion(block.hash)).unwrap();
+        assert_error(client.verify_inclusion(&[0x43; 32], &inclusion(fork.hash)), BridgeError::UnknownBlock);
+        assert_error(client.verify_inclusion(&[0x44; 32], &inclusion(block.hash)), BridgeError::InvalidInclusionProof);
+    }
+
+    fn token_2022_mint(extensions: &[spl_token_2022::extension::ExtensionType], frozen_by_default: bool) -> Vec<u8> {
+        use spl_token_2022::extension::default_account_state::DefaultAccountState;
+        use spl_token_2022::extension::metadata_pointer::MetadataPointer;
+        use spl_token_2022::extension::non_transferable::NonTransferable;
+        use spl_token_2022::extension::permanent_delegate::PermanentDelegate;
+        use spl_token_2022::extension::transfer_fee::TransferFeeConfig;
+        use spl_token_2022::extension::transfer_hook::TransferHook;
+        use spl_token_2022::extension::{ExtensionType, StateWithExtensionsMut};
+        use spl_token_2022::state::{AccountState, Mint as MintState};
+        
+        let len = ExtensionType::try_calculate_account_len::<MintState>(extensions).unwrap();
+        let mut data = vec![0u8; len];
+        let mut state = StateWithExtensionsMut::<MintState>::unpack_uninitialized(&mut data).unwrap();
+        for extension in extensions {
+            match extension {
+                ExtensionType::TransferFeeConfig => {
+                    state.init_extension::<TransferFeeConfig>(true).unwrap();
+                }
+                ExtensionType::MetadataPointer => {
+                    state.init_extension::<MetadataPointer>(true).unwrap();
+                }
+                ExtensionType::PermanentDelegate => {
+                    state.init_extension::<PermanentDelegate>(true).unwrap();
+                }
+                ExtensionType::TransferHook => {
+                    state.init_extension::<TransferHook>(true).unwrap();
+                }
+                ExtensionType::NonTransferable => {
+                    state.init_extension::<NonTransferable>(true).unwrap();
+                }
+                ExtensionType::DefaultAccountState => {
+                    let default = state.init_extension::<DefaultAccountState>(true).unwrap();
+                    default.state = if frozen_by_default { AccountState::Frozen } else { AccountState::Initialized } as u8;
+                }
+                other => panic!("no test setup for {:?}", other),
+            }
+        }
+        state.base.is_initialized = true;
+        state.base.decimals = 8;
+        state.pack_base();
+        state.init_account_type().unwrap();
+        data
+    }
+
+    #[test]
+    fn mint_extensions_are_allow_listed() {
+        use spl_token_2022::extension::ExtensionType;
+        
+        check_mint_extensions(&token_2022_mint(&[], false)).unwrap();
+        check_mint_extensions(&token_2022_mint(
+            &[ExtensionType::TransferFeeConfig, ExtensionType::MetadataPointer, ExtensionType::DefaultAccountState],
+            false,
+        ))
+        .unwrap();
+        
+        for extension in [
+            ExtensionType::PermanentDelegate,
+            ExtensionType::TransferHook,
+            ExtensionType::NonTransferable,
+        ] {
+            let mint = token_2022_mint(&[ExtensionType::TransferFeeConfig, extension], false);
+            assert_error(check_mint_extensions(&mint), BridgeError::UnsupportedMintExtension);
+        }
+        
+        let frozen = token_2022_mint(&[ExtensionType::DefaultAccountState], true);
+        assert_error(check_mint_extensions(&frozen), BridgeError::UnsupportedMintExtension);
+    }
+
+    /// Account info over leaked storage, so loaders can borrow it for 'static
+    fn account_info(key: Pubkey, owner: Pubkey, data: Vec<u8>, is_writable: bool) -> AccountInfo<'static> {
+        AccountInfo::new(
+            Box::leak(Box::new(key)),
+            false,
+            is_writable,
+            Box::leak(Box::new(1_000_000)),
+            Box::leak(data.into_boxed_slice()),
+            Box::leak(Box::new(owner)),
+            false,
+            0,
+        )
+    }
+
+    fn program_account<T: AccountSerialize>(key: Pubkey, account: &T, is_writable: bool) -> AccountInfo<'static> {
+        let mut data = Vec::new();
+        account.try_serialize(&mut data).unwrap();
+        account_info(key, crate::ID, data, is_writable)
+    }
+
+    fn token_account(mint: Pubkey, owner: Pubkey) -> AccountInfo<'static> {
+        use anchor_lang::solana_program::program_pack::Pack;
+        use anchor_spl::token::spl_token;
+        
+        let account = spl_token::state::Account {
+            mint,
+            owner,
+            state: spl_token::state::AccountState::Initialized,
+            ..Default::default()
+        };
+        let mut data = vec![0u8; spl_token::state::Account::LEN];
+        account.pack_into_slice(&mut data);
+        account_info(Pubkey::new_unique(), spl_token::ID, data, true)
+    }
+
+    fn batch_deposit(mint: Pubkey) -> (Pubkey, DepositTicket, Pubkey, DepositShard) {
+        let depositor = Pubkey::new_unique();
+        let (ticket, bump) = Pubkey::find_program_address(
+            &[b"deposit", depositor.as_ref(), &0u64.to_le_bytes()],
+            &crate::ID,
+        );
+        let deposit = DepositTicket {
+            ticket_id: 0,
+            depositor,
+            mint,
+            amount: 100,
+            fee: 1,
+            zcash_shielded_address: String::new(),
+            zcash_pool: ZcashPool::Sapling,
+            zcash_receiver: [0u8; 43],
+            memo: [0u8; 64],
+            status: DepositStatus::Pending,
+            created_at: 0,
+            processed_at: 0,
+            zcash_tx_id: [0u8; 32],
+            bump,
+            expires_at: 0,
+            attestations: Attestations::default(),
+            finalizes_at: 0,
+            challenger: Pubkey::default(),
+            delivery_note_commitment: [0u8; 32],
+            claimed_by: Pubkey::default(),
+            lease_expires_at: 0,
+        };
+        let index = deposit_shard_index(&depositor);
+        let (shard_key, shard_bump) = Pubkey::find_program_address(&[b"deposit_shard", mint.as_ref(), &[index]], &crate::ID);
+        let shard = DepositShard {
+            mint,
+            index,
+            deposit_count: 0,
+            total_deposited: 0,
+            bump: shard_bump,
+            pending_escrow: 101,
+        };
+        (ticket, deposit, shard_key, shard)
+    }
+
+    fn batch_withdrawal(mint: Pubkey) -> (Pubkey, WithdrawalTicket, Pubkey, NullifierRecord) {
+        let nullifier = [7u8; 32];
+        let (ticket, bump) = Pubkey::find_program_address(&[b"withdrawal", &0u64.to_le_bytes()], &crate::ID);
+        let withdrawal = WithdrawalTicket {
+            ticket_id: 0,
+            recipient: Pubkey::new_unique(),
+            mint,
+            amount: 100,
+            fee: 1,
+            partial_note_commitment: [0u8; 32],
+            partial_note_nullifier: nullifier,
+            encrypted_value: [0u8; 32],
+            status: WithdrawalStatus::Processing,
+            created_at: 0,
+            processed_at: 0,
+            bump,
+            attestations: Attestations::default(),
+            finalizes_at: 0,
+            challenger: Pubkey::default(),
+            claimed_by: Pubkey::default(),
+            lease_expires_at: 0,
+        };
+        let (record_key, record_bump) = Pubkey::find_program_address(&[b"nullifier", nullifier.as_ref()], &crate::ID);
+        let record = NullifierRecord {
+            nullifier,
+            withdrawal_ticket: ticket,
+            is_spent: false,
+            created_at: 0,
+            bump: record_bump,
+        };
+        (ticket, withdrawal, record_key, record)
+    }
+
+    fn deposit_accounts(
+        ticket: Pubkey,
+        deposit: &DepositTicket,
+        token_owner: Pubkey,
+        shard_key: Pubkey,
+        shard: &DepositShard,
+    ) -> &'static [AccountInfo<'static>] {
+        Box::leak(
+            vec![
+                program_account(ticket, deposit, true),
+                token_account(deposit.mint, token_owner),
+                program_account(shard_key, shard, true),
+            ]
+            .into_boxed_slice(),
+        )
+    }
+
+    #[test]
+    fn batch_deposits_are_checked_against_their_pdas() {
+        let mint = Pubkey::new_unique();
+        let (ticket, deposit, shard_key, shard) = batch_deposit(mint);
+        let depositor = deposit.depositor;
+        
+        let accounts = deposit_accounts(ticket, &deposit, depositor, shard_key, &shard);
+        let (loaded, _, loaded_shard) = load_batch_deposit(accounts, &mint, &crate::ID).unwrap();
+        assert_eq!(loaded.key(), ticket);
+        assert_eq!(loaded_shard.pending_escrow, 101);
+        
+        let accounts = deposit_accounts(Pubkey::new_unique(), &deposit, depositor, shard_key, &shard);
+        assert_error(load_batch_deposit(accounts, &mint, &crate::ID), BridgeError::InvalidBatchTicket);
+        
+        let accounts = deposit_accounts(ticket, &deposit, depositor, shard_key, &shard);
+        assert_error(load_batch_deposit(accounts, &Pubkey::new_unique(), &crate::ID), BridgeError::InvalidBatchTicket);
+        
+        let accounts = deposit_accounts(ticket, &deposit, Pubkey::new_unique(), shard_key, &shard);
+        assert_error(load_batch_deposit(accounts, &mint, &crate::ID), BridgeError::InvalidBatchTicket);
+        
+        let accounts = deposit_accounts(ticket, &deposit, depositor, Pubkey::new_unique(), &shard);
+        assert_error(load_batch_deposit(accounts, &mint, &crate::ID), BridgeError::InvalidBatchTicket);
+        
+        let accounts = deposit_accounts(ticket, &deposit, depositor, shard_key, &shard);
+        let read_only: &'static [AccountInfo<'static>] = Box::leak(
+            vec![
+                program_account(ticket, &deposit, false),
+                accounts[1].clone(),
+                accounts[2].clone(),
+            ]
+            .into_boxed_slice(),
+        );
+        assert_error(load_batch_deposit(read_only, &mint, &crate::ID), BridgeError::InvalidBatchTicket);
+    }
+
+    #[test]
+    fn duplicate_batch_tickets_see_earlier_attestations() {
+        let mint = Pubkey::new_unique();
+        let (ticket, deposit, shard_key, shard) = batch_deposit(mint);
+        let ticket_info = program_account(ticket, &deposit, true);
+        let shard_info = program_account(shard_key, &shard, true);
+        let accounts: &'static [AccountInfo<'static>] = Box::leak(
+            vec![
+                ticket_info.clone(),
+                token_account(mint, deposit.depositor),
+                shard_info.clone(),
+                ticket_info,
+                token_account(mint, deposit.depositor),
+                shard_info,
+            ]
+            .into_boxed_slice(),
+        );
+        let set = operator_set(3, 2);
+        let operator_bond = bond(&set.operators[0], MIN_BOND);
+        
+        // Each chunk is loaded after the previous one was written back
+        let (mut first, _, _) = load_batch_deposit(&accounts[..3], &mint, &crate::ID).unwrap();
+        first.attestations.record(&set, &operator_bond, MIN_BOND, [1u8; 32]).unwrap();
+        first.exit(&crate::ID).unwrap();
+        
+        let (mut second, _, _) = load_batch_deposit(&accounts[3..], &mint, &crate::ID).unwrap();
+        let duplicate = second.attestations.record(&set, &operator_bond, MIN_BOND, [1u8; 32]);
+        assert_error(BatchMode::AllOrNothing.admit(duplicate), BridgeError::AlreadyAttested);
+        
+        let duplicate = second.attestations.record(&set, &operator_bond, MIN_BOND, [1u8; 32]);
+        assert!(BatchMode::BestEffort.admit(duplicate).unwrap().is_none());
+        assert_eq!(BatchMode::BestEffort.admit(Ok(1u8)).unwrap(), Some(1));
+        assert_eq!(BatchMode::AllOrNothing.admit(Ok(1u8)).unwrap(), Some(1));
+    }
+
+    #[test]
+    fn batch_withdrawals_are_checked_against_their_pdas() {
+        let mint = Pubkey::new_unique();
+        let (ticket, withdrawal, record_key, record) = batch_withdrawal(mint);
+        let accounts = |ticket, record_key, record: &NullifierRecord| -> &'static [AccountInfo<'static>] {
+            Box::leak(
+                vec![
+                    program_account(ticket, &withdrawal, true),
+                    program_account(record_key, record, true),
+                ]
+                .into_boxed_slice(),
+            )
+        };
+        
+        let (loaded, loaded_record) = load_batch_withdrawal(accounts(ticket, record_key, &record), &mint, &crate::ID).unwrap();
+        assert_eq!(loaded.key(), ticket);
+        assert_eq!(loaded_record.withdrawal_ticket, ticket);
+        
+        let wrong_ticket = accounts(Pubkey::new_unique(), record_key, &record);
+        assert_error(load_batch_withdrawal(wrong_ticket, &mint, &crate::ID), BridgeError::InvalidBatchTicket);
+        
+        let wrong_mint = accounts(ticket, record_key, &record);
+        assert_error(load_batch_withdrawal(wrong_mint, &Pubkey::new_unique(), &crate::ID), BridgeError::InvalidBatchTicket);
+        
+        let wrong_record = accounts(ticket, Pubkey::new_unique(), &record);
+        assert_error(load_batch_withdrawal(wrong_record, &mint, &crate::ID), BridgeError::InvalidBatchTicket);
+        
+        let spent = NullifierRecord {
+            is_spent: true,
+            ..record.clone()
+        };
+        assert_error(load_batch_withdrawal(accounts(ticket, record_key, &spent), &mint, &crate::ID), BridgeError::NullifierAlreadySpent);
+        
+        let reassigned = NullifierRecord {
+            withdrawal_ticket: Pubkey::new_unique(),
+            ..record
+        };
+        assert_error(load_batch_withdrawal(accounts(ticket, record_key, &reassigned), &mint, &crate::ID), BridgeError::NullifierAlreadySpent);
+    }
+
+    #[test]
+    fn mint_burn_deposits_must_be_funded_at_the_custody_address() {
+        let (_, mut deposit, _, _) = batch_deposit(Pubkey::new_unique());
+        let mut asset = AssetConfig {
+            mint: deposit.mint,
+            vault: Pubkey::new_unique(),
+            min_deposit: 0,
+            max_deposit: u64::MAX,
+            protocol_fee_bps: 0,
+            pending_fee_bps: 0,
+            pending_fee_effective_at: 0,
+            accrued_fees: 0,
+            total_fees_collected: 0,
+            total_deposited: 0,
+            total_withdrawn: 0,
+            outflow_limiter: OutflowLimiter::default(),
+            bump: 0,
+            custody_mode: CustodyMode::MintBurn,
+            supply_cap: u64::MAX,
+            locked_amount: 0,
+            pending_withdrawals: 0,
+            custody_receiver: [9u8; 43],
+        };
+        deposit.zcash_receiver = asset.custody_receiver;
+        
+        let tx = PayoutTx {
+            consensus_branch_id: 0xc2d6_d0b4,
+            header_digest: [1; 32],
+            transparent_digest: [2; 32],
+            other_pool_digest: [3; 32],
+            bundle: PayoutBundle::Sapling {
+                spends_digest: [4; 32],
+                outputs: vec![SaplingCompactOutput {
+                    cmu: [0xaa; 32],
+                    ephemeral_key: [5; 32],
+                    enc_ciphertext: [6; 52],
+                }],
+                memos_digest: [7; 32],
+                noncompact_digest: [8; 32],
+                value_balance: 0,
+            },
+        };
+        let zcash_tx_id = zcash_tx::tx_id(&tx);
+        let funding = DeliveryProof {
+            zcash_tx_id,
+            note_commitment: [0xaa; 32],
+            receiver: asset.custody_receiver,
+            value: deposit.amount + deposit.fee,
+            transcript_hash: [0u8; 32],
+            tx,
+        };
+        assert!(verify_deposit_funding(&asset, &deposit, &zcash_tx_id, &Some(funding.clone())).is_ok());
+        assert_error(verify_deposit_funding(&asset, &deposit, &zcash_tx_id, &None), BridgeError::FundingMismatch);
+        
+        let short = DeliveryProof { value: deposit.amount, ..funding.clone() };
+        assert_error(verify_deposit_funding(&asset, &deposit, &zcash_tx_id, &Some(short)), BridgeError::FundingMismatch);
+        let elsewhere = DeliveryProof { receiver: [8u8; 43], ..funding.clone() };
+        assert_error(verify_deposit_funding(&asset, &deposit, &zcash_tx_id, &Some(elsewhere)), BridgeError::FundingMismatch);
+        let other_note = DeliveryProof { note_commitment: [0xbb; 32], ..funding.clone() };
+        assert_error(
+            verify_deposit_funding(&asset, &deposit, &zcash_tx_id, &Some(other_note)),
+            BridgeError::NoteNotInTransaction
+        );
+        assert_error(
+            verify_deposit_funding(&asset, &deposit, &[0u8; 32], &Some(funding.clone())),
+            BridgeError::FundingMismatch
+        );
+        
+        asset.custody_mode = CustodyMode::Vault;
+        assert!(verify_deposit_funding(&asset, &deposit, &zcash_tx_id, &None).is_ok());
+        assert_error(verify_deposit_funding(&asset, &deposit, &zcash_tx_id, &Some(funding)), BridgeError::FundingMismatch);
+    }
+
+    #[test]
+    fn deposit_shards_are_summed_in_index_order() {
+        let mint = Pubkey::new_unique();
+        let shard_key = |index: u8| Pubkey::find_program_address(&[b"deposit_shard", mint.as_ref(), &[index]], &crate::ID).0;
+        let shard = |index: u8| DepositShard {
+            mint,
+            index,
+            deposit_count: 1,
+            total_deposited: 100,
+            bump: 0,
+            pending_escrow: 10,
+        };
+        // Odd shards have never been deposited into
+        let shards: Vec<AccountInfo<'static>> = (0..DEPOSIT_SHARDS)
+            .map(|index| match index % 2 {
+                0 => program_account(shard_key(index), &shard(index), false),
+                _ => account_info(shard_key(index), Pubkey::default(), vec![], false),
+            })
+            .collect();
+        
+        let totals = sum_deposit_shards(&shards, &mint, &crate::ID).unwrap();
+        let initialized = DEPOSIT_SHARDS as u64 / 2;
+        assert_eq!(totals.deposit_count, initialized);
+        assert_eq!(totals.total_deposited, 100 * initialized);
+        assert_eq!(totals.pending_escrow, 10 * initialized);
+        
+        assert_error(sum_deposit_shards(&shards[1..], &mint, &crate::ID), BridgeError::InvalidDepositShard);
+        
+        let mut swapped = shards.clone();
+        swapped.swap(0, 2);
+        assert_error(sum_deposit_shards(&swapped, &mint, &crate::ID), BridgeError::InvalidDepositShard);
+        
+        let mut foreign = shards.clone();
+        let mut data = Vec::new();
+        shard(0).try_serialize(&mut data).unwrap();
+        foreign[0] = account_info(shard_key(0), Pubkey::new_unique(), data, false);
+        assert_error(sum_deposit_shards(&foreign, &mint, &crate::ID), BridgeError::InvalidDepositShard);
+    }
 }
@@ -0,0 +1,414 @@
+//! Zcash shielded address decoding
+//!
+//! Supports Sapling addresses (bech32, ZIP 173) and Unified Addresses
+//! (bech32m + F4Jumble, ZIP 316) for mainnet and testnet. Only addresses
+//! that carry a shielded receiver are accepted.
+
+use blake2b_simd::Params;
+
+use crate::{ZcashNetwork, ZcashPool};
+
+pub const SHIELDED_RECEIVER_LEN: usize = 43;
+
+const SAPLING_HRP_MAINNET: &str = "zs";
+const SAPLING_HRP_TESTNET: &str = "ztestsapling";
+const UNIFIED_HRP_MAINNET: &str = "u";
+const UNIFIED_HRP_TESTNET: &str = "utest";
+
+const TYPECODE_P2PKH: u64 = 0x00;
+const TYPECODE_P2SH: u64 = 0x01;
+const TYPECODE_SAPLING: u64 = 0x02;
+const TYPECODE_ORCHARD: u64 = 0x03;
+
+const CHARSET: &[u8; 32] = b"qpzry9x8gf2tvdw0s3jn54khce6mua7l";
+const BECH32_CONST: u32 = 1;
+const BECH32M_CONST: u32 = 0x2bc8_30a3;
+const CHECKSUM_LEN: usize = 6;
+
+// ZIP 316 padding appended to the raw encoding before jumbling
+const UA_PADDING_LEN: usize = 16;
+const F4JUMBLE_MIN_LEN: usize = 48;
+
+pub struct ShieldedAddress {
+    pub network: ZcashNetwork,
+    pub pool: ZcashPool,
+    pub receiver: [u8; SHIELDED_RECEIVER_LEN],
+}
+
+/// Decodes a Sapling or Unified Address and returns its preferred shielded
+/// receiver (Orchard over Sapling for Unified Addresses)
+pub fn decode(address: &str) -> Option<ShieldedAddress> {
+    let (hrp, data, is_bech32m) = bech32_decode(address)?;
+
+    match (hrp.as_str(), is_bech32m) {
+        (SAPLING_HRP_MAINNET, false) => decode_sapling(ZcashNetwork::Mainnet, &data),
+        (SAPLING_HRP_TESTNET, false) => decode_sapling(ZcashNetwork::Testnet, &data),
+        (UNIFIED_HRP_MAINNET, true) => decode_unified(ZcashNetwork::Mainnet, &hrp, &data),
+        (UNIFIED_HRP_TESTNET, true) => decode_unified(ZcashNetwork::Testnet, &hrp, &data),
+        _ => None,
+    }
+}
+
+fn decode_sapling(network: ZcashNetwork, data: &[u8]) -> Option<ShieldedAddress> {
+    Some(ShieldedAddress {
+        network,
+        pool: ZcashPool::Sapling,
+        receiver: data.try_into().ok()?,
+    })
+}
+
+fn decode_unified(network: ZcashNetwork, hrp: &str, data: &[u8]) -> Option<ShieldedAddress> {
+    let raw = f4jumble_inv(data)?;
+
+    let (items, padding) = raw.split_at(raw.len() - UA_PADDING_LEN);
+    let mut expected_padding = [0u8; UA_PADDING_LEN];
+    expected_padding[..hrp.len()].copy_from_slice(hrp.as_bytes());
+    if padding != expected_padding {
+        return None;
+    }
+
+    let mut sapling = None;
+    let mut orchard = None;
+    let mut has_transparent = false;
+    let mut last_typecode = None;
+    let mut rest = items;
+
+    while !rest.is_empty() {
+        let (typecode, after_typecode) = read_compact_size(rest)?;
+        let (length, after_length) = read_compact_size(after_typecode)?;
+        let length = usize::try_from(length).ok()?;
+        if after_length.len() < length {
+            return None;
+        }
+        let (value, after_value) = after_length.split_at(length);
+        rest = after_value;
+
+        // Items must appear in strictly ascending typecode order
+        if last_typecode.is_some_and(|last| typecode <= last) {
+            return None;
+        }
+        last_typecode = Some(typecode);
+
+        match typecode {
+            TYPECODE_P2PKH | TYPECODE_P2SH => {
+                if has_transparent || length != 20 {
+                    return None;
+                }
+                has_transparent = true;
+            }
+            TYPECODE_SAPLING => sapling = Some(<[u8; SHIELDED_RECEIVER_LEN]>::try_from(value).ok()?),
+            TYPECODE_ORCHARD => orchard = Some(<[u8; SHIELDED_RECEIVER_LEN]>::try_from(value).ok()?),
+            // Unknown receiver types are allowed but never selected
+            _ => {}
+        }
+    }
+
+    let (pool, receiver) = match (orchard, sapling) {
+        (Some(receiver), _) => (ZcashPool::Orchard, receiver),
+        (None, Some(receiver)) => (ZcashPool::Sapling, receiver),
+        // Transparent-only addresses are rejected
+        (None, None) => return None,
+    };
+
+    Some(ShieldedAddress { network, pool, receiver })
+}
+
+fn read_compact_size(data: &[u8]) -> Option<(u64, &[u8])> {
+    let (&prefix, rest) = data.split_first()?;
+    let (value, rest) = match prefix {
+        0x00..=0xfc => (prefix as u64, rest),
+        0xfd if rest.len() >= 2 => (u16::from_le_bytes([rest[0], rest[1]]) as u64, &rest[2..]),
+        0xfe if rest.len() >= 4 => (u32::from_le_bytes(rest[..4].try_into().ok()?) as u64, &rest[4..]),
+        _ => return None,
+    };
+
+    // Reject non-canonical encodings
+    let canonical = match prefix {
+        0xfd => value >= 0xfd,
+        0xfe => value > 0xffff,
+        _ => true,
+    };
+    canonical.then_some((value, rest))
+}
+
+/// Decodes a bech32 or bech32m string, returning (hrp, 8-bit data, is_bech32m)
+fn bech32_decode(encoded: &str) -> Option<(String, Vec<u8>, bool)> {
+    let has_lower = encoded.bytes().any(|c| c.is_ascii_lowercase());
+    let has_upper = encoded.bytes().any(|c| c.is_ascii_uppercase());
+    if has_lower && has_upper || !encoded.is_ascii() {
+        return None;
+    }
+    let encoded = encoded.to_ascii_lowercase();
+
+    let separator = encoded.rfind('1')?;
+    let (hrp, data_part) = (&encoded[..separator], &encoded[separator + 1..]);
+    if hrp.is_empty() || data_part.len() < CHECKSUM_LEN {
+        return None;
+    }
+    if hrp.bytes().any(|c| !(33..=126).contains(&c)) {
+        return None;
+    }
+
+    let values = data_part
+        .bytes()
+        .map(|c| CHARSET.iter().position(|&x| x == c).map(|v| v as u8))
+        .collect::<Option<Vec<u8>>>()?;
+
+    let mut checked = hrp_expand(hrp);
+    checked.extend_from_slice(&values);
+    let is_bech32m = match polymod(&checked) {
+        BECH32_CONST => false,
+        BECH32M_CONST => true,
+        _ => return None,
+    };
+
+    let data = convert_bits_5_to_8(&values[..values.len() - CHECKSUM_LEN])?;
+    Some((hrp.to_string(), data, is_bech32m))
+}
+
+fn hrp_expand(hrp: &str) -> Vec<u8> {
+    let mut out: Vec<u8> = hrp.bytes().map(|c| c >> 5).collect();
+    out.push(0);
+    out.extend(hrp.bytes().map(|c| c & 31));
+    out
+}
+
+fn polymod(values: &[u8]) -> u32 {
+    const GENERATORS: [u32; 5] = [0x3b6a_57b2, 0x2650_8e6d, 0x1ea1_19fa, 0x3d42_33dd, 0x2a14_62b3];
+    let mut chk: u32 = 1;
+    for &value in values {
+        let top = chk >> 25;
+        chk = (chk & 0x01ff_ffff) << 5 ^ value as u32;
+        for (i, generator) in GENERATORS.iter().enumerate() {
+            if (top >> i) & 1 == 1 {
+                chk ^= generator;
+            }
+        }
+    }
+    chk
+}
+
+fn convert_bits_5_to_8(data: &[u8]) -> Option<Vec<u8>> {
+    let mut acc: u32 = 0;
+    let mut bits: u32 = 0;
+    let mut out = Vec::with_capacity(data.len() * 5 / 8);
+    for &value in data {
+        acc = ((acc << 5) | value as u32) & 0xfff;
+        bits += 5;
+        if bits >= 8 {
+            bits -= 8;
+            out.push((acc >> bits) as u8);
+        }
+    }
+    // Leftover padding must be shorter than a byte and all zeros
+    if bits >= 5 || acc & ((1 << bits) - 1) != 0 {
+        return None;
+    }
+    Some(out)
+}
+
+// F4Jumble inverse from ZIP 316
+fn f4jumble_inv(message: &[u8]) -> Option<Vec<u8>> {
+    if message.len() < F4JUMBLE_MIN_LEN {
+        return None;
+    }
+    let left_len = core::cmp::min(64, message.len() / 2);
+    let (c, d) = message.split_at(left_len);
+
+    let y = xor(c, &f4jumble_h(1, d, left_len));
+    let x = xor(d, &f4jumble_g(1, &y, d.len()));
+    let a = xor(&y, &f4jumble_h(0, &x, left_len));
+    let b = xor(&x, &f4jumble_g(0, &a, d.len()));
+
+    let mut out = a;
+    out.extend_from_slice(&b);
+    Some(out)
+}
+
+fn f4jumble_h(round: u8, input: &[u8], len: usize) -> Vec<u8> {
+    let mut personal = [0u8; 16];
+    personal[..13].copy_from_slice(b"UA_F4Jumble_H");
+    personal[13] = round;
+    Params::new()
+        .hash_length(len)
+        .personal(&personal)
+        .hash(input)
+        .as_bytes()
+        .to_vec()
+}
+
+fn f4jumble_g(round: u8, input: &[u8], len: usize) -> Vec<u8> {
+    let mut out = Vec::with_capacity(len + 64);
+    let mut counter: u16 = 0;
+    while out.len() < len {
+        let mut personal = [0u8; 16];
+        personal[..13].copy_from_slice(b"UA_F4Jumble_G");
+        personal[13] = round;
+        personal[14..].copy_from_slice(&counter.to_le_bytes());
+        out.extend_from_slice(Params::new().hash_length(64).personal(&personal).hash(input).as_bytes());
+        counter += 1;
+    }
+    out.truncate(len);
+    out
+}
+
+fn xor(lhs: &[u8], rhs: &[u8]) -> Vec<u8> {
+    lhs.iter().zip(rhs.iter()).map(|(a, b)| a ^ b).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bech32_encode(hrp: &str, data: &[u8], bech32m: bool) -> String {
+        let mut values = Vec::new();
+        let (mut acc, mut bits) = (0u32, 0u32);
+        for &byte in data {
+            acc = (acc << 8) | byte as u32;
+            bits += 8;
+            while bits >= 5 {
+                bits -= 5;
+                values.push(((acc >> bits) & 31) as u8);
+            }
+        }
+        if bits > 0 {
+            values.push(((acc << (5 - bits)) & 31) as u8);
+        }
+
+        let mut checked = hrp_expand(hrp);
+        checked.extend_from_slice(&values);
+        checked.extend_from_slice(&[0u8; CHECKSUM_LEN]);
+        let constant = if bech32m { BECH32M_CONST } else { BECH32_CONST };
+        let checksum = polymod(&checked) ^ constant;
+        values.extend((0..CHECKSUM_LEN).map(|i| ((checksum >> (5 * (5 - i))) & 31) as u8));
+
+        let mut out = format!("{}1", hrp);
+        out.extend(values.iter().map(|&v| CHARSET[v as usize] as char));
+        out
+    }
+
+    fn f4jumble(message: &[u8]) -> Vec<u8> {
+        let left_len = core::cmp::min(64, message.len() / 2);
+        let (a, b) = message.split_at(left_len);
+
+        let x = xor(b, &f4jumble_g(0, a, b.len()));
+        let y = xor(a, &f4jumble_h(0, &x, left_len));
+        let d = xor(&x, &f4jumble_g(1, &y, x.len()));
+        let c = xor(&y, &f4jumble_h(1, &d, left_len));
+
+        let mut out = c;
+        out.extend_from_slice(&d);
+        out
+    }
+
+    fn unified(hrp: &str, padding_hrp: &str, items: &[(u64, Vec<u8>)]) -> String {
+        let mut raw = Vec::new();
+        for (typecode, value) in items {
+            raw.push(*typecode as u8);
+            raw.push(value.len() as u8);
+            raw.extend_from_slice(value);
+        }
+        let mut padding = [0u8; UA_PADDING_LEN];
+        padding[..padding_hrp.len()].copy_from_slice(padding_hrp.as_bytes());
+        raw.extend_from_slice(&padding);
+        bech32_encode(hrp, &f4jumble(&raw), true)
+    }
+
+    fn sapling_item() -> (u64, Vec<u8>) {
+        (TYPECODE_SAPLING, vec![0x22; SHIELDED_RECEIVER_LEN])
+    }
+
+    fn orchard_item() -> (u64, Vec<u8>) {
+        (TYPECODE_ORCHARD, vec![0x33; SHIELDED_RECEIVER_LEN])
+    }
+
+    fn p2pkh_item() -> (u64, Vec<u8>) {
+        (TYPECODE_P2PKH, vec![0x11; 20])
+    }
+
+    #[test]
+    fn checksums_match_bip_vectors() {
+        // BIP 173 and BIP 350 test vectors
+        assert_eq!(bech32_decode("a12uel5l").map(|d| d.2), Some(false));
+        assert_eq!(bech32_decode("A12UEL5L").map(|d| d.2), Some(false));
+        assert_eq!(bech32_decode("abcdef1qpzry9x8gf2tvdw0s3jn54khce6mua7lmqqqxw").map(|d| d.2), Some(false));
+        assert_eq!(bech32_decode("a1lqfn3a").map(|d| d.2), Some(true));
+        assert_eq!(bech32_decode("abcdef1l7aum6echk45nj3s0wdvt2fg8x9yrzpqzd3ryx").map(|d| d.2), Some(true));
+        assert!(bech32_decode("a12uel5m").is_none());
+        assert!(bech32_decode("A12uEL5L").is_none());
+    }
+
+    #[test]
+    fn decodes_sapling_address() {
+        let receiver = [0x5a; SHIELDED_RECEIVER_LEN];
+        let decoded = decode(&bech32_encode(SAPLING_HRP_MAINNET, &receiver, false)).unwrap();
+        assert!(decoded.network == ZcashNetwork::Mainnet);
+        assert!(decoded.pool == ZcashPool::Sapling);
+        assert_eq!(decoded.receiver, receiver);
+
+        let decoded = decode(&bech32_encode(SAPLING_HRP_TESTNET, &receiver, false)).unwrap();
+        assert!(decoded.network == ZcashNetwork::Testnet);
+    }
+
+    #[test]
+    fn rejects_malformed_sapling_address() {
+        let receiver = [0x5a; SHIELDED_RECEIVER_LEN];
+        assert!(decode(&bech32_encode(SAPLING_HRP_MAINNET, &receiver, true)).is_none());
+        assert!(decode(&bech32_encode(SAPLING_HRP_MAINNET, &receiver[..42], false)).is_none());
+        assert!(decode(&bech32_encode("zx", &receiver, false)).is_none());
+    }
+
+    #[test]
+    fn decodes_unified_address_preferring_orchard() {
+        let address = unified(UNIFIED_HRP_MAINNET, UNIFIED_HRP_MAINNET, &[p2pkh_item(), sapling_item(), orchard_item()]);
+        let decoded = decode(&address).unwrap();
+        assert!(decoded.network == ZcashNetwork::Mainnet);
+        assert!(decoded.pool == ZcashPool::Orchard);
+        assert_eq!(decoded.receiver, [0x33; SHIELDED_RECEIVER_LEN]);
+
+        let address = unified(UNIFIED_HRP_TESTNET, UNIFIED_HRP_TESTNET, &[sapling_item()]);
+        let decoded = decode(&address).unwrap();
+        assert!(decoded.network == ZcashNetwork::Testnet);
+        assert!(decoded.pool == ZcashPool::Sapling);
+        assert_eq!(decoded.receiver, [0x22; SHIELDED_RECEIVER_LEN]);
+    }
+
+    #[test]
+    fn rejects_bad_checksum() {
+        let mut address = unified(UNIFIED_HRP_MAINNET, UNIFIED_HRP_MAINNET, &[orchard_item()]);
+        let last = address.pop().unwrap();
+        address.push(if last == 'q' { 'p' } else { 'q' });
+        assert!(decode(&address).is_none());
+
+        let mut address = bech32_encode(SAPLING_HRP_MAINNET, &[0x5a; SHIELDED_RECEIVER_LEN], false);
+        address.replace_range(10..11, if &address[10..11] == "q" { "p" } else { "q" });
+        assert!(decode(&address).is_none());
+    }
+
+    #[test]
+    fn rejects_wrong_network_padding() {
+        // Mainnet prefix over a payload jumbled for testnet
+        let address = unified(UNIFIED_HRP_MAINNET, UNIFIED_HRP_TESTNET, &[orchard_item()]);
+        assert!(decode(&address).is_none());
+    }
+
+    #[test]
+    fn rejects_transparent_only_unified_address() {
+        let address = unified(UNIFIED_HRP_MAINNET, UNIFIED_HRP_MAINNET, &[p2pkh_item(), (0x40, vec![0u8; 32])]);
+        assert!(decode(&address).is_none());
+    }
+
+    #[test]
+    fn rejects_unordered_unified_items() {
+        let address = unified(UNIFIED_HRP_MAINNET, UNIFIED_HRP_MAINNET, &[orchard_item(), sapling_item()]);
+        assert!(decode(&address).is_none());
+    }
+
+    #[test]
+    fn f4jumble_round_trips() {
+        for len in [48usize, 83, 128, 200] {
+            let message: Vec<u8> = (0..len).map(|i| i as u8).collect();
+            assert_eq!(f4jumble_inv(&f4jumble(&message)).unwrap(), message);
+        }
+    }
+}
@@ -0,0 +1,437 @@
+//! Zcash block header parsing, proof-of-work target checks and
+//! transaction Merkle inclusion proofs
+//!
+//! Hashes are kept in internal byte order (the raw double-SHA256 output);
+//! explorers display them reversed.
+//!
+//! The Equihash solution is hashed into the block hash but is not itself
+//! verified here, which would not fit in the compute budget. Meeting the
+//! target then takes SHA-256 work alone, so relayed headers are only as
+//! trustworthy as the relayers submitting them. `required_bits` still
+//! recomputes the target from the chain instead of taking the header's
+//! word for it, which keeps a relayer from lowering the difficulty.
+
+use anchor_lang::solana_program::hash::{hash, hashv};
+
+pub const HEADER_PREFIX_LEN: usize = 4 + 32 + 32 + 32 + 4 + 4 + 32;
+pub const EQUIHASH_SOLUTION_LEN: usize = 1344;
+/// Header prefix, compact-size solution length (0xfd 0x40 0x05) and solution
+pub const HEADER_LEN: usize = HEADER_PREFIX_LEN + 3 + EQUIHASH_SOLUTION_LEN;
+
+const MAINNET_POW_LIMIT: [u8; 32] = pow_limit(0x00, 0x07);
+const TESTNET_POW_LIMIT: [u8; 32] = pow_limit(0x07, 0xff);
+
+// Difficulty adjustment parameters (ZIP 208, post-Blossom spacing)
+const POW_AVERAGING_WINDOW: usize = 17;
+const POW_MEDIAN_BLOCK_SPAN: usize = 11;
+const POW_TARGET_SPACING: i64 = 75;
+const POW_MAX_ADJUST_DOWN: i64 = 32;
+const POW_MAX_ADJUST_UP: i64 = 16;
+/// Testnet accepts a minimum-difficulty block after this many missed spacings
+const TESTNET_MIN_DIFFICULTY_GAP: i64 = 6;
+
+/// Number of headers, ending at the parent, that `required_bits` looks at
+pub const DIFFICULTY_WINDOW: usize = POW_AVERAGING_WINDOW + POW_MEDIAN_BLOCK_SPAN;
+
+const fn pow_limit(first: u8, second: u8) -> [u8; 32] {
+    let mut limit = [0xffu8; 32];
+    limit[0] = first;
+    limit[1] = second;
+    limit
+}
+
+pub struct ParsedHeader {
+    pub hash: [u8; 32],
+    pub prev_hash: [u8; 32],
+    pub merkle_root: [u8; 32],
+    pub time: u32,
+    pub bits: u32,
+}
+
+pub fn parse(header: &[u8]) -> Option<ParsedHeader> {
+    if header.len() != HEADER_LEN {
+        return None;
+    }
+    let solution_len = &header[HEADER_PREFIX_LEN..HEADER_PREFIX_LEN + 3];
+    if solution_len != [0xfd, 0x40, 0x05] {
+        return None;
+    }
+
+    let read_u32 = |at: usize| u32::from_le_bytes(header[at..at + 4].try_into().unwrap());
+
+    Some(ParsedHeader {
+        hash: sha256d(header),
+        prev_hash: header[4..36].try_into().ok()?,
+        merkle_root: header[36..68].try_into().ok()?,
+        time: read_u32(100),
+        bits: read_u32(104),
+    })
+}
+
+/// Expands compact `bits` into a big-endian 256-bit target
+pub fn target_from_bits(bits: u32) -> Option<[u8; 32]> {
+    let exponent = (bits >> 24) as usize;
+    let mantissa = bits & 0x007f_ffff;
+    // Negative or zero targets are invalid
+    if bits & 0x0080_0000 != 0 || mantissa == 0 {
+        return None;
+    }
+
+    let mut target = [0u8; 32];
+    let mantissa_bytes = mantissa.to_be_bytes();
+    for (i, byte) in mantissa_bytes[1..].iter().enumerate() {
+        // Byte i of the mantissa sits at 256^(exponent - 1 - i)
+        let power = exponent as isize - 1 - i as isize;
+        if power < 0 {
+            continue;
+        }
+        if power >= 32 {
+            if *byte != 0 {
+                return None;
+            }
+            continue;
+        }
+        target[31 - power as usize] = *byte;
+    }
+    Some(target)
+}
+
+/// Checks the block hash against the target encoded in `bits` and the
+/// network's proof-of-work limit
+pub fn meets_target(block_hash: &[u8; 32], bits: u32, is_mainnet: bool) -> bool {
+    let target = match target_from_bits(bits) {
+        Some(target) => target,
+        None => return false,
+    };
+    let pow_limit = if is_mainnet { MAINNET_POW_LIMIT } else { TESTNET_POW_LIMIT };
+    if target > pow_limit {
+        return false;
+    }
+
+    // The hash is compared as a little-endian number
+    let mut hash_be = *block_hash;
+    hash_be.reverse();
+    hash_be <= target
+}
+
+/// Approximate work for a block at `bits`: 2^256 / target, saturating
+pub fn work_from_bits(bits: u32) -> u128 {
+    let exponent = (bits >> 24) as i64;
+    let mantissa = (bits & 0x007f_ffff) as u128;
+    if mantissa == 0 {
+        return 0;
+    }
+    // target = mantissa * 2^(8 * (exponent - 3))
+    let shift = 256 - 8 * (exponent - 3);
+    if shift >= 128 {
+        return u128::MAX / mantissa;
+    }
+    if shift <= 0 {
+        return 0;
+    }
+    (1u128 << shift) / mantissa
+}
+
+/// Compact `bits` the block following `window` must carry. `window` holds
+/// the (time, bits) of the DIFFICULTY_WINDOW preceding headers, parent
+/// first. Mirrors zcashd's DigiShield v3 rule: the mean target of the last
+/// 17 blocks scaled by their damped and clamped median-time timespan.
+pub fn required_bits(window: &[(u32, u32)], time: u32, is_mainnet: bool) -> Option<u32> {
+    if window.len() != DIFFICULTY_WINDOW {
+        return None;
+    }
+    let pow_limit = if is_mainnet { MAINNET_POW_LIMIT } else { TESTNET_POW_LIMIT };
+
+    let parent_time = window[0].0 as i64;
+    if !is_mainnet && time as i64 > parent_time + TESTNET_MIN_DIFFICULTY_GAP * POW_TARGET_SPACING {
+        return Some(compact_from_target(&pow_limit));
+    }
+
+    let mut total = U256::ZERO;
+    for (_, bits) in &window[..POW_AVERAGING_WINDOW] {
+        total = total.checked_add(&U256::from_be_bytes(&target_from_bits(*bits)?))?;
+    }
+    let average = total.div_u64(POW_AVERAGING_WINDOW as u64);
+
+    let averaging_timespan = POW_AVERAGING_WINDOW as i64 * POW_TARGET_SPACING;
+    let min_timespan = averaging_timespan * (100 - POW_MAX_ADJUST_UP) / 100;
+    let max_timespan = averaging_timespan * (100 + POW_MAX_ADJUST_DOWN) / 100;
+    let last = median_time(&window[..POW_MEDIAN_BLOCK_SPAN]);
+    let first = median_time(&window[POW_AVERAGING_WINDOW..]);
+    let actual = last - first;
+    let damped = averaging_timespan + (actual - averaging_timespan) / 4;
+    let timespan = damped.clamp(min_timespan, max_timespan);
+
+    let target = average
+        .div_u64(averaging_timespan as u64)
+        .checked_mul_u64(timespan as u64)?
+        .to_be_bytes();
+    Some(compact_from_target(if target > pow_limit { &pow_limit } else { &target }))
+}
+
+fn median_time(window: &[(u32, u32)]) -> i64 {
+    let mut times: Vec<u32> = window.iter().map(|(time, _)| *time).collect();
+    times.sort_unstable();
+    times[times.len() / 2] as i64
+}
+
+/// Encodes a big-endian target in compact form, truncating to the three
+/// most significant bytes as zcashd's GetCompact does
+pub fn compact_from_target(target: &[u8; 32]) -> u32 {
+    let first = match target.iter().position(|&byte| byte != 0) {
+        Some(first) => first,
+        None => return 0,
+    };
+    let mut size = 32 - first;
+    let mut mantissa = [0u8; 4];
+    for i in 0..3 {
+        mantissa[i + 1] = target.get(first + i).copied().unwrap_or(0);
+    }
+    let mut compact = u32::from_be_bytes(mantissa);
+    if size < 3 {
+        compact >>= 8 * (3 - size);
+    }
+    // Keep the sign bit clear
+    if compact & 0x0080_0000 != 0 {
+        compact >>= 8;
+        size += 1;
+    }
+    compact | (size as u32) << 24
+}
+
+/// Minimal unsigned 256-bit arithmetic for target averaging, as four
+/// little-endian 64-bit limbs
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+struct U256([u64; 4]);
+
+impl U256 {
+    const ZERO: U256 = U256([0; 4]);
+
+    fn from_be_bytes(bytes: &[u8; 32]) -> Self {
+        let mut limbs = [0u64; 4];
+        for (i, limb) in limbs.iter_mut().enumerate() {
+            let start = 32 - 8 * (i + 1);
+            *limb = u64::from_be_bytes(bytes[start..start + 8].try_into().unwrap());
+        }
+        U256(limbs)
+    }
+
+    fn to_be_bytes(self) -> [u8; 32] {
+        let mut bytes = [0u8; 32];
+        for (i, limb) in self.0.iter().enumerate() {
+            let start = 32 - 8 * (i + 1);
+            bytes[start..start + 8].copy_from_slice(&limb.to_be_bytes());
+        }
+        bytes
+    }
+
+    fn checked_add(&self, other: &U256) -> Option<U256> {
+        let mut out = [0u64; 4];
+        let mut carry = false;
+        for (i, limb) in out.iter_mut().enumerate() {
+            let (sum, overflow_a) = self.0[i].overflowing_add(other.0[i]);
+            let (sum, overflow_b) = sum.overflowing_add(carry as u64);
+            *limb = sum;
+            carry = overflow_a || overflow_b;
+        }
+        (!carry).then_some(U256(out))
+    }
+
+    fn checked_mul_u64(&self, factor: u64) -> Option<U256> {
+        let mut out = [0u64; 4];
+        let mut carry: u128 = 0;
+        for (i, limb) in out.iter_mut().enumerate() {
+            let product = self.0[i] as u128 * factor as u128 + carry;
+            *limb = product as u64;
+            carry = product >> 64;
+        }
+        (carry == 0).then_some(U256(out))
+    }
+
+    fn div_u64(&self, divisor: u64) -> U256 {
+        let mut out = [0u64; 4];
+        let mut remainder: u128 = 0;
+        for i in (0..4).rev() {
+            let current = (remainder << 64) | self.0[i] as u128;
+            out[i] = (current / divisor as u128) as u64;
+            remainder = current % divisor as u128;
+        }
+        U256(out)
+    }
+}
+
+/// Folds a Merkle branch over `tx_id` at position `index` and returns the root
+pub fn merkle_root_from_branch(tx_id: &[u8; 32], index: u32, branch: &[[u8; 32]]) -> [u8; 32] {
+    let mut node = *tx_id;
+    let mut position = index;
+    for sibling in branch {
+        node = if position & 1 == 0 {
+            sha256d_pair(&node, sibling)
+        } else {
+            sha256d_pair(sibling, &node)
+        };
+        position >>= 1;
+    }
+    node
+}
+
+fn sha256d(data: &[u8]) -> [u8; 32] {
+    hash(&hash(data).to_bytes()).to_bytes()
+}
+
+fn sha256d_pair(left: &[u8; 32], right: &[u8; 32]) -> [u8; 32] {
+    hash(&hashv(&[left, right]).to_bytes()).to_bytes()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TESTNET_LIMIT_BITS: u32 = 0x2007ffff;
+
+    fn steady_window(bits: u32, spacing: u32) -> Vec<(u32, u32)> {
+        (0..DIFFICULTY_WINDOW as u32)
+            .map(|i| (1_000_000 - i * spacing, bits))
+            .collect()
+    }
+
+    #[test]
+    fn compact_round_trips() {
+        for bits in [0x1d00ffff, 0x1c0a1b2c, 0x2007ffff, 0x1f07ffff, 0x03123456] {
+            assert_eq!(compact_from_target(&target_from_bits(bits).unwrap()), bits);
+        }
+        assert_eq!(compact_from_target(&TESTNET_POW_LIMIT), TESTNET_LIMIT_BITS);
+        assert_eq!(compact_from_target(&MAINNET_POW_LIMIT), 0x1f07ffff);
+    }
+
+    #[test]
+    fn rejects_negative_and_oversized_targets() {
+        assert!(target_from_bits(0x1d80ffff).is_none());
+        assert!(target_from_bits(0x1d000000).is_none());
+        assert!(target_from_bits(0x21010000).is_none());
+    }
+
+    #[test]
+    fn meets_target_respects_pow_limit() {
+        let easy_hash = [0u8; 32];
+        assert!(meets_target(&easy_hash, TESTNET_LIMIT_BITS, false));
+        // Valid compact target, but easier than mainnet allows
+        assert!(!meets_target(&easy_hash, TESTNET_LIMIT_BITS, true));
+
+        // Hash bytes are little-endian: the last byte is most significant
+        let mut hard_hash = [0u8; 32];
+        hard_hash[31] = 0x08;
+        assert!(!meets_target(&hard_hash, TESTNET_LIMIT_BITS, false));
+        hard_hash[31] = 0x07;
+        assert!(meets_target(&hard_hash, TESTNET_LIMIT_BITS, false));
+    }
+
+    #[test]
+    fn work_grows_with_difficulty() {
+        assert!(work_from_bits(0x1d00ffff) > work_from_bits(0x1f07ffff));
+        assert!(work_from_bits(0x1f07ffff) > work_from_bits(TESTNET_LIMIT_BITS));
+        assert_eq!(work_from_bits(0x1d000000), 0);
+    }
+
+    #[test]
+    fn parse_reads_fields() {
+        let mut header = vec![0u8; HEADER_LEN];
+        header[4..36].copy_from_slice(&[1u8; 32]);
+        header[36..68].copy_from_slice(&[2u8; 32]);
+        header[100..104].copy_from_slice(&1_700_000_000u32.to_le_bytes());
+        header[104..108].copy_from_slice(&TESTNET_LIMIT_BITS.to_le_bytes());
+        header[HEADER_PREFIX_LEN..HEADER_PREFIX_LEN + 3].copy_from_slice(&[0xfd, 0x40, 0x05]);
+
+        let parsed = parse(&header).unwrap();
+        assert_eq!(parsed.prev_hash, [1u8; 32]);
+        assert_eq!(parsed.merkle_root, [2u8; 32]);
+        assert_eq!(parsed.time, 1_700_000_000);
+        assert_eq!(parsed.bits, TESTNET_LIMIT_BITS);
+        assert_eq!(parsed.hash, sha256d(&header));
+
+        header[HEADER_PREFIX_LEN] = 0xfe;
+        assert!(parse(&header).is_none());
+        assert!(parse(&header[1..]).is_none());
+    }
+
+    #[test]
+    fn merkle_branch_folds_by_position() {
+        let leaves: Vec<[u8; 32]> = (0..4u8).map(|i| [i; 32]).collect();
+        let left = sha256d_pair(&leaves[0], &leaves[1]);
+        let right = sha256d_pair(&leaves[2], &leaves[3]);
+        let root = sha256d_pair(&left, &right);
+
+        assert_eq!(merkle_root_from_branch(&leaves[0], 0, &[leaves[1], right]), root);
+        assert_eq!(merkle_root_from_branch(&leaves[3], 3, &[leaves[2], left]), root);
+        assert_ne!(merkle_root_from_branch(&leaves[3], 2, &[leaves[2], left]), root);
+    }
+
+    #[test]
+    fn steady_blocks_keep_difficulty() {
+        // Mantissa divisible by the 1275s averaging timespan, so the
+        // divide-then-multiply in the rule is exact
+        let bits = 0x1c137478;
+        let window = steady_window(bits, POW_TARGET_SPACING as u32);
+        let next = required_bits(&window, window[0].0 + 75, true).unwrap();
+        assert_eq!(next, bits);
+    }
+
+    #[test]
+    fn fast_blocks_raise_difficulty_up_to_the_clamp() {
+        let bits = 0x1c0a1b2c;
+        let slightly_fast = required_bits(&steady_window(bits, 70), 1_000_070, true).unwrap();
+        assert!(work_from_bits(slightly_fast) > work_from_bits(bits));
+
+        // Every block at the same second hits the 16% ceiling
+        let instant = required_bits(&steady_window(bits, 0), 1_000_000, true).unwrap();
+        let expected = U256::from_be_bytes(&target_from_bits(bits).unwrap())
+            .div_u64(1275)
+            .checked_mul_u64(1275 * 84 / 100)
+            .unwrap();
+        assert_eq!(instant, compact_from_target(&expected.to_be_bytes()));
+    }
+
+    #[test]
+    fn slow_blocks_lower_difficulty_up_to_the_pow_limit() {
+        let bits = 0x1c0a1b2c;
+        let slow = required_bits(&steady_window(bits, 300), 1_000_300, true).unwrap();
+        assert!(work_from_bits(slow) < work_from_bits(bits));
+
+        let at_limit = required_bits(&steady_window(0x1f07ffff, 300), 1_000_300, true).unwrap();
+        assert_eq!(at_limit, 0x1f07ffff);
+    }
+
+    #[test]
+    fn testnet_allows_min_difficulty_after_a_gap() {
+        let bits = 0x1c137478;
+        let window = steady_window(bits, 75);
+        let parent_time = window[0].0;
+        assert_eq!(required_bits(&window, parent_time + 6 * 75, false), Some(bits));
+        assert_eq!(
+            required_bits(&window, parent_time + 6 * 75 + 1, false),
+            Some(TESTNET_LIMIT_BITS)
+        );
+        // Mainnet has no such exception
+        assert_eq!(required_bits(&window, parent_time + 6 * 75 + 1, true), Some(bits));
+    }
+
+    #[test]
+    fn required_bits_needs_a_full_window() {
+        let window = steady_window(0x1c0a1b2c, 75);
+        assert!(required_bits(&window[1..], 1_000_075, true).is_none());
+    }
+
+    #[test]
+    fn u256_arithmetic() {
+        let mut max = [0xffu8; 32];
+        let big = U256::from_be_bytes(&max);
+        assert!(big.checked_add(&U256([1, 0, 0, 0])).is_none());
+        assert!(big.checked_mul_u64(2).is_none());
+        assert_eq!(big.div_u64(1).to_be_bytes(), max);
+
+        max[0] = 0x7f;
+        let half = U256::from_be_bytes(&max);
+        assert_eq!(half.checked_add(&half).unwrap().checked_add(&U256([1, 0, 0, 0])), Some(big));
+        assert_eq!(half.checked_mul_u64(2).unwrap().div_u64(2), half);
+    }
+}
@@ -0,0 +1,185 @@
+//! ZIP 244 transaction ids recomputed from the digests of a v5 transaction
+//!
+//! A payout is shown to be part of a transaction by opening one shielded
+//! pool: its outputs (Sapling) or actions (Orchard) are supplied in their
+//! compact form, which carries the note commitment, and everything else is
+//! supplied as the digests ZIP 244 already defines for it. The resulting id
+//! is in internal byte order, as the block Merkle tree uses it.
+
+use blake2b_simd::{Params, State};
+
+use crate::{PayoutBundle, PayoutTx};
+
+fn state(personal: &[u8; 16]) -> State {
+    Params::new().hash_length(32).personal(personal).to_state()
+}
+
+fn digest(personal: &[u8; 16], parts: &[&[u8]]) -> [u8; 32] {
+    let mut state = state(personal);
+    for part in parts {
+        state.update(part);
+    }
+    state.finalize().as_bytes().try_into().unwrap()
+}
+
+/// Transaction id of `tx` per ZIP 244
+pub fn tx_id(tx: &PayoutTx) -> [u8; 32] {
+    let opened = bundle_digest(&tx.bundle);
+    let (sapling, orchard) = match tx.bundle {
+        PayoutBundle::Sapling { .. } => (opened, tx.other_pool_digest),
+        PayoutBundle::Orchard { .. } => (tx.other_pool_digest, opened),
+    };
+
+    let mut personal = *b"ZcashTxHash_\0\0\0\0";
+    personal[12..].copy_from_slice(&tx.consensus_branch_id.to_le_bytes());
+    digest(&personal, &[&tx.header_digest, &tx.transparent_digest, &sapling, &orchard])
+}
+
+/// Whether `note_commitment` belongs to one of the bundle's outputs
+pub fn has_output(bundle: &PayoutBundle, note_commitment: &[u8; 32]) -> bool {
+    match bundle {
+        PayoutBundle::Sapling { outputs, .. } => outputs.iter().any(|output| &output.cmu == note_commitment),
+        PayoutBundle::Orchard { actions, .. } => actions.iter().any(|action| &action.cmx == note_commitment),
+    }
+}
+
+// Only bundles with at least one output or action reach these digests;
+// ZIP 244 hashes empty bundles differently
+fn bundle_digest(bundle: &PayoutBundle) -> [u8; 32] {
+    match bundle {
+        PayoutBundle::Sapling {
+            spends_digest,
+            outputs,
+            memos_digest,
+            noncompact_digest,
+            value_balance,
+        } => {
+            let mut compact = state(b"ZTxIdSOutC__Hash");
+            for output in outputs {
+                compact.update(&output.cmu);
+                compact.update(&output.ephemeral_key);
+                compact.update(&output.enc_ciphertext);
+            }
+            let outputs_digest = digest(
+                b"ZTxIdSOutputHash",
+                &[compact.finalize().as_bytes(), memos_digest, noncompact_digest],
+            );
+            digest(
+                b"ZTxIdSaplingHash",
+                &[spends_digest, &outputs_digest, &value_balance.to_le_bytes()],
+            )
+        }
+        PayoutBundle::Orchard {
+            actions,
+            memos_digest,
+            noncompact_digest,
+            flags,
+            value_balance,
+            anchor,
+        } => {
+            let mut compact = state(b"ZTxIdOrcActCHash");
+            for action in actions {
+                compact.update(&action.nullifier);
+                compact.update(&action.cmx);
+                compact.update(&action.ephemeral_key);
+                compact.update(&action.enc_ciphertext);
+            }
+            digest(
+                b"ZTxIdOrchardHash",
+                &[
+                    compact.finalize().as_bytes(),
+                    memos_digest,
+                    noncompact_digest,
+                    &[*flags],
+                    &value_balance.to_le_bytes(),
+                    anchor,
+                ],
+            )
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{OrchardCompactAction, SaplingCompactOutput};
+
+    fn sapling_tx(cmus: &[[u8; 32]]) -> PayoutTx {
+        PayoutTx {
+            consensus_branch_id: 0xc2d6_d0b4,
+            header_digest: [1; 32],
+            transparent_digest: [2; 32],
+            other_pool_digest: [3; 32],
+            bundle: PayoutBundle::Sapling {
+                spends_digest: [4; 32],
+                outputs: cmus
+                    .iter()
+                    .map(|cmu| SaplingCompactOutput {
+                        cmu: *cmu,
+                        ephemeral_key: [5; 32],
+                        enc_ciphertext: [6; 52],
+                    })
+                    .collect(),
+                memos_digest: [7; 32],
+                noncompact_digest: [8; 32],
+                value_balance: -1000,
+            },
+        }
+    }
+
+    #[test]
+    fn tx_id_matches_the_zip_244_layout() {
+        let tx = sapling_tx(&[[0xaa; 32]]);
+
+        let compact = digest(b"ZTxIdSOutC__Hash", &[&[0xaa; 32], &[5; 32], &[6; 52]]);
+        let outputs = digest(b"ZTxIdSOutputHash", &[&compact, &[7; 32], &[8; 32]]);
+        let sapling = digest(b"ZTxIdSaplingHash", &[&[4; 32], &outputs, &(-1000i64).to_le_bytes()]);
+        let mut personal = *b"ZcashTxHash_\0\0\0\0";
+        personal[12..].copy_from_slice(&[0xb4, 0xd0, 0xd6, 0xc2]);
+        let expected = digest(&personal, &[&[1; 32], &[2; 32], &sapling, &[3; 32]]);
+
+        assert_eq!(tx_id(&tx), expected);
+    }
+
+    #[test]
+    fn tx_id_commits_to_every_output() {
+        let tx = sapling_tx(&[[0xaa; 32], [0xbb; 32]]);
+        assert!(has_output(&tx.bundle, &[0xbb; 32]));
+        assert!(!has_output(&tx.bundle, &[0xcc; 32]));
+
+        // Swapping in another commitment or dropping an output changes the id
+        assert_ne!(tx_id(&tx), tx_id(&sapling_tx(&[[0xaa; 32], [0xcc; 32]])));
+        assert_ne!(tx_id(&tx), tx_id(&sapling_tx(&[[0xaa; 32]])));
+    }
+
+    #[test]
+    fn orchard_bundles_fill_the_orchard_slot() {
+        let action = OrchardCompactAction {
+            nullifier: [9; 32],
+            cmx: [0xdd; 32],
+            ephemeral_key: [5; 32],
+            enc_ciphertext: [6; 52],
+        };
+        let tx = PayoutTx {
+            bundle: PayoutBundle::Orchard {
+                actions: vec![action],
+                memos_digest: [7; 32],
+                noncompact_digest: [8; 32],
+                flags: 0x03,
+                value_balance: 0,
+                anchor: [10; 32],
+            },
+            ..sapling_tx(&[])
+        };
+        assert!(has_output(&tx.bundle, &[0xdd; 32]));
+
+        let compact = digest(b"ZTxIdOrcActCHash", &[&[9; 32], &[0xdd; 32], &[5; 32], &[6; 52]]);
+        let orchard = digest(
+            b"ZTxIdOrchardHash",
+            &[&compact, &[7; 32], &[8; 32], &[0x03], &0i64.to_le_bytes(), &[10; 32]],
+        );
+        let mut personal = *b"ZcashTxHash_\0\0\0\0";
+        personal[12..].copy_from_slice(&0xc2d6_d0b4u32.to_le_bytes());
+        assert_eq!(tx_id(&tx), digest(&personal, &[&[1; 32], &[2; 32], &[3; 32], &orchard]));
+    }
+}
@@ -0,0 +1,403 @@
+//! Drives a withdrawal through the program from initiation to completion,
+//! proving it with the Groth16 test vector in `tests/fixtures`
+
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::entrypoint::ProgramResult;
+use anchor_lang::solana_program::program_pack::Pack;
+use anchor_lang::{system_program, InstructionData};
+use anchor_spl::token::spl_token;
+use solana_program_test::{processor, BanksClientError, ProgramTest, ProgramTestContext};
+use solana_sdk::account::Account as SolanaAccount;
+use solana_sdk::instruction::{Instruction, InstructionError};
+use solana_sdk::signature::{Keypair, Signer};
+use solana_sdk::transaction::{Transaction, TransactionError};
+use zcash_bridge::{
+    BridgeConfig, BridgeError, CustodyMode, NullifierRecord, OperatorBond, VerifyingKeyData, WithdrawalProof,
+    WithdrawalStatus, WithdrawalTicket, ZcashNetwork,
+};
+
+const FIXTURE: &str = include_str!("../../../tests/fixtures/groth16_withdrawal.json");
+const USER_BALANCE: u64 = 10_000_000_000;
+
+fn hex<const N: usize>(value: &serde_json::Value) -> [u8; N] {
+    let text = value.as_str().expect("hex string");
+    assert_eq!(text.len(), N * 2);
+    let mut out = [0u8; N];
+    for (i, byte) in out.iter_mut().enumerate() {
+        *byte = u8::from_str_radix(&text[i * 2..i * 2 + 2], 16).expect("hex digit");
+    }
+    out
+}
+
+struct Fixture {
+    key: VerifyingKeyData,
+    proof: [u8; 256],
+    commitment: [u8; 32],
+    nullifier: [u8; 32],
+    amount: u64,
+}
+
+impl Fixture {
+    fn load() -> Self {
+        let json: serde_json::Value = serde_json::from_str(FIXTURE).unwrap();
+        let vk = &json["verifying_key"];
+        let ticket = &json["withdrawal_ticket"];
+        let ic = vk["ic"].as_array().unwrap();
+        Fixture {
+            key: VerifyingKeyData {
+                alpha_g1: hex(&vk["alpha_g1"]),
+                beta_g2: hex(&vk["beta_g2"]),
+                gamma_g2: hex(&vk["gamma_g2"]),
+                delta_g2: hex(&vk["delta_g2"]),
+                ic: [hex(&ic[0]), hex(&ic[1]), hex(&ic[2]), hex(&ic[3])],
+            },
+            proof: hex(&json["proof_data"]),
+            commitment: hex(&ticket["partial_note_commitment"]),
+            nullifier: hex(&ticket["partial_note_nullifier"]),
+            amount: ticket["amount"].as_u64().unwrap(),
+        }
+    }
+}
+
+// Anchor's entrypoint ties the accounts to the slice's lifetime, which the
+// builtin processor signature cannot express
+fn process(program_id: &Pubkey, accounts: &[AccountInfo], data: &[u8]) -> ProgramResult {
+    let accounts = Box::leak(accounts.to_vec().into_boxed_slice());
+    zcash_bridge::entry(program_id, accounts, data)
+}
+
+fn pda(seeds: &[&[u8]]) -> (Pubkey, u8) {
+    Pubkey::find_program_address(seeds, &zcash_bridge::ID)
+}
+
+fn packed<T: Pack>(state: T) -> Vec<u8> {
+    let mut data = vec![0u8; T::LEN];
+    state.pack_into_slice(&mut data);
+    data
+}
+
+struct Bridge {
+    context: ProgramTestContext,
+    authority: Keypair,
+    operator: Keypair,
+    user: Keypair,
+    mint: Pubkey,
+    user_token_account: Pubkey,
+}
+
+impl Bridge {
+    async fn start() -> Self {
+        let authority = Keypair::new();
+        let operator = Keypair::new();
+        let user = Keypair::new();
+        let mint = Pubkey::new_unique();
+        let user_token_account = Pubkey::new_unique();
+
+        let mut program_test = ProgramTest::new("zcash_bridge", zcash_bridge::ID, processor!(process));
+        program_test.prefer_bpf(false);
+        for signer in [&authority, &operator, &user] {
+            program_test.add_account(
+                signer.pubkey(),
+                SolanaAccount::new(10_000_000_000, 0, &system_program::ID),
+            );
+        }
+        let rent = Rent::default();
+        program_test.add_account(
+            mint,
+            SolanaAccount {
+                lamports: rent.minimum_balance(spl_token::state::Mint::LEN),
+                data: packed(spl_token::state::Mint {
+                    mint_authority: Some(authority.pubkey()).into(),
+                    supply: USER_BALANCE,
+                    decimals: 8,
+                    is_initialized: true,
+                    freeze_authority: None.into(),
+                }),
+                owner: spl_token::ID,
+                executable: false,
+                rent_epoch: 0,
+            },
+        );
+        program_test.add_account(
+            user_token_account,
+            SolanaAccount {
+                lamports: rent.minimum_balance(spl_token::state::Account::LEN),
+                data: packed(spl_token::state::Account {
+                    mint,
+                    owner: user.pubkey(),
+                    amount: USER_BALANCE,
+                    state: spl_token::state::AccountState::Initialized,
+                    ..Default::default()
+                }),
+                owner: spl_token::ID,
+                executable: false,
+                rent_epoch: 0,
+            },
+        );
+
+        // The minimum bond starts at zero, so an empty bond lets the
+        // operator attest without going through the bond vault
+        let (bond_key, bond_bump) = pda(&[b"operator_bond", operator.pubkey().as_ref()]);
+        let mut bond = Vec::new();
+        OperatorBond {
+            operator: operator.pubkey(),
+            amount: 0,
+            unbonding_amount: 0,
+            unbonding_available_at: 0,
+            bump: bond_bump,
+        }
+        .try_serialize(&mut bond)
+        .unwrap();
+        program_test.add_account(
+            bond_key,
+            SolanaAccount {
+                lamports: rent.minimum_balance(bond.len()),
+                data: bond,
+                owner: zcash_bridge::ID,
+                executable: false,
+                rent_epoch: 0,
+            },
+        );
+
+        let mut bridge = Bridge {
+            context: program_test.start_with_context().await,
+            authority,
+            operator,
+            user,
+            mint,
+            user_token_account,
+        };
+        bridge.initialize().await;
+        bridge
+    }
+
+    async fn send(&mut self, instruction: Instruction, signer: &Keypair) -> std::result::Result<(), BanksClientError> {
+        let payer = &self.context.payer;
+        let blockhash = self.context.banks_client.get_latest_blockhash().await.unwrap();
+        let transaction = Transaction::new_signed_with_payer(
+            &[instruction],
+            Some(&payer.pubkey()),
+            &[payer, signer],
+            blockhash,
+        );
+        self.context.banks_client.process_transaction(transaction).await
+    }
+
+    async fn account<T: AccountDeserialize>(&mut self, key: Pubkey) -> T {
+        let account = self.context.banks_client.get_account(key).await.unwrap().unwrap();
+        T::try_deserialize(&mut account.data.as_slice()).unwrap()
+    }
+
+    async fn initialize(&mut self) {
+        let authority = self.authority.insecure_clone();
+        let (bridge_state, _) = pda(&[b"bridge"]);
+
+        let instruction = Instruction {
+            program_id: zcash_bridge::ID,
+            accounts: zcash_bridge::accounts::Initialize {
+                bridge_state,
+                operator_set: pda(&[b"operator_set"]).0,
+                authority: authority.pubkey(),
+                wrapped_zec_mint: self.mint,
+                asset_config: pda(&[b"asset", self.mint.as_ref()]).0,
+                vault: pda(&[b"vault", self.mint.as_ref()]).0,
+                system_program: system_program::ID,
+                token_program: spl_token::ID,
+                rent: anchor_lang::solana_program::sysvar::rent::ID,
+            }
+            .to_account_metas(None),
+            data: zcash_bridge::instruction::Initialize {
+                config: BridgeConfig {
+                    operator: self.operator.pubkey(),
+                    min_deposit: 0,
+                    max_deposit: u64::MAX,
+                    protocol_fee_bps: 0,
+                    deposit_timeout: 60 * 60,
+                    zcash_network: ZcashNetwork::Testnet,
+                    custody_mode: CustodyMode::Vault,
+                    supply_cap: 0,
+                    custody_address: String::new(),
+                },
+            }
+            .data(),
+        };
+        self.send(instruction, &authority).await.unwrap();
+
+        let instruction = Instruction {
+            program_id: zcash_bridge::ID,
+            accounts: zcash_bridge::accounts::InitializeCommitmentTree {
+                bridge_state,
+                commitment_tree: pda(&[b"commitment_tree"]).0,
+                authority: authority.pubkey(),
+                system_program: system_program::ID,
+            }
+            .to_account_metas(None),
+            data: zcash_bridge::instruction::InitializeCommitmentTree {}.data(),
+        };
+        self.send(instruction, &authority).await.unwrap();
+
+        let instruction = Instruction {
+            program_id: zcash_bridge::ID,
+            accounts: zcash_bridge::accounts::SetWithdrawalVerifyingKey {
+                bridge_state,
+                verifying_key: pda(&[b"verifying_key"]).0,
+                authority: authority.pubkey(),
+                system_program: system_program::ID,
+            }
+            .to_account_metas(None),
+            data: zcash_bridge::instruction::SetWithdrawalVerifyingKey { key: Fixture::load().key }.data(),
+        };
+        self.send(instruction, &authority).await.unwrap();
+    }
+
+    /// Opens the next withdrawal ticket and returns its address
+    async fn initiate_withdrawal(
+        &mut self,
+        amount: u64,
+        commitment: [u8; 32],
+        nullifier: [u8; 32],
+    ) -> std::result::Result<Pubkey, BanksClientError> {
+        let user = self.user.insecure_clone();
+        let (bridge_state, _) = pda(&[b"bridge"]);
+        let nonce = self.account::<zcash_bridge::BridgeState>(bridge_state).await.withdrawal_nonce;
+        let (withdrawal_ticket, _) = pda(&[b"withdrawal", &nonce.to_le_bytes()]);
+
+        let instruction = Instruction {
+            program_id: zcash_bridge::ID,
+            accounts: zcash_bridge::accounts::InitiateWithdrawal {
+                bridge_state,
+                asset_config: pda(&[b"asset", self.mint.as_ref()]).0,
+                withdrawal_ticket,
+                nullifier_record: pda(&[b"nullifier", nullifier.as_ref()]).0,
+                user: user.pubkey(),
+                user_token_account: self.user_token_account,
+                vault: pda(&[b"vault", self.mint.as_ref()]).0,
+                mint: self.mint,
+                token_program: spl_token::ID,
+                system_program: system_program::ID,
+            }
+            .to_account_metas(None),
+            data: zcash_bridge::instruction::InitiateWithdrawal {
+                amount,
+                partial_note_commitment: commitment,
+                partial_note_nullifier: nullifier,
+                encrypted_value: [0u8; 32],
+            }
+            .data(),
+        };
+        self.send(instruction, &user).await.map(|_| withdrawal_ticket)
+    }
+
+    async fn claim_withdrawal(&mut self, withdrawal_ticket: Pubkey) {
+        let operator = self.operator.insecure_clone();
+        let instruction = Instruction {
+            program_id: zcash_bridge::ID,
+            accounts: zcash_bridge::accounts::ClaimWithdrawal {
+                bridge_state: pda(&[b"bridge"]).0,
+                withdrawal_ticket,
+                operator_set: pda(&[b"operator_set"]).0,
+                operator_bond: pda(&[b"operator_bond", operator.pubkey().as_ref()]).0,
+                operator: operator.pubkey(),
+            }
+            .to_account_metas(None),
+            data: zcash_bridge::instruction::ClaimWithdrawal {}.data(),
+        };
+        self.send(instruction, &operator).await.unwrap();
+    }
+
+    async fn process_withdrawal(
+        &mut self,
+        withdrawal_ticket: Pubkey,
+        nullifier: [u8; 32],
+        proof: WithdrawalProof,
+    ) -> std::result::Result<(), BanksClientError> {
+        let operator = self.operator.insecure_clone();
+        let instruction = Instruction {
+            program_id: zcash_bridge::ID,
+            accounts: zcash_bridge::accounts::ProcessWithdrawal {
+                bridge_state: pda(&[b"bridge"]).0,
+                withdrawal_ticket,
+                asset_config: pda(&[b"asset", self.mint.as_ref()]).0,
+                commitment_tree: pda(&[b"commitment_tree"]).0,
+                verifying_key: pda(&[b"verifying_key"]).0,
+                nullifier_record: pda(&[b"nullifier", nullifier.as_ref()]).0,
+                operator_set: pda(&[b"operator_set"]).0,
+                operator_bond: pda(&[b"operator_bond", operator.pubkey().as_ref()]).0,
+                operator: operator.pubkey(),
+                mint: self.mint,
+                vault: pda(&[b"vault", self.mint.as_ref()]).0,
+                token_program: spl_token::ID,
+            }
+            .to_account_metas(None),
+            data: zcash_bridge::instruction::ProcessWithdrawal { proof }.data(),
+        };
+        self.send(instruction, &operator).await
+    }
+}
+
+fn assert_bridge_error<T: std::fmt::Debug>(result: std::result::Result<T, BanksClientError>, expected: BridgeError) {
+    match result.unwrap_err().unwrap() {
+        TransactionError::InstructionError(0, InstructionError::Custom(code)) => {
+            assert_eq!(code, u32::from(expected))
+        }
+        error => panic!("expected {:?}, got {:?}", expected, error),
+    }
+}
+
+#[tokio::test]
+async fn fixture_proof_completes_a_withdrawal() {
+    let fixture = Fixture::load();
+    let mut bridge = Bridge::start().await;
+
+    let ticket = bridge
+        .initiate_withdrawal(fixture.amount, fixture.commitment, fixture.nullifier)
+        .await
+        .unwrap();
+    bridge.claim_withdrawal(ticket).await;
+
+    // A proof for another note is rejected once the threshold is reached
+    let mut forged = fixture.proof;
+    forged[255] ^= 1;
+    let result = bridge
+        .process_withdrawal(
+            ticket,
+            fixture.nullifier,
+            WithdrawalProof {
+                proof_data: forged,
+                commitment: fixture.commitment,
+                nullifier: fixture.nullifier,
+            },
+        )
+        .await;
+    assert_bridge_error(result, BridgeError::InvalidProof);
+
+    bridge
+        .process_withdrawal(
+            ticket,
+            fixture.nullifier,
+            WithdrawalProof {
+                proof_data: fixture.proof,
+                commitment: fixture.commitment,
+                nullifier: fixture.nullifier,
+            },
+        )
+        .await
+        .unwrap();
+
+    let withdrawal: WithdrawalTicket = bridge.account(ticket).await;
+    assert!(withdrawal.status == WithdrawalStatus::Completed);
+    assert_eq!(withdrawal.amount, fixture.amount);
+    let record: NullifierRecord = bridge.account(pda(&[b"nullifier", fixture.nullifier.as_ref()]).0).await;
+    assert!(record.is_spent);
+}
+
+#[tokio::test]
+async fn non_scalar_public_inputs_are_refused_at_initiation() {
+    let fixture = Fixture::load();
+    let mut bridge = Bridge::start().await;
+
+    let result = bridge.initiate_withdrawal(fixture.amount, fixture.commitment, [0xff; 32]).await;
+    assert_bridge_error(result, BridgeError::InvalidPublicInput);
+    let result = bridge.initiate_withdrawal(fixture.amount, [0xff; 32], fixture.nullifier).await;
+    assert_bridge_error(result, BridgeError::InvalidPublicInput);
+}